@@ -4,5 +4,46 @@ fn main() {
         println!("cargo:rerun-if-env-changed=CARGO_HUSKY");
     }
 
+    // 构建信息，供 get_app_info 使用
+    let git_commit = std::process::Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_default();
+    println!("cargo:rustc-env=CICONIA_GIT_COMMIT={}", git_commit);
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    // HEAD 指向分支时提交只改动分支的 ref 文件 (或 gc 后的 packed-refs)；
+    // 不存在的路径会让 cargo 每次都重新运行构建脚本，只登记存在的文件
+    let head_ref = std::fs::read_to_string("../.git/HEAD")
+        .ok()
+        .and_then(|head| head.trim().strip_prefix("ref: ").map(str::to_string));
+    for path in head_ref
+        .map(|head_ref| format!("../.git/{}", head_ref))
+        .into_iter()
+        .chain(["../.git/packed-refs".to_string()])
+    {
+        if std::path::Path::new(&path).exists() {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+
+    let russh_version = std::fs::read_to_string("Cargo.lock")
+        .ok()
+        .and_then(|lock| locked_version(&lock, "russh"))
+        .unwrap_or_default();
+    println!("cargo:rustc-env=CICONIA_RUSSH_VERSION={}", russh_version);
+    println!("cargo:rerun-if-changed=Cargo.lock");
+
     tauri_build::build()
 }
+
+/// Cargo.lock 中 `name = "<name>"` 下一行的版本号
+fn locked_version(lock: &str, name: &str) -> Option<String> {
+    let mut lines = lock.lines();
+    let needle = format!("name = \"{}\"", name);
+    lines.find(|line| line.trim() == needle)?;
+    let version = lines.next()?.trim().strip_prefix("version = ")?;
+    Some(version.trim_matches('"').to_string())
+}
@@ -0,0 +1,2 @@
+-- Optional OpenSSH ControlMaster socket used to request forwards without re-authenticating
+ALTER TABLE tunnels_v2 ADD COLUMN control_path TEXT;
@@ -0,0 +1,2 @@
+-- Container runtime used to inspect containers in docker mode ("docker" | "podman" | "auto")
+ALTER TABLE tunnels_v2 ADD COLUMN container_runtime TEXT;
@@ -0,0 +1,2 @@
+-- PROXY protocol header written on each forwarded connection ("v1" | "v2")
+ALTER TABLE tunnels_v2 ADD COLUMN proxy_protocol TEXT;
@@ -0,0 +1,2 @@
+-- Restrict key auth to the configured key only (like OpenSSH IdentitiesOnly)
+ALTER TABLE tunnels_v2 ADD COLUMN identities_only BOOLEAN NOT NULL DEFAULT 1;
@@ -0,0 +1,2 @@
+-- Per-tunnel override of the global auto_reconnect setting, NULL follows the setting
+ALTER TABLE tunnels_v2 ADD COLUMN auto_reconnect BOOLEAN;
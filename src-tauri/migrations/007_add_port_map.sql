@@ -0,0 +1,3 @@
+-- Extra local port ranges forwarded over the same session, stored as JSON:
+-- [{"local_start": 21000, "local_end": 21010, "remote_base": 21000}]
+ALTER TABLE tunnels_v2 ADD COLUMN port_map TEXT;
@@ -0,0 +1,2 @@
+-- Read buffer size in bytes for forwarded connections, NULL uses the default (64KB)
+ALTER TABLE tunnels_v2 ADD COLUMN io_buffer_size INTEGER;
@@ -0,0 +1,2 @@
+-- Pick the next free local port when the configured one is taken
+ALTER TABLE tunnels_v2 ADD COLUMN auto_remap_port BOOLEAN NOT NULL DEFAULT 0;
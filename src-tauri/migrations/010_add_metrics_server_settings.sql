@@ -0,0 +1,4 @@
+-- Local WebSocket server streaming tunnel metrics to external dashboards
+ALTER TABLE app_settings ADD COLUMN metrics_ws_enabled BOOLEAN NOT NULL DEFAULT 0;
+ALTER TABLE app_settings ADD COLUMN metrics_ws_addr TEXT NOT NULL DEFAULT '127.0.0.1:9477';
+ALTER TABLE app_settings ADD COLUMN metrics_ws_allow_remote BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- Seconds a forwarded connection may stay idle before keepalive probes are sent, NULL disables
+ALTER TABLE tunnels_v2 ADD COLUMN keepalive_idle_secs INTEGER;
@@ -0,0 +1,12 @@
+-- Per-tunnel traffic/latency samples, one row per tunnel per sampling interval
+CREATE TABLE IF NOT EXISTS tunnel_metrics_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    tunnel_id TEXT NOT NULL,
+    timestamp INTEGER NOT NULL, -- unix millis
+    tx_bytes INTEGER NOT NULL,  -- bytes sent during the interval
+    rx_bytes INTEGER NOT NULL,  -- bytes received during the interval
+    latency_ms INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS idx_metrics_history_tunnel_time
+    ON tunnel_metrics_history (tunnel_id, timestamp);
@@ -0,0 +1,2 @@
+-- How remote commands are run on the SSH host: NULL uses the login shell, 'none' or e.g. 'sh -c'
+ALTER TABLE tunnels_v2 ADD COLUMN remote_shell TEXT;
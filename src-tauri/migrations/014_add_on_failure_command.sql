@@ -0,0 +1,2 @@
+-- Local command run when the tunnel enters the error state, NULL disables
+ALTER TABLE tunnels_v2 ADD COLUMN on_failure_command TEXT;
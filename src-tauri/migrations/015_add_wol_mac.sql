@@ -0,0 +1,2 @@
+-- MAC address woken through the SSH host before forwarding, NULL disables
+ALTER TABLE tunnels_v2 ADD COLUMN wol_mac TEXT;
@@ -0,0 +1,2 @@
+-- Tray left-click action: 'show' | 'toggle' | 'menu' | 'none'
+ALTER TABLE app_settings ADD COLUMN tray_left_click TEXT NOT NULL DEFAULT 'show';
@@ -0,0 +1,10 @@
+-- Notable tunnel events (started, stopped, failed, reconnected, high_traffic) for the activity feed
+CREATE TABLE IF NOT EXISTS tunnel_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    tunnel_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    detail TEXT,
+    timestamp INTEGER NOT NULL -- unix millis
+);
+
+CREATE INDEX IF NOT EXISTS idx_tunnel_events_time ON tunnel_events (timestamp);
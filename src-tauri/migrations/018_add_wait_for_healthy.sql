@@ -0,0 +1,2 @@
+-- Docker mode: wait for the container healthcheck to pass before forwarding
+ALTER TABLE tunnels_v2 ADD COLUMN wait_for_healthy BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- Whether the tunnel was running when the app last closed, restored on next launch
+ALTER TABLE tunnels_v2 ADD COLUMN was_running BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- Probe the target through the SSH host on start and fail instead of binding when unreachable
+ALTER TABLE tunnels_v2 ADD COLUMN fail_fast BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- Local address the outgoing SSH connection is bound to, for multi-homed machines
+ALTER TABLE tunnels_v2 ADD COLUMN source_addr TEXT;
@@ -0,0 +1,3 @@
+-- Per-tunnel cap on concurrent forwarded connections, extra connections wait in a queue
+ALTER TABLE tunnels_v2 ADD COLUMN max_connections INTEGER;
+ALTER TABLE tunnels_v2 ADD COLUMN queue_timeout_secs INTEGER;
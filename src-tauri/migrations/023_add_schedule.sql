@@ -0,0 +1,2 @@
+-- JSON encoded time window the tunnel is allowed to run in
+ALTER TABLE tunnels_v2 ADD COLUMN schedule TEXT;
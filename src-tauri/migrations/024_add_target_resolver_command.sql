@@ -0,0 +1,2 @@
+-- Remote command printing host:port, run on every connect to pick the forward target
+ALTER TABLE tunnels_v2 ADD COLUMN target_resolver_command TEXT;
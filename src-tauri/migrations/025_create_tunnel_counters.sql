@@ -0,0 +1,6 @@
+-- Lifetime connection counters per tunnel, flushed with the metrics sampler
+CREATE TABLE IF NOT EXISTS tunnel_counters (
+    tunnel_id TEXT PRIMARY KEY NOT NULL,
+    connections INTEGER NOT NULL DEFAULT 0,       -- connections accepted
+    connection_errors INTEGER NOT NULL DEFAULT 0  -- connections rejected or failed
+);
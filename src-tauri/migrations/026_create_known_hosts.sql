@@ -0,0 +1,10 @@
+-- Pinned SSH host keys, importable from / exportable to OpenSSH known_hosts
+-- host_pattern is kept verbatim: "host", "[host]:port", comma lists or hashed "|1|salt|hash"
+CREATE TABLE IF NOT EXISTS known_hosts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    host_pattern TEXT NOT NULL,
+    key_type TEXT NOT NULL,
+    key_base64 TEXT NOT NULL,
+    added_at INTEGER NOT NULL, -- unix millis
+    UNIQUE (host_pattern, key_type, key_base64)
+);
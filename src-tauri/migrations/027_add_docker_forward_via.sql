@@ -0,0 +1,2 @@
+-- Docker mode: forward to the container IP or to the port published on the host
+ALTER TABLE tunnels_v2 ADD COLUMN docker_forward_via TEXT;
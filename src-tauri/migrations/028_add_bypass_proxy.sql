@@ -0,0 +1,2 @@
+-- Connect directly even when a system proxy is configured
+ALTER TABLE tunnels_v2 ADD COLUMN bypass_proxy BOOLEAN NOT NULL DEFAULT 0;
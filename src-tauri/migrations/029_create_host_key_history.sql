@@ -0,0 +1,13 @@
+-- Host key fingerprints seen per SSH host, to audit key changes over time
+CREATE TABLE IF NOT EXISTS host_key_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    host TEXT NOT NULL,
+    port INTEGER NOT NULL,
+    key_type TEXT NOT NULL,
+    fingerprint TEXT NOT NULL, -- "SHA256:..."
+    first_seen INTEGER NOT NULL, -- unix millis
+    last_seen INTEGER NOT NULL, -- unix millis
+    UNIQUE (host, port, fingerprint)
+);
+
+CREATE INDEX IF NOT EXISTS idx_host_key_history_host ON host_key_history (host, port);
@@ -0,0 +1,4 @@
+-- Periodic target reachability check while running: interval (NULL disables) and
+-- what to do when the target is unreachable ("mark" | "reconnect")
+ALTER TABLE tunnels_v2 ADD COLUMN target_check_interval_secs INTEGER;
+ALTER TABLE tunnels_v2 ADD COLUMN target_unreachable_action TEXT;
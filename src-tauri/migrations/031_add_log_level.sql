@@ -0,0 +1,2 @@
+-- Persisted log level ('error' | 'warn' | 'info' | 'debug' | 'trace'), NULL uses the build default
+ALTER TABLE app_settings ADD COLUMN log_level TEXT;
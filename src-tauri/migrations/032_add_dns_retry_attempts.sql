@@ -0,0 +1,2 @@
+-- Attempts for resolving the SSH host before a connect fails, separate from reconnects
+ALTER TABLE app_settings ADD COLUMN dns_retry_attempts INTEGER NOT NULL DEFAULT 3;
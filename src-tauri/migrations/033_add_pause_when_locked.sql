@@ -0,0 +1,2 @@
+-- Refuse new forwarded connections while the screen is locked
+ALTER TABLE tunnels_v2 ADD COLUMN pause_when_locked BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- JSON array of read-only commands allowed for run_diagnostic_command, NULL uses the built-in list
+ALTER TABLE app_settings ADD COLUMN diagnostic_commands TEXT;
@@ -0,0 +1,2 @@
+-- Seconds SSH keepalives must keep failing before a session is marked failed, 0 is immediate
+ALTER TABLE app_settings ADD COLUMN failure_grace_secs INTEGER NOT NULL DEFAULT 0;
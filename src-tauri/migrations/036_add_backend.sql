@@ -0,0 +1,2 @@
+-- SSH implementation per tunnel: NULL/'auto', 'russh' or 'system_ssh'
+ALTER TABLE tunnels_v2 ADD COLUMN backend TEXT;
@@ -0,0 +1,2 @@
+-- Command run on the SSH host by run_check_command, without starting the tunnel
+ALTER TABLE tunnels_v2 ADD COLUMN check_command TEXT;
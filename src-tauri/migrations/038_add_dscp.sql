@@ -0,0 +1,2 @@
+-- DSCP value (0-63) marked on the SSH connection and forwarded sockets
+ALTER TABLE tunnels_v2 ADD COLUMN dscp INTEGER;
@@ -0,0 +1,5 @@
+-- What the window close button does: "minimize" to the tray, "quit", or "ask" each time
+ALTER TABLE app_settings ADD COLUMN close_button_action TEXT NOT NULL DEFAULT 'minimize';
+
+-- Carry over the previous minimize-to-tray choice
+UPDATE app_settings SET close_button_action = 'quit' WHERE minimize_to_tray_on_close = 0;
@@ -0,0 +1,2 @@
+-- Passphrase of an encrypted private key, used only with key auth
+ALTER TABLE tunnels_v2 ADD COLUMN ssh_key_passphrase TEXT;
@@ -0,0 +1,2 @@
+-- Try the other saved credentials when the configured auth method is rejected (opt-in)
+ALTER TABLE tunnels_v2 ADD COLUMN auth_fallback BOOLEAN NOT NULL DEFAULT 0;
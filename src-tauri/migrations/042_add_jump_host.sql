@@ -0,0 +1,6 @@
+-- Bastion host the SSH connection goes through (ssh -J), one hop only
+ALTER TABLE tunnels_v2 ADD COLUMN jump_host TEXT;
+ALTER TABLE tunnels_v2 ADD COLUMN jump_port INTEGER;
+ALTER TABLE tunnels_v2 ADD COLUMN jump_user TEXT;
+-- "password" | "key" | "agent", NULL uses the tunnel's auth type; credentials are the tunnel's
+ALTER TABLE tunnels_v2 ADD COLUMN jump_auth TEXT;
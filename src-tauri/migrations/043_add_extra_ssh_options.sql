@@ -0,0 +1,2 @@
+-- JSON encoded list of "Key=Value" options passed to the system ssh as -o arguments
+ALTER TABLE tunnels_v2 ADD COLUMN extra_ssh_options TEXT;
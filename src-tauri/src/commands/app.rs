@@ -0,0 +1,70 @@
+use crate::state::AppState;
+use log::info;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// 运行中的版本与构建信息，用于提交问题
+#[derive(Debug, Serialize)]
+pub struct AppInfo {
+    pub version: String,
+    /// 构建时的 git commit，不在 git 仓库中构建时为 None
+    pub git_commit: Option<&'static str>,
+    pub tauri_version: &'static str,
+    pub russh_version: Option<&'static str>,
+    /// 当前只有 russh 实现
+    pub ssh_backend: &'static str,
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub debug_build: bool,
+}
+
+#[tauri::command]
+pub fn get_app_info(app: AppHandle) -> AppInfo {
+    let non_empty = |value: &'static str| (!value.is_empty()).then_some(value);
+    AppInfo {
+        version: app.package_info().version.to_string(),
+        git_commit: non_empty(env!("CICONIA_GIT_COMMIT")),
+        tauri_version: tauri::VERSION,
+        russh_version: non_empty(env!("CICONIA_RUSSH_VERSION")),
+        ssh_backend: "russh",
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        debug_build: cfg!(debug_assertions),
+    }
+}
+
+/// 有隧道正在传输数据时显示窗口并发出 `quit-requested` 请前端确认，否则直接退出
+pub fn request_quit(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let service = app.state::<AppState>().tunnel_service.clone();
+        let busy = service.busy_tunnels().await;
+        let window = app.get_webview_window("main");
+        match window {
+            Some(window) if !busy.is_empty() => {
+                info!("Quit deferred, {} tunnel(s) carrying traffic", busy.len());
+                let _ = window.show();
+                let _ = window.set_focus();
+                let _ = window.emit("quit-requested", busy);
+            }
+            _ => app.exit(0),
+        }
+    });
+}
+
+/// 关闭按钮设为 ask 时前端的选择：退出 (有传输时仍会确认) 或隐藏到托盘
+#[tauri::command]
+pub fn answer_close_request(app: AppHandle, quit: bool) {
+    if quit {
+        request_quit(&app);
+    } else if let Some(window) = app.get_webview_window("main") {
+        let _ = window.hide();
+    }
+}
+
+/// 用户确认后退出，正在进行的传输会被中断
+#[tauri::command]
+pub fn quit_app(app: AppHandle) {
+    info!("Quit confirmed");
+    app.exit(0);
+}
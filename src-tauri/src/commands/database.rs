@@ -0,0 +1,44 @@
+use crate::database::DB;
+use crate::error::{CommandError, CommandResult};
+use crate::state::AppState;
+use log::{error, info};
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+#[tauri::command]
+pub async fn backup_database(dest_path: String) -> CommandResult<u64> {
+    info!("Backing up database to {}", dest_path);
+    DB::backup(&PathBuf::from(dest_path)).await.map_err(|e| {
+        error!("Failed to back up database: {:#}", e);
+        CommandError::from(e)
+    })
+}
+
+#[tauri::command]
+pub async fn repair_database_schema() -> CommandResult<Vec<String>> {
+    info!("Checking database schema");
+    DB::repair_schema().await.map_err(|e| {
+        error!("Failed to repair database schema: {:#}", e);
+        CommandError::from(e)
+    })
+}
+
+/// 检查设置行，补齐缺失或为 NULL 的列后重新加载，返回修复的列名
+#[tauri::command]
+pub async fn repair_settings(app: AppHandle) -> CommandResult<Vec<String>> {
+    info!("Checking settings row");
+    let settings = app.state::<AppState>().settings.clone();
+    settings.repair().await.map_err(|e| {
+        error!("Failed to repair settings: {:#}", e);
+        CommandError::from(e)
+    })
+}
+
+#[tauri::command]
+pub async fn compact_database() -> CommandResult<u64> {
+    info!("Compacting database");
+    DB::compact().await.map_err(|e| {
+        error!("Failed to compact database: {:#}", e);
+        CommandError::from(e)
+    })
+}
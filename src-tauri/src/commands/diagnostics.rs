@@ -0,0 +1,227 @@
+use crate::commands::docker::FetchContainerParams;
+use crate::database::models::HostKeyRecord;
+use crate::database::DB;
+use crate::error::{CommandError, CommandResult};
+use crate::server::dns;
+use crate::server::link_quality::{self, LinkQuality, DEFAULT_SAMPLES};
+use crate::server::model::SshConnectConfig;
+use crate::server::mtu::{self, PathMtu};
+use crate::server::overhead::OverheadReport;
+use crate::server::port_owner::{self, PortOwner};
+use crate::server::remote_cmd::{self, TimeSkew};
+use crate::server::remote_tail::{self, TailEvent};
+use crate::server::ssh::{ExecOutput, Ssh};
+use crate::server::trace;
+use crate::service::diagnostics::ConnectionTest;
+use crate::state::AppState;
+use log::debug;
+use serde::Serialize;
+use std::net::IpAddr;
+use tauri::{AppHandle, Emitter, Manager};
+
+#[tauri::command]
+pub async fn capture_trace(params: FetchContainerParams) -> CommandResult<String> {
+    debug!(
+        "Capturing connection trace for {}:{}",
+        params.host, params.port
+    );
+    let ssh_connect_config = SshConnectConfig::try_from(&params).map_err(CommandError::from)?;
+    trace::capture_trace(ssh_connect_config)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// 估算路径 MTU，未指定 target_host 时探测 SSH 主机回到本机的路径
+#[tauri::command]
+pub async fn probe_path_mtu(
+    params: FetchContainerParams,
+    target_host: Option<String>,
+) -> CommandResult<PathMtu> {
+    debug!("Probing path MTU via {}:{}", params.host, params.port);
+    let ssh_connect_config = SshConnectConfig::try_from(&params).map_err(CommandError::from)?;
+    mtu::probe_path_mtu(ssh_connect_config, target_host)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// SSH 主机时钟与本地的偏差 (秒)
+#[tauri::command]
+pub async fn check_time_skew(params: FetchContainerParams) -> CommandResult<TimeSkew> {
+    debug!("Checking clock skew of {}:{}", params.host, params.port);
+    let ssh_connect_config = SshConnectConfig::try_from(&params).map_err(CommandError::from)?;
+    let skew = remote_cmd::check_time_skew(&ssh_connect_config)
+        .await
+        .map_err(CommandError::from)?;
+    if skew.exceeds_threshold {
+        log::warn!(
+            "Clock of {} is off by {}s from the local clock",
+            params.host,
+            skew.offset_secs
+        );
+    }
+    Ok(skew)
+}
+
+/// 服务器允许的认证方式 (publickey、password、keyboard-interactive 等)
+#[tauri::command]
+pub async fn get_auth_methods(host: String, port: u16, user: String) -> CommandResult<Vec<String>> {
+    debug!("Querying auth methods of {}:{}", host, port);
+    Ssh::probe_auth_methods(&host, port, &user)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// 多次 TCP 建连测量到 SSH 主机的延迟、抖动和失败率，区分高延迟和不稳定的链路
+#[tauri::command]
+pub async fn measure_link_quality(
+    host: String,
+    port: u16,
+    samples: Option<u32>,
+) -> CommandResult<LinkQuality> {
+    debug!("Measuring link quality to {}:{}", host, port);
+    link_quality::measure_link_quality(host, port, samples.unwrap_or(DEFAULT_SAMPLES))
+        .await
+        .map_err(CommandError::from)
+}
+
+/// 对比经隧道和直连到目标的延迟并测量会话吞吐，可通过 cancel_discovery 取消
+///
+/// io_buffer_size 为 None 时使用隧道保存的读缓冲区大小。
+#[tauri::command]
+pub async fn measure_tunnel_overhead(
+    app: AppHandle,
+    id: String,
+    samples: Option<u32>,
+    io_buffer_size: Option<u32>,
+) -> CommandResult<OverheadReport> {
+    debug!("Measuring the overhead of tunnel {}", id);
+    let service = app.state::<AppState>().tunnel_service.clone();
+    service
+        .measure_tunnel_overhead(id, samples, io_buffer_size)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// 解析 SSH 主机名的全部地址，用于发现过期的 DNS 记录
+#[tauri::command]
+pub async fn resolve_ssh_host(host: String) -> CommandResult<Vec<IpAddr>> {
+    debug!("Resolving {}", host);
+    dns::resolve_all(&host).await.map_err(CommandError::from)
+}
+
+/// 在 SSH 主机上执行设置中白名单内的只读命令，返回原始输出和退出码
+#[tauri::command]
+pub async fn run_diagnostic_command(
+    app: AppHandle,
+    params: FetchContainerParams,
+    command: String,
+) -> CommandResult<ExecOutput> {
+    let allow_list = app
+        .state::<AppState>()
+        .settings
+        .get_settings()
+        .diagnostic_commands;
+    let ssh_connect_config = SshConnectConfig::try_from(&params).map_err(CommandError::from)?;
+    remote_cmd::run_diagnostic_command(ssh_connect_config, &command, &allow_list)
+        .await
+        .map_err(CommandError::from)
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct TailPayload {
+    id: u64,
+    #[serde(flatten)]
+    event: TailEvent,
+}
+
+/// 在 SSH 主机上执行 `tail -F` 跟踪文件，输出以 `remote-tail` 事件推送，返回用于停止的 id
+#[tauri::command]
+pub async fn tail_remote_file(
+    app: AppHandle,
+    params: FetchContainerParams,
+    path: String,
+    lines: u32,
+) -> CommandResult<u64> {
+    let ssh_connect_config = SshConnectConfig::try_from(&params).map_err(CommandError::from)?;
+    remote_tail::start(ssh_connect_config, path, lines, move |id, event| {
+        let _ = app.emit("remote-tail", &TailPayload { id, event });
+    })
+    .map_err(CommandError::from)
+}
+
+/// 停止 `tail_remote_file` 启动的跟踪
+#[tauri::command]
+pub async fn stop_remote_tail(id: u64) -> bool {
+    remote_tail::stop(id)
+}
+
+/// 主机出现过的公钥指纹及时间，用于审计主机重装或中间人
+#[tauri::command]
+pub async fn get_host_key_history(host: String, port: u16) -> CommandResult<Vec<HostKeyRecord>> {
+    DB::load_host_key_history(&host, port)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// 执行隧道配置的 check_command 并返回输出，不启动隧道；timeout_secs 默认 30 秒
+#[tauri::command]
+pub async fn run_check_command(
+    app: AppHandle,
+    id: String,
+    timeout_secs: Option<u64>,
+) -> CommandResult<ExecOutput> {
+    let service = app.state::<AppState>().tunnel_service.clone();
+    service
+        .run_check_command(id, timeout_secs)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// 依次测试所有隧道能否建立 SSH 连接
+#[tauri::command]
+pub async fn test_all_tunnels(app: AppHandle) -> CommandResult<Vec<ConnectionTest>> {
+    let service = app.state::<AppState>().tunnel_service.clone();
+    service.test_all_tunnels().await.map_err(CommandError::from)
+}
+
+/// 生成脱敏的诊断包：设置、隧道配置、最近事件与日志、连接测试结果
+#[tauri::command]
+pub async fn create_diagnostic_bundle(app: AppHandle, dest_path: String) -> CommandResult<u64> {
+    let version = app.package_info().version.to_string();
+    let service = app.state::<AppState>().tunnel_service.clone();
+    service
+        .create_diagnostic_bundle(dest_path, version)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// 把所有隧道当前的指标写入 JSON 文件，便于排查前后对比
+#[tauri::command]
+pub async fn snapshot_metrics(app: AppHandle, dest_path: String) -> CommandResult<u64> {
+    let version = app.package_info().version.to_string();
+    let service = app.state::<AppState>().tunnel_service.clone();
+    service
+        .snapshot_metrics(dest_path, version)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// 指定私钥是否已加入服务器的 authorized_keys，网络错误单独报错
+#[tauri::command]
+pub async fn is_key_authorized(
+    host: String,
+    port: u16,
+    user: String,
+    key_path: String,
+) -> CommandResult<bool> {
+    debug!("Checking whether a key is authorized on {}:{}", host, port);
+    Ssh::is_key_authorized(&host, port, &user, &key_path)
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn who_has_port(port: u16) -> CommandResult<Option<PortOwner>> {
+    debug!("Looking up the owner of local port {}", port);
+    Ok(port_owner::who_has_port(port).await)
+}
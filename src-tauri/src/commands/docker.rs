@@ -1,7 +1,14 @@
 use crate::error::{CommandError, CommandResult};
-use crate::server::model::{SshConnectConfig, TunnelAuth};
-use crate::server::remote_cmd::get_container_infos;
-use anyhow::anyhow;
+use crate::server::discovery;
+use crate::server::model::{parse_source_addr, JumpHost, SshConnectConfig, TunnelAuth};
+use crate::server::proxy;
+use crate::server::remote_cmd::{
+    check_container_port as check_port, check_target_reachable as check_reachable,
+    get_container_infos, get_container_ports, get_listening_ports, ContainerPort,
+    ContainerPortStatus, ContainerRuntime, ListeningPort, RemoteShell, TargetReachability,
+};
+use anyhow::{anyhow, Context};
+use log::info;
 use serde::{Deserialize, Serialize};
 use tauri::command;
 
@@ -25,36 +32,81 @@ pub struct FetchContainerParams {
     pub host: String,
     pub port: u16,
     pub username: String,
-    pub auth_type: String, // "key" | "password"
+    pub auth_type: String, // "key" | "password" | "agent"
     pub private_key_path: Option<String>,
+    pub key_passphrase: Option<String>,
     pub password: Option<String>,
     pub keyword: Option<String>,
+    pub container_runtime: Option<String>, // "docker" | "podman" | "auto"
+    pub identities_only: Option<bool>,
+    pub remote_shell: Option<String>, // None | "none" | "sh -c"
+    pub source_addr: Option<String>,
+    pub bypass_proxy: Option<bool>,
+    // 跳板机，与隧道配置相同，使用同一组凭据
+    pub jump_host: Option<String>,
+    pub jump_port: Option<u16>,
+    pub jump_user: Option<String>,
+    pub jump_auth: Option<String>,
 }
 
-impl TryFrom<&FetchContainerParams> for TunnelAuth {
-    type Error = anyhow::Error;
-
-    fn try_from(value: &FetchContainerParams) -> anyhow::Result<Self> {
-        let auth = match value.auth_type.as_str() {
+impl FetchContainerParams {
+    fn auth(&self, auth_type: &str) -> anyhow::Result<TunnelAuth> {
+        let auth = match auth_type {
             "password" => {
-                let password = value
+                let password = self
                     .password
                     .as_ref()
                     .ok_or_else(|| anyhow!("Password not provided for password authentication"))?;
                 TunnelAuth::Password(password.clone())
             }
             "key" => {
-                let key_path = value
+                let key_path = self
                     .private_key_path
                     .as_ref()
                     .ok_or_else(|| anyhow!("Key path not provided for key authentication"))?;
-                TunnelAuth::Key(key_path.clone())
+                let passphrase = self.key_passphrase.clone().filter(|p| !p.is_empty());
+                TunnelAuth::Key(key_path.clone(), passphrase)
             }
+            "agent" => TunnelAuth::Agent,
             other => return Err(anyhow!("Invalid auth type: {}", other)),
         };
 
         Ok(auth)
     }
+
+    fn jump(&self) -> anyhow::Result<Option<JumpHost>> {
+        let Some(host) = self
+            .jump_host
+            .as_deref()
+            .map(str::trim)
+            .filter(|host| !host.is_empty())
+        else {
+            return Ok(None);
+        };
+        let auth_type = self
+            .jump_auth
+            .as_deref()
+            .filter(|auth| !auth.is_empty())
+            .unwrap_or(&self.auth_type);
+        Ok(Some(JumpHost::new(
+            host,
+            self.jump_port.unwrap_or(22),
+            self.jump_user
+                .clone()
+                .filter(|user| !user.trim().is_empty())
+                .unwrap_or_else(|| self.username.clone()),
+            self.auth(auth_type).context("Invalid jump host auth")?,
+            self.bypass_proxy.unwrap_or(false),
+        )))
+    }
+}
+
+impl TryFrom<&FetchContainerParams> for TunnelAuth {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &FetchContainerParams) -> anyhow::Result<Self> {
+        value.auth(&value.auth_type)
+    }
 }
 
 impl TryFrom<&FetchContainerParams> for SshConnectConfig {
@@ -68,6 +120,17 @@ impl TryFrom<&FetchContainerParams> for SshConnectConfig {
             ssh_port: value.port,
             ssh_user: value.username.clone(),
             auth,
+            fallback_auth: Vec::new(),
+            jump: value.jump()?,
+            identities_only: value.identities_only.unwrap_or(true),
+            remote_shell: RemoteShell::parse(value.remote_shell.as_deref())?,
+            source_addr: parse_source_addr(value.source_addr.as_deref())?,
+            dscp: None,
+            proxy: if value.bypass_proxy.unwrap_or(false) {
+                None
+            } else {
+                proxy::system_proxy(&value.host)
+            },
         })
     }
 }
@@ -75,7 +138,71 @@ impl TryFrom<&FetchContainerParams> for SshConnectConfig {
 #[command]
 pub async fn fetch_containers(params: FetchContainerParams) -> CommandResult<Vec<DockerContainer>> {
     let ssh_connect_config = SshConnectConfig::try_from(&params).map_err(CommandError::from)?;
-    let containers = get_container_infos(&ssh_connect_config, params.keyword).await?;
+    let runtime = ContainerRuntime::parse(params.container_runtime.as_deref())?;
+    let containers = get_container_infos(&ssh_connect_config, params.keyword, runtime).await?;
 
     Ok(containers.iter().map(DockerContainer::from).collect())
 }
+
+/// 容器端口的结构化列表 (ip, host_port, container_port, protocol)，用于端口下拉框
+#[command]
+pub async fn get_container_ports_structured(
+    params: FetchContainerParams,
+    id: String,
+) -> CommandResult<Vec<ContainerPort>> {
+    let ssh_connect_config = SshConnectConfig::try_from(&params).map_err(CommandError::from)?;
+    let runtime = ContainerRuntime::parse(params.container_runtime.as_deref())?;
+    let ports = get_container_ports(&ssh_connect_config, &id, runtime).await?;
+
+    Ok(ports)
+}
+
+/// 列出远程主机上正在监听的端口，供选择 target_port
+#[command]
+pub async fn fetch_remote_ports(params: FetchContainerParams) -> CommandResult<Vec<ListeningPort>> {
+    let ssh_connect_config = SshConnectConfig::try_from(&params).map_err(CommandError::from)?;
+    let ports = get_listening_ports(&ssh_connect_config).await?;
+
+    Ok(ports)
+}
+
+/// 在 SSH 主机上检查 target_host:target_port 是否可达，用于区分 SSH 问题和目标故障
+#[command]
+pub async fn check_target_reachable(
+    params: FetchContainerParams,
+    target_host: String,
+    target_port: u16,
+) -> CommandResult<TargetReachability> {
+    let ssh_connect_config = SshConnectConfig::try_from(&params).map_err(CommandError::from)?;
+    let reachability = check_reachable(&ssh_connect_config, target_host, target_port).await?;
+
+    Ok(reachability)
+}
+
+/// 检查容器端口是否发布到宿主机，决定转发到容器 IP 还是宿主机端口
+#[command]
+pub async fn check_container_port(
+    params: FetchContainerParams,
+    container_name: String,
+    container_port: u16,
+) -> CommandResult<ContainerPortStatus> {
+    let ssh_connect_config = SshConnectConfig::try_from(&params).map_err(CommandError::from)?;
+    let runtime = ContainerRuntime::parse(params.container_runtime.as_deref())?;
+    let status = check_port(&ssh_connect_config, container_name, container_port, runtime).await?;
+
+    Ok(status)
+}
+
+/// 取消所有进行中的容器/端口发现并关闭缓存的会话，关闭创建隧道对话框时调用
+#[command]
+pub async fn cancel_discovery() -> usize {
+    let cancelled = discovery::cancel_all();
+    if cancelled > 0 {
+        info!("Cancelled {} in-flight discovery operation(s)", cancelled);
+    }
+    let closed = discovery::close_warm_sessions().await;
+    if closed > 0 {
+        info!("Closed {} warm discovery session(s)", closed);
+    }
+    cancelled
+}
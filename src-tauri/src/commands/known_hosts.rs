@@ -0,0 +1,107 @@
+use crate::database::DB;
+use crate::error::{CommandError, CommandResult};
+use crate::server::known_hosts;
+use anyhow::Context;
+use log::{error, info};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use tokio::io::AsyncWriteExt;
+
+fn known_hosts_path(path: Option<String>) -> anyhow::Result<PathBuf> {
+    match path.filter(|p| !p.trim().is_empty()) {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => known_hosts::default_path(),
+    }
+}
+
+/// 从 OpenSSH known_hosts 导入主机公钥，默认 `~/.ssh/known_hosts`，返回新增条数
+#[tauri::command]
+pub async fn import_known_hosts(path: Option<String>) -> CommandResult<u64> {
+    let result = async {
+        let path = known_hosts_path(path)?;
+        info!("Importing known hosts from {}", path.display());
+        let content = tokio::fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let parsed = known_hosts::parse(&content);
+        if parsed.skipped > 0 {
+            info!(
+                "Skipped {} marker or unparseable known_hosts lines",
+                parsed.skipped
+            );
+        }
+        DB::insert_known_hosts(&parsed.entries).await
+    }
+    .await;
+    result.map_err(|e| {
+        error!("Failed to import known hosts: {:#}", e);
+        CommandError::from(e)
+    })
+}
+
+/// 把固定的主机公钥追加到 OpenSSH known_hosts，已有的行不重复写入，返回写入条数
+#[tauri::command]
+pub async fn export_known_hosts(dest_path: Option<String>) -> CommandResult<u64> {
+    let result = async {
+        let path = known_hosts_path(dest_path)?;
+        info!("Exporting known hosts to {}", path.display());
+        let existing = match tokio::fs::read_to_string(&path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        let present: HashSet<String> = known_hosts::parse(&existing)
+            .entries
+            .iter()
+            .map(known_hosts::format_line)
+            .collect();
+
+        let mut lines = String::new();
+        let mut written = 0;
+        for host in DB::load_known_hosts().await? {
+            let line = known_hosts::format_line(&host);
+            if present.contains(&line) {
+                continue;
+            }
+            lines.push_str(&line);
+            lines.push('\n');
+            written += 1;
+        }
+        if written == 0 {
+            return Ok(0);
+        }
+        if !existing.is_empty() && !existing.ends_with('\n') {
+            lines.insert(0, '\n');
+        }
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        file.write_all(lines.as_bytes()).await?;
+        file.flush().await?;
+        anyhow::Ok(written)
+    }
+    .await;
+    result.map_err(|e| {
+        error!("Failed to export known hosts: {:#}", e);
+        CommandError::from(e)
+    })
+}
+
+/// 回应 `host-key-prompt` 事件，返回该主机是否还有等待中的连接
+#[tauri::command]
+pub async fn answer_host_key(host: String, port: u16, approved: bool) -> CommandResult<bool> {
+    info!(
+        "Host key for {}:{} {}",
+        host,
+        port,
+        if approved { "approved" } else { "rejected" }
+    );
+    Ok(known_hosts::answer(&host, port, approved))
+}
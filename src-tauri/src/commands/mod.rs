@@ -1,3 +1,7 @@
+pub mod app;
+pub mod database;
+pub mod diagnostics;
 pub mod docker;
+pub mod known_hosts;
 pub mod settings;
 pub mod tunnel;
@@ -1,8 +1,19 @@
-use crate::error::{CommandError, CommandResult};
+use crate::error::{AppError, CommandError, CommandResult};
+use crate::logging;
+use crate::server::dns;
+use crate::server::known_hosts;
+use crate::server::maintenance::{self, MaintenanceStatus};
+use crate::server::metrics_ws::MetricsWsServer;
+use crate::server::ssh;
 use crate::settings::AppSettings;
 use crate::state::AppState;
-use log::{debug, error, info};
-use tauri::{Manager, State};
+use crate::{apply_tray_left_click, CLOSE_BUTTON_ACTIONS, TRAY_LEFT_CLICK_ACTIONS};
+use anyhow::anyhow;
+use log::{debug, error, info, warn};
+use serde::Serialize;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_autostart::ManagerExt;
 
 #[tauri::command]
 pub fn get_settings(state: State<'_, AppState>) -> AppSettings {
@@ -15,6 +26,51 @@ pub fn get_settings(state: State<'_, AppState>) -> AppSettings {
 #[tauri::command]
 pub async fn save_settings(app: tauri::AppHandle, settings: AppSettings) -> CommandResult<()> {
     debug!("Saving application settings");
+    if !TRAY_LEFT_CLICK_ACTIONS.contains(&settings.tray_left_click.as_str()) {
+        return Err(AppError::validation(format!(
+            "Invalid tray left-click action: {}",
+            settings.tray_left_click
+        ))
+        .into());
+    }
+    if !CLOSE_BUTTON_ACTIONS.contains(&settings.close_button_action.as_str()) {
+        return Err(AppError::validation(format!(
+            "Invalid close button action: {}",
+            settings.close_button_action
+        ))
+        .into());
+    }
+    if !(1..=dns::MAX_RETRY_ATTEMPTS).contains(&settings.dns_retry_attempts) {
+        return Err(AppError::validation(format!(
+            "DNS retry attempts must be between 1 and {}",
+            dns::MAX_RETRY_ATTEMPTS
+        ))
+        .into());
+    }
+    if settings.failure_grace_secs > ssh::MAX_FAILURE_GRACE_SECS {
+        return Err(AppError::validation(format!(
+            "Failure grace period must be at most {} seconds",
+            ssh::MAX_FAILURE_GRACE_SECS
+        ))
+        .into());
+    }
+    if settings.metrics_ws_enabled {
+        MetricsWsServer::validate_addr(&settings.metrics_ws_addr, settings.metrics_ws_allow_remote)
+            .map_err(|e| CommandError::from(AppError::validation(format!("{:#}", e))))?;
+    }
+    let tray_left_click = settings.tray_left_click.clone();
+    let dns_retry_attempts = settings.dns_retry_attempts;
+    let failure_grace_secs = settings.failure_grace_secs;
+    let strict_host_key_checking = settings.strict_host_key_checking;
+    let log_level = match settings.log_level.as_deref() {
+        Some(level) => Some(logging::parse_level(level).ok_or_else(|| {
+            CommandError::from(AppError::validation(format!(
+                "Invalid log level: {}",
+                level
+            )))
+        })?),
+        None => None,
+    };
 
     // Also save to in-memory settings manager
     let state: tauri::State<'_, AppState> = app.state::<AppState>();
@@ -22,7 +78,21 @@ pub async fn save_settings(app: tauri::AppHandle, settings: AppSettings) -> Comm
     match result {
         Ok(()) => {
             info!("Application settings saved successfully");
-            Ok(())
+            apply_tray_left_click(&app, &tray_left_click);
+            dns::set_retry_attempts(dns_retry_attempts);
+            ssh::set_failure_grace(failure_grace_secs);
+            known_hosts::set_strict(strict_host_key_checking);
+            if let Some(level) = log_level {
+                logging::set_configured_level(level);
+            }
+            state
+                .tunnel_service
+                .apply_metrics_server()
+                .await
+                .map_err(|e| {
+                    error!("Failed to apply metrics server settings: {:#}", e);
+                    CommandError::from(e)
+                })
         }
         Err(e) => {
             error!("Failed to save application settings: {}", e);
@@ -30,3 +100,123 @@ pub async fn save_settings(app: tauri::AppHandle, settings: AppSettings) -> Comm
         }
     }
 }
+
+/// 系统中开机启动的实际注册状态与设置中的 launch_at_login
+#[derive(Debug, Serialize)]
+pub struct AutostartState {
+    pub enabled: bool,
+    pub setting: bool,
+    /// 两者不一致，通常是用户在系统设置中关闭了开机启动
+    pub mismatch: bool,
+}
+
+fn autostart_state(app: &AppHandle) -> CommandResult<AutostartState> {
+    let enabled = app
+        .autolaunch()
+        .is_enabled()
+        .map_err(|e| CommandError::from(anyhow!("Failed to query autostart: {}", e)))?;
+    let setting = app
+        .state::<AppState>()
+        .settings
+        .get_settings()
+        .launch_at_login;
+    Ok(AutostartState {
+        enabled,
+        setting,
+        mismatch: enabled != setting,
+    })
+}
+
+/// 查询开机启动是否真的已注册
+#[tauri::command]
+pub fn get_autostart_state(app: AppHandle) -> CommandResult<AutostartState> {
+    let state = autostart_state(&app)?;
+    if state.mismatch {
+        warn!(
+            "Autostart is {} in the system but {} in settings",
+            if state.enabled { "enabled" } else { "disabled" },
+            if state.setting { "enabled" } else { "disabled" }
+        );
+    }
+    Ok(state)
+}
+
+/// 注册或取消开机启动，并同步设置中的 launch_at_login
+#[tauri::command]
+pub async fn set_autostart(app: AppHandle, enabled: bool) -> CommandResult<AutostartState> {
+    let autolaunch = app.autolaunch();
+    let result = if enabled {
+        autolaunch.enable()
+    } else {
+        autolaunch.disable()
+    };
+    result.map_err(|e| CommandError::from(anyhow!("Failed to update autostart: {}", e)))?;
+
+    let state = app.state::<AppState>();
+    let mut current = state.settings.get_settings();
+    if current.launch_at_login != enabled {
+        current.launch_at_login = enabled;
+        state
+            .settings
+            .save_settings(current)
+            .await
+            .map_err(CommandError::from)?;
+    }
+    info!("Autostart {}", if enabled { "enabled" } else { "disabled" });
+    autostart_state(&app)
+}
+
+/// 当前生效的日志级别
+#[tauri::command]
+pub fn get_log_level() -> String {
+    logging::configured_level().as_str().to_lowercase()
+}
+
+/// 运行时调整日志级别 (包括 russh 的日志)，`persist` 时写入设置，重启后仍然生效
+#[tauri::command]
+pub async fn set_log_level(
+    app: AppHandle,
+    level: String,
+    persist: Option<bool>,
+) -> CommandResult<String> {
+    let filter = logging::parse_level(&level).ok_or_else(|| {
+        CommandError::from(AppError::validation(format!(
+            "Invalid log level: {}",
+            level
+        )))
+    })?;
+    let level = filter.as_str().to_lowercase();
+    logging::set_configured_level(filter);
+    info!("Log level set to {}", level);
+
+    if persist.unwrap_or(false) {
+        let state = app.state::<AppState>();
+        let mut settings = state.settings.get_settings();
+        settings.log_level = Some(level.clone());
+        state
+            .settings
+            .save_settings(settings)
+            .await
+            .map_err(CommandError::from)?;
+    }
+    Ok(level)
+}
+
+#[tauri::command]
+pub fn get_maintenance_mode() -> MaintenanceStatus {
+    maintenance::status()
+}
+
+/// 开关维护模式，`duration_minutes` 到期后自动退出，不填则需要手动关闭
+#[tauri::command]
+pub fn set_maintenance_mode(enabled: bool, duration_minutes: Option<u32>) -> MaintenanceStatus {
+    if enabled {
+        maintenance::enable(
+            duration_minutes
+                .filter(|minutes| *minutes > 0)
+                .map(|minutes| Duration::from_secs(minutes as u64 * 60)),
+        )
+    } else {
+        maintenance::disable()
+    }
+}
@@ -1,9 +1,17 @@
-use crate::database::models::TunnelConfig;
-use crate::error::{CommandError, CommandResult};
-use crate::server::model::{TunnelMetric, TunnelState};
-use crate::service::tunnel::TunnelService;
+use crate::database::models::{TunnelConfig, TunnelCounters};
+use crate::error::{AppError, CommandError, CommandResult};
+use crate::server::key_path;
+use crate::server::model::{LogPayload, ShutdownReport, TunnelMetric, TunnelState};
+use crate::server::preview::ForwardPreview;
+use crate::service::tunnel::{
+    ActivityEntry, BatchUpdateResult, FieldDiff, KeyPathMigration, KeyPermIssue, TunnelPatch,
+    TunnelService,
+};
+use crate::service::usage::{self, UsageSummary};
 use crate::state::AppState;
-use log::debug;
+use log::{debug, info};
+use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tauri::{AppHandle, Manager};
 
@@ -14,19 +22,40 @@ pub struct TunnelStatusResponse {
     state: String,
     send_bytes: u128,
     recv_bytes: u128,
+    // 实际监听端口，自动换端口后与配置不同
+    local_port: Option<u16>,
+    stats_paused: bool,
+    // 最近一次暂停统计以来的流量
+    window_send_bytes: u128,
+    window_recv_bytes: u128,
+    active_connections: u32,
+    queued_connections: u32,
+    // 本次会话中监听回环地址却来自非回环地址的连接数
+    unexpected_sources: u64,
+    // 认证回退成功时的方式，不含密码
+    auth_fallback: Option<String>,
 }
 
 impl From<&TunnelMetric> for TunnelStatusResponse {
     fn from(tunnel_metric: &TunnelMetric) -> Self {
-        let is_running = matches!(tunnel_metric.tunnel_state, TunnelState::Running(_));
+        let is_running = matches!(
+            tunnel_metric.tunnel_state,
+            TunnelState::Listening(_) | TunnelState::Running(_) | TunnelState::Unstable(_)
+        );
+        // 使用健康检查缓存的会话 RTT
         let ping = match &tunnel_metric.tunnel_state {
-            TunnelState::Running(duration) => Some(duration.as_millis()),
+            TunnelState::Listening(_) | TunnelState::Running(_) | TunnelState::Unstable(_) => {
+                tunnel_metric.last_latency.map(|d| d.as_millis())
+            }
             _ => None,
         };
         let state = match &tunnel_metric.tunnel_state {
             TunnelState::Stopped => "stopped".to_string(),
             TunnelState::Starting => "starting".to_string(),
+            TunnelState::WaitingForHealth => "waiting_for_health".to_string(),
+            TunnelState::Listening(_) => "listening".to_string(),
             TunnelState::Running(_) => "running".to_string(),
+            TunnelState::Unstable(e) => format!("unstable: {}", e),
             TunnelState::Stopping => "stopping".to_string(),
             TunnelState::Error(e) => format!("error: {}", e),
         };
@@ -37,6 +66,17 @@ impl From<&TunnelMetric> for TunnelStatusResponse {
             state,
             send_bytes: tunnel_metric.traffic.send_bytes,
             recv_bytes: tunnel_metric.traffic.recv_bytes,
+            local_port: tunnel_metric.local_addr.map(|addr| addr.port()),
+            stats_paused: tunnel_metric.stats_paused,
+            window_send_bytes: tunnel_metric.window_traffic.send_bytes,
+            window_recv_bytes: tunnel_metric.window_traffic.recv_bytes,
+            active_connections: tunnel_metric.active_connections,
+            queued_connections: tunnel_metric.queued_connections,
+            unexpected_sources: tunnel_metric.unexpected_sources,
+            auth_fallback: tunnel_metric
+                .auth_fallback
+                .as_ref()
+                .map(|auth| auth.describe()),
         }
     }
 }
@@ -62,6 +102,172 @@ pub async fn save_tunnel(app_handle: AppHandle, tunnel: TunnelConfig) -> Command
         .map_err(CommandError::from)
 }
 
+/// 保存运行中隧道的配置，返回是否因连接参数变化而重启
+#[tauri::command]
+pub async fn update_running_tunnel(
+    app_handle: AppHandle,
+    tunnel: TunnelConfig,
+) -> CommandResult<bool> {
+    get_tunnel_service(app_handle)
+        .update_running_tunnel(tunnel)
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn create_tunnel_from_uri(
+    app_handle: AppHandle,
+    uri: String,
+) -> CommandResult<TunnelConfig> {
+    get_tunnel_service(app_handle)
+        .create_tunnel_from_uri(&uri)
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn migrate_key_paths(app: AppHandle) -> CommandResult<Vec<KeyPathMigration>> {
+    get_tunnel_service(app)
+        .migrate_key_paths()
+        .await
+        .map_err(CommandError::from)
+}
+
+/// 检查已配置私钥的文件权限
+#[tauri::command]
+pub async fn audit_key_permissions(app: AppHandle) -> CommandResult<Vec<KeyPermIssue>> {
+    get_tunnel_service(app)
+        .audit_key_permissions()
+        .await
+        .map_err(CommandError::from)
+}
+
+/// 把私钥文件权限改为 0600，返回规范化后的路径
+#[tauri::command]
+pub async fn fix_key_permissions(path: String) -> CommandResult<String> {
+    let path = key_path::fix_key_permissions(&path).map_err(CommandError::from)?;
+    info!("Fixed permissions of key file {}", path.display());
+    Ok(path.display().to_string())
+}
+
+/// 换用新私钥，测试登录通过后才保存
+#[tauri::command]
+pub async fn rotate_tunnel_key(
+    app: AppHandle,
+    id: String,
+    new_key_path: String,
+    passphrase: Option<String>,
+) -> CommandResult<()> {
+    get_tunnel_service(app)
+        .rotate_tunnel_key(id, new_key_path, passphrase)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// 把回退成功的认证方式保存到隧道配置
+#[tauri::command]
+pub async fn apply_auth_fallback(app: AppHandle, id: String) -> CommandResult<()> {
+    get_tunnel_service(app)
+        .apply_auth_fallback(id)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// 批量修改多个隧道的 SSH 用户名、端口或私钥
+#[tauri::command]
+pub async fn batch_update(
+    app: AppHandle,
+    ids: Vec<String>,
+    patch: TunnelPatch,
+) -> CommandResult<Vec<BatchUpdateResult>> {
+    get_tunnel_service(app)
+        .batch_update(ids, patch)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// 调试用：移除隧道并报告 Metrics 任务、监听循环和连接任务是否全部退出
+#[tauri::command]
+pub async fn shutdown_tunnel_debug(app: AppHandle, id: String) -> CommandResult<ShutdownReport> {
+    get_tunnel_service(app)
+        .shutdown_tunnel_debug(id)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// 一段时间内的流量合计及各隧道明细
+///
+/// `preset` 为 "today" | "week" | "month" 时忽略 `since`，否则 `since` 必填 (unix 毫秒)。
+#[tauri::command]
+pub async fn get_usage_summary(
+    preset: Option<String>,
+    since: Option<u64>,
+    until: Option<u64>,
+) -> CommandResult<UsageSummary> {
+    let since = match (preset.as_deref(), since) {
+        (Some(preset), _) => {
+            usage::preset_since(preset, chrono::Local::now()).map_err(CommandError::from)?
+        }
+        (None, Some(since)) => since,
+        (None, None) => {
+            return Err(AppError::validation("Either a preset or a start time is required").into())
+        }
+    };
+    usage::usage_summary(since, until)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// 处于错误状态的隧道 id
+#[tauri::command]
+pub async fn get_failed_tunnels(app: AppHandle) -> Vec<String> {
+    get_tunnel_service(app).get_failed_tunnels().await
+}
+
+/// 清除单个隧道的错误状态并刷新托盘
+#[tauri::command]
+pub async fn clear_failed_tunnel(app: AppHandle, id: String) -> CommandResult<()> {
+    let service = get_tunnel_service(app.clone());
+    service
+        .clear_failed_tunnel(id)
+        .await
+        .map_err(CommandError::from)?;
+    service.emit_tray_status(&app).await;
+    Ok(())
+}
+
+/// 清除所有隧道的错误状态并刷新托盘，返回清除的数量
+#[tauri::command]
+pub async fn clear_all_failed(app: AppHandle) -> CommandResult<usize> {
+    let service = get_tunnel_service(app.clone());
+    let cleared = service
+        .clear_all_failed()
+        .await
+        .map_err(CommandError::from)?;
+    service.emit_tray_status(&app).await;
+    Ok(cleared)
+}
+
+#[tauri::command]
+pub async fn find_duplicate_tunnels(app: AppHandle) -> CommandResult<Vec<Vec<String>>> {
+    get_tunnel_service(app)
+        .find_duplicate_tunnels()
+        .await
+        .map_err(CommandError::from)
+}
+
+/// 两个隧道配置的差异，用于合并重复的隧道
+#[tauri::command]
+pub async fn diff_tunnels(
+    app: AppHandle,
+    id_a: String,
+    id_b: String,
+) -> CommandResult<Vec<FieldDiff>> {
+    get_tunnel_service(app)
+        .diff_tunnels(id_a, id_b)
+        .await
+        .map_err(CommandError::from)
+}
+
 #[tauri::command]
 pub async fn delete_tunnel(app_handle: AppHandle, id: String) -> CommandResult<()> {
     get_tunnel_service(app_handle)
@@ -70,10 +276,15 @@ pub async fn delete_tunnel(app_handle: AppHandle, id: String) -> CommandResult<(
         .map_err(CommandError::from)
 }
 
+/// 不在时间段内时拒绝启动，`override_schedule` 为 true 时忽略时间段
 #[tauri::command]
-pub async fn start_tunnel(app_handle: AppHandle, id: String) -> CommandResult<()> {
+pub async fn start_tunnel(
+    app_handle: AppHandle,
+    id: String,
+    override_schedule: Option<bool>,
+) -> CommandResult<()> {
     get_tunnel_service(app_handle)
-        .start_tunnel(id)
+        .start_tunnel_manually(id, override_schedule.unwrap_or(false))
         .await
         .map_err(CommandError::from)
 }
@@ -103,3 +314,111 @@ pub async fn get_tunnel_status(app: AppHandle, id: String) -> CommandResult<Tunn
     );
     Ok(tunnel_status)
 }
+
+/// 所有已加载隧道的状态，按隧道 id 索引；未加载的隧道不在结果中
+///
+/// 隧道很多时代替逐个调用 `get_tunnel_status`。
+#[tauri::command]
+pub async fn get_all_tunnel_statuses(
+    app: AppHandle,
+) -> CommandResult<HashMap<String, TunnelStatusResponse>> {
+    let statuses = get_tunnel_service(app)
+        .get_all_tunnel_health_status()
+        .await
+        .iter()
+        .map(|(id, metric)| (id.clone(), TunnelStatusResponse::from(metric)))
+        .collect();
+    Ok(statuses)
+}
+
+#[tauri::command]
+pub async fn to_ssh_command(app: AppHandle, id: String) -> CommandResult<String> {
+    get_tunnel_service(app)
+        .to_ssh_command(id)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// 启动前预览实际的转发：使用的实现、SSH 地址、本地监听地址和解析后的目标
+///
+/// docker 模式和配置了解析命令时会临时建立 SSH 连接，不绑定本地端口。
+#[tauri::command]
+pub async fn preview_tunnel(app: AppHandle, id: String) -> CommandResult<ForwardPreview> {
+    get_tunnel_service(app)
+        .preview_tunnel(id)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// 暂停历史流量统计，测量窗口从零开始累计
+#[tauri::command]
+pub async fn pause_stats(app: AppHandle, id: String) -> CommandResult<()> {
+    get_tunnel_service(app)
+        .pause_stats(id)
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn resume_stats(app: AppHandle, id: String) -> CommandResult<()> {
+    get_tunnel_service(app)
+        .resume_stats(id)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// 最近一次测得的会话内 RTT (毫秒)，隧道未运行或尚未测量时为 None
+#[tauri::command]
+pub async fn get_tunnel_latency(app: AppHandle, id: String) -> CommandResult<Option<u128>> {
+    let latency = get_tunnel_service(app).get_tunnel_latency(id).await;
+    Ok(latency.map(|d| d.as_millis()))
+}
+
+/// 累计连接数和失败数，失败率高说明目标间歇性故障
+#[tauri::command]
+pub async fn get_tunnel_counters(app: AppHandle, id: String) -> CommandResult<TunnelCounters> {
+    get_tunnel_service(app)
+        .get_tunnel_counters(id)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// ciconia 自身占用的本地地址 (隧道 id, 实际绑定地址)
+#[tauri::command]
+pub async fn get_active_bindings(app: AppHandle) -> CommandResult<Vec<(String, SocketAddr)>> {
+    Ok(get_tunnel_service(app).get_active_bindings().await)
+}
+
+/// 导出流量历史为 CSV，`id` 为空时导出所有隧道，返回数据行数
+#[tauri::command]
+pub async fn export_metrics_csv(
+    app: AppHandle,
+    id: Option<String>,
+    dest_path: String,
+) -> CommandResult<usize> {
+    get_tunnel_service(app)
+        .export_metrics_csv(id, dest_path)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// 所有隧道的最近事件 (启动、停止、失败、重连、大流量)，新的在前
+#[tauri::command]
+pub async fn get_activity_feed(
+    app: AppHandle,
+    limit: Option<u64>,
+) -> CommandResult<Vec<ActivityEntry>> {
+    get_tunnel_service(app)
+        .get_activity_feed(limit.unwrap_or(50))
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn get_recent_logs(
+    app: AppHandle,
+    id: Option<String>,
+    limit: Option<usize>,
+) -> CommandResult<Vec<LogPayload>> {
+    Ok(get_tunnel_service(app).get_recent_logs(id, limit.unwrap_or(200)))
+}
@@ -15,6 +15,15 @@ pub struct Model {
     pub auto_reconnect: bool,
     pub theme: String,
     pub language: String,
+    pub metrics_ws_enabled: bool,
+    pub metrics_ws_addr: String,
+    pub metrics_ws_allow_remote: bool,
+    pub tray_left_click: String,
+    pub log_level: Option<String>,
+    pub dns_retry_attempts: u32,
+    pub diagnostic_commands: Option<String>, // JSON encoded Vec<String>
+    pub failure_grace_secs: u32,
+    pub close_button_action: String,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -1,3 +1,8 @@
 pub mod app_settings;
+pub mod host_key_history;
+pub mod known_hosts;
+pub mod metrics_history;
 pub mod prelude;
 pub mod tunnel_config;
+pub mod tunnel_counters;
+pub mod tunnel_events;
@@ -1,2 +1,7 @@
 pub use super::app_settings::Entity as AppSettings;
+pub use super::host_key_history::Entity as HostKeyHistory;
+pub use super::known_hosts::Entity as KnownHosts;
+pub use super::metrics_history::Entity as MetricsHistory;
 pub use super::tunnel_config::Entity as TunnelConfig;
+pub use super::tunnel_counters::Entity as TunnelCounters;
+pub use super::tunnel_events::Entity as TunnelEvents;
@@ -7,26 +7,69 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub id: String, // UUID length
     pub name: String,
-    pub mode: String, // "standard" | "docker"
+    pub mode: String, // "standard" | "docker" | "socks"
 
     // SSH Connection
     pub ssh_host: String,
     pub ssh_port: u16,
     pub ssh_username: String,
-    pub auth_type: String, // "password" | "key"
+    pub auth_type: String, // "password" | "key" | "agent"
     pub ssh_password: Option<String>,
     pub ssh_key_path: Option<String>,
+    pub ssh_key_passphrase: Option<String>,
+    pub identities_only: bool,
+    pub source_addr: Option<String>,
+    pub bypass_proxy: bool,
+    pub auth_fallback: bool,
+    pub jump_host: Option<String>,
+    pub jump_port: Option<u16>,
+    pub jump_user: Option<String>,
+    pub jump_auth: Option<String>,
 
-    pub forward_type: String, // "direct" | "container"
+    pub forward_type: String, // "direct" | "container" | "remote"
 
     // Forwarding
     pub local_port: Option<u16>,
+    pub auto_remap_port: bool,
     pub target_host: Option<String>,
     pub target_port: Option<u16>,
+    pub target_resolver_command: Option<String>,
+    pub proxy_protocol: Option<String>, // "v1" | "v2"
+    pub port_map: Option<String>,       // JSON encoded Vec<PortMapping>
+    pub io_buffer_size: Option<u32>,    // bytes
+    pub keepalive_idle_secs: Option<u32>,
+    pub fail_fast: bool,
+    pub target_check_interval_secs: Option<u32>,
+    pub target_unreachable_action: Option<String>, // None | "mark" | "reconnect"
+    pub max_connections: Option<u32>,
+    pub queue_timeout_secs: Option<u32>,
+    pub pause_when_locked: bool,
+    pub dscp: Option<u8>,
 
     // Docker Info
     pub container_name: Option<String>,
     pub container_port: Option<u16>,
+    pub container_runtime: Option<String>, // "docker" | "podman" | "auto"
+    pub docker_forward_via: Option<String>, // None | "container_ip" | "published_port"
+    pub wait_for_healthy: bool,
+    pub remote_shell: Option<String>, // None | "none" | "sh -c"
+
+    // Multiplexing
+    pub control_path: Option<String>,
+    pub backend: Option<String>, // None | "auto" | "russh" | "system_ssh"
+    pub extra_ssh_options: Option<String>, // JSON encoded Vec<String>
+
+    // Reconnect
+    pub auto_reconnect: Option<bool>,
+    pub on_failure_command: Option<String>,
+    pub check_command: Option<String>,
+    pub wol_mac: Option<String>,
+
+    // Schedule
+    pub schedule: Option<String>, // JSON encoded TunnelSchedule
+
+    // Runtime state, not part of the user facing config
+    pub was_running: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
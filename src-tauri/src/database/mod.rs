@@ -1,19 +1,50 @@
 pub mod entity;
 pub mod models;
+mod repair;
+mod retry;
 
-use anyhow::{Context, Result};
+use crate::error::AppError;
+use anyhow::{anyhow, Context, Result};
 use entity::prelude::*;
-use entity::{app_settings, tunnel_config};
+use entity::{
+    app_settings, host_key_history, known_hosts, metrics_history, tunnel_config, tunnel_counters,
+    tunnel_events,
+};
 use log::{debug, error, info, warn};
-use models::{AppSettings as AppSettingsModel, TunnelConfig as TunnelConfigModel};
+use models::{
+    AppSettings as AppSettingsModel, HostKeyRecord, KnownHost, MetricSample, TrafficTotal,
+    TunnelConfig as TunnelConfigModel, TunnelCounters as TunnelCountersModel, TunnelEvent,
+};
 use once_cell::sync::OnceCell;
+use retry::with_retry;
 use sea_orm::{
-    sea_query::OnConflict, ConnectOptions, Database, DatabaseConnection, EntityTrait, Set,
+    sea_query::{Expr, OnConflict},
+    ColumnTrait, ConnectOptions, ConnectionTrait, Database, DatabaseConnection, DbBackend,
+    EntityTrait, NotSet, QueryFilter, QueryOrder, QuerySelect, Set, Statement, TransactionTrait,
 };
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 pub static DB_POOL: OnceCell<DatabaseConnection> = OnceCell::new();
+static DB_PATH: OnceCell<PathBuf> = OnceCell::new();
+
+// 备份/压缩等维护操作互斥执行
+static MAINTENANCE_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+// 写操作持有读锁，维护操作等在途写入完成后持有写锁，期间新的写入等待维护结束
+static WRITE_GATE: tokio::sync::RwLock<()> = tokio::sync::RwLock::const_new(());
+
+// 隧道表的内存缓存，状态轮询和启停不必每次查询数据库
+static TUNNEL_CACHE: Mutex<TunnelCache> = Mutex::new(TunnelCache {
+    generation: 0,
+    rows: None,
+});
+
+/// 每次写入隧道表都会递增 `generation`，失效前开始的查询结果不会写回缓存
+struct TunnelCache {
+    generation: u64,
+    rows: Option<Arc<Vec<tunnel_config::Model>>>,
+}
 
 #[derive(Clone, Debug)]
 pub struct DB;
@@ -53,12 +84,23 @@ impl DB {
         // 3. 运行迁移 (集成在初始化中)
         run_migrations(&db_path).await?;
 
+        // 4. 修复历史版本遗留的不完整表结构
+        if let Err(e) =
+            repair::ensure_columns(&connection, "tunnels_v2", repair::TUNNEL_COLUMNS).await
+        {
+            error!("Tunnel schema self-check failed: {:#}", e);
+        }
+        if let Err(e) = Self::heal_settings_with(&connection).await {
+            error!("Settings self-check failed: {:#}", e);
+        }
+
         // 设置全局单例
         //以此确保线程安全，如果设置失败说明被其他线程抢先了，直接获取即可
         match DB_POOL.set(connection) {
             Ok(_) => info!("Global DB pool set successfully"),
             Err(_) => debug!("Global DB pool already initialized"),
         }
+        let _ = DB_PATH.set(db_path);
 
         Ok(())
     }
@@ -67,10 +109,13 @@ impl DB {
         debug!("Loading application settings");
 
         let connection = DB_POOL.get().context("Failed to get DB pool")?;
-        let settings = AppSettings::find_by_id(1)
-            .one(connection)
-            .await
-            .context("Failed to query app settings")?;
+        let settings = with_retry("load_settings", || async move {
+            AppSettings::find_by_id(1)
+                .one(connection)
+                .await
+                .context("Failed to query app settings")
+        })
+        .await?;
 
         // 转换逻辑建议下沉到 Model 的 From trait，这里直接 map
         Ok(settings.map(|s| AppSettingsModel {
@@ -83,13 +128,72 @@ impl DB {
             auto_reconnect: s.auto_reconnect,
             theme: s.theme,
             language: s.language,
+            metrics_ws_enabled: s.metrics_ws_enabled,
+            metrics_ws_addr: s.metrics_ws_addr,
+            metrics_ws_allow_remote: s.metrics_ws_allow_remote,
+            tray_left_click: s.tray_left_click,
+            log_level: s.log_level,
+            dns_retry_attempts: s.dns_retry_attempts,
+            diagnostic_commands: parse_diagnostic_commands(s.diagnostic_commands.as_deref()),
+            failure_grace_secs: s.failure_grace_secs,
+            close_button_action: s.close_button_action,
         }))
     }
 
+    /// 补齐设置行缺失的列并把 NULL 的必填列重置为默认值，保留其它已有的设置
+    ///
+    /// 返回修复的列名，设置行完好时为空。
+    pub async fn heal_settings() -> Result<Vec<String>> {
+        let connection = DB_POOL.get().context("Failed to get DB pool")?;
+        let _write = hold_off_maintenance().await;
+        Self::heal_settings_with(connection).await
+    }
+
+    async fn heal_settings_with(connection: &DatabaseConnection) -> Result<Vec<String>> {
+        let mut repaired =
+            repair::ensure_columns(connection, "app_settings", repair::SETTINGS_COLUMNS).await?;
+
+        let d = AppSettingsModel::default();
+        let defaults = vec![
+            ("launch_at_login", d.launch_at_login.into()),
+            (
+                "minimize_to_tray_on_close",
+                d.minimize_to_tray_on_close.into(),
+            ),
+            ("keep_alive_interval", d.keep_alive_interval.into()),
+            (
+                "strict_host_key_checking",
+                d.strict_host_key_checking.into(),
+            ),
+            ("connection_timeout", d.connection_timeout.into()),
+            ("auto_reconnect", d.auto_reconnect.into()),
+            ("theme", d.theme.into()),
+            ("language", d.language.into()),
+            ("metrics_ws_enabled", d.metrics_ws_enabled.into()),
+            ("metrics_ws_addr", d.metrics_ws_addr.into()),
+            ("metrics_ws_allow_remote", d.metrics_ws_allow_remote.into()),
+            ("tray_left_click", d.tray_left_click.into()),
+            ("dns_retry_attempts", d.dns_retry_attempts.into()),
+            ("failure_grace_secs", d.failure_grace_secs.into()),
+            ("close_button_action", d.close_button_action.into()),
+        ];
+        for name in repair::backfill_nulls(connection, "app_settings", defaults).await? {
+            if !repaired.contains(&name) {
+                repaired.push(name);
+            }
+        }
+
+        if !repaired.is_empty() {
+            info!("Repaired settings columns: {:?}", repaired);
+        }
+        Ok(repaired)
+    }
+
     pub async fn save_settings(settings: &AppSettingsModel) -> Result<()> {
         debug!("Saving application settings (Upsert)");
 
         let connection = DB_POOL.get().context("Failed to get DB pool")?;
+        let _write = hold_off_maintenance().await;
         let active_model = app_settings::ActiveModel {
             id: Set(1),
             launch_at_login: Set(settings.launch_at_login),
@@ -101,29 +205,51 @@ impl DB {
             auto_reconnect: Set(settings.auto_reconnect),
             theme: Set(settings.theme.clone()),
             language: Set(settings.language.clone()),
+            metrics_ws_enabled: Set(settings.metrics_ws_enabled),
+            metrics_ws_addr: Set(settings.metrics_ws_addr.clone()),
+            metrics_ws_allow_remote: Set(settings.metrics_ws_allow_remote),
+            tray_left_click: Set(settings.tray_left_click.clone()),
+            log_level: Set(settings.log_level.clone()),
+            dns_retry_attempts: Set(settings.dns_retry_attempts),
+            diagnostic_commands: Set(Some(serde_json::to_string(&settings.diagnostic_commands)?)),
+            failure_grace_secs: Set(settings.failure_grace_secs),
+            close_button_action: Set(settings.close_button_action.clone()),
         };
 
         // 4. 使用 Upsert (On Conflict Do Update)
         // 只有当 ID=1 冲突时，更新除 ID 外的所有字段
-        AppSettings::insert(active_model)
-            .on_conflict(
-                OnConflict::column(app_settings::Column::Id)
-                    .update_columns([
-                        app_settings::Column::LaunchAtLogin,
-                        app_settings::Column::MinimizeToTrayOnClose,
-                        app_settings::Column::KeepAliveInterval,
-                        app_settings::Column::DefaultSshKey,
-                        app_settings::Column::StrictHostKeyChecking,
-                        app_settings::Column::ConnectionTimeout,
-                        app_settings::Column::AutoReconnect,
-                        app_settings::Column::Theme,
-                        app_settings::Column::Language,
-                    ])
-                    .to_owned(),
-            )
-            .exec(connection)
-            .await
-            .context("Failed to upsert settings")?;
+        let active_model = &active_model;
+        with_retry("save_settings", || async move {
+            AppSettings::insert(active_model.clone())
+                .on_conflict(
+                    OnConflict::column(app_settings::Column::Id)
+                        .update_columns([
+                            app_settings::Column::LaunchAtLogin,
+                            app_settings::Column::MinimizeToTrayOnClose,
+                            app_settings::Column::KeepAliveInterval,
+                            app_settings::Column::DefaultSshKey,
+                            app_settings::Column::StrictHostKeyChecking,
+                            app_settings::Column::ConnectionTimeout,
+                            app_settings::Column::AutoReconnect,
+                            app_settings::Column::Theme,
+                            app_settings::Column::Language,
+                            app_settings::Column::MetricsWsEnabled,
+                            app_settings::Column::MetricsWsAddr,
+                            app_settings::Column::TrayLeftClick,
+                            app_settings::Column::MetricsWsAllowRemote,
+                            app_settings::Column::LogLevel,
+                            app_settings::Column::DnsRetryAttempts,
+                            app_settings::Column::DiagnosticCommands,
+                            app_settings::Column::FailureGraceSecs,
+                            app_settings::Column::CloseButtonAction,
+                        ])
+                        .to_owned(),
+                )
+                .exec(connection)
+                .await
+                .context("Failed to upsert settings")
+        })
+        .await?;
 
         Ok(())
     }
@@ -131,32 +257,66 @@ impl DB {
     pub async fn load_tunnels() -> Result<Vec<TunnelConfigModel>> {
         debug!("Loading tunnels");
 
-        let connection = DB_POOL.get().context("Failed to get DB pool")?;
-        let entities = TunnelConfig::find().all(connection).await.map_err(|e| {
-            error!("Database query failed for tunnels: {:?}", e);
-            e
-        })?;
-
+        let entities = tunnel_rows().await?;
         debug!("Found {} tunnels", entities.len());
 
         // 建议：在 models.rs 中实现 impl From<tunnel_config::Model> for TunnelConfigModel
         let configs = entities
-            .into_iter()
+            .iter()
+            .cloned()
             .map(|entity| TunnelConfigModel {
+                port_map: parse_port_map(&entity.id, entity.port_map.as_deref()),
+                schedule: parse_schedule(&entity.id, entity.schedule.as_deref()),
+                extra_ssh_options: parse_extra_ssh_options(
+                    &entity.id,
+                    entity.extra_ssh_options.as_deref(),
+                ),
                 id: entity.id,
                 name: entity.name,
                 mode: entity.mode,
+                direction: (entity.forward_type == "remote").then(|| "remote".to_string()),
                 ssh_host: entity.ssh_host,
                 ssh_port: entity.ssh_port,
                 ssh_username: entity.ssh_username,
                 auth_type: entity.auth_type,
                 ssh_password: entity.ssh_password,
                 ssh_key_path: entity.ssh_key_path,
+                ssh_key_passphrase: entity.ssh_key_passphrase,
+                identities_only: entity.identities_only,
+                source_addr: entity.source_addr,
+                bypass_proxy: entity.bypass_proxy,
+                auth_fallback: entity.auth_fallback,
+                jump_host: entity.jump_host,
+                jump_port: entity.jump_port,
+                jump_user: entity.jump_user,
+                jump_auth: entity.jump_auth,
                 local_port: entity.local_port,
+                auto_remap_port: entity.auto_remap_port,
                 target_host: entity.target_host,
                 target_port: entity.target_port,
+                target_resolver_command: entity.target_resolver_command,
+                proxy_protocol: entity.proxy_protocol,
+                io_buffer_size: entity.io_buffer_size,
+                keepalive_idle_secs: entity.keepalive_idle_secs,
+                fail_fast: entity.fail_fast,
+                target_check_interval_secs: entity.target_check_interval_secs,
+                target_unreachable_action: entity.target_unreachable_action,
+                max_connections: entity.max_connections,
+                queue_timeout_secs: entity.queue_timeout_secs,
+                pause_when_locked: entity.pause_when_locked,
+                dscp: entity.dscp,
                 container_name: entity.container_name,
                 container_port: entity.container_port,
+                container_runtime: entity.container_runtime,
+                docker_forward_via: entity.docker_forward_via,
+                wait_for_healthy: entity.wait_for_healthy,
+                remote_shell: entity.remote_shell,
+                control_path: entity.control_path,
+                backend: entity.backend,
+                auto_reconnect: entity.auto_reconnect,
+                on_failure_command: entity.on_failure_command,
+                check_command: entity.check_command,
+                wol_mac: entity.wol_mac,
             })
             .collect();
 
@@ -167,72 +327,91 @@ impl DB {
         debug!("Saving tunnel {} (Upsert)", tunnel.id);
 
         let connection = DB_POOL.get().context("Failed to get DB pool")?;
-        let active_model = tunnel_config::ActiveModel {
-            id: Set(tunnel.id.clone()),
-            name: Set(tunnel.name.clone()),
-            mode: Set(tunnel.mode.clone()),
-            ssh_host: Set(tunnel.ssh_host.clone()),
-            ssh_port: Set(tunnel.ssh_port),
-            ssh_username: Set(tunnel.ssh_username.clone()),
-            auth_type: Set(tunnel.auth_type.clone()),
-            ssh_password: Set(tunnel.ssh_password.clone()),
-            ssh_key_path: Set(tunnel.ssh_key_path.clone()),
-            forward_type: Set(if tunnel.mode == "docker" {
-                "container".to_string()
-            } else {
-                "direct".to_string()
-            }),
-            local_port: Set(tunnel.local_port),
-            target_host: Set(tunnel.target_host.clone()),
-            target_port: Set(tunnel.target_port),
-            container_name: Set(tunnel.container_name.clone()),
-            container_port: Set(tunnel.container_port),
-        };
+        let _write = hold_off_maintenance().await;
+        let active_model = &tunnel_active_model(tunnel)?;
 
         // 5. 使用 Upsert 优化隧道保存
         // 相比原本的 insert-fail-update，这里更原子化
-        TunnelConfig::insert(active_model)
-            .on_conflict(
-                OnConflict::column(tunnel_config::Column::Id)
-                    .update_columns([
-                        tunnel_config::Column::Name,
-                        tunnel_config::Column::Mode,
-                        tunnel_config::Column::SshHost,
-                        tunnel_config::Column::SshPort,
-                        tunnel_config::Column::SshUsername,
-                        tunnel_config::Column::AuthType,
-                        tunnel_config::Column::SshPassword,
-                        tunnel_config::Column::SshKeyPath,
-                        tunnel_config::Column::LocalPort,
-                        tunnel_config::Column::TargetHost,
-                        tunnel_config::Column::TargetPort,
-                        tunnel_config::Column::ContainerName,
-                        tunnel_config::Column::ContainerPort,
-                    ])
-                    .to_owned(),
-            )
-            .exec(connection)
-            .await
-            .map_err(|e| {
-                error!("Failed to save tunnel {} due to: {:?}", tunnel.id, e);
-                e
-            })?;
+        let result = with_retry("save_tunnel", || async move {
+            upsert_tunnel(connection, active_model.clone())
+                .await
+                .map_err(anyhow::Error::from)
+        })
+        .await;
+        invalidate_tunnel_cache();
+        result.map_err(|e| {
+            error!("Failed to save tunnel {} due to: {:?}", tunnel.id, e);
+            e
+        })?;
+
+        Ok(())
+    }
+
+    /// 在一个事务中保存多个隧道，任一失败时全部回滚
+    pub async fn save_tunnels(tunnels: &[TunnelConfigModel]) -> Result<()> {
+        let connection = DB_POOL.get().context("Failed to get DB pool")?;
+        let _write = hold_off_maintenance().await;
+        let models = &tunnels
+            .iter()
+            .map(tunnel_active_model)
+            .collect::<Result<Vec<_>>>()?;
+
+        let result = with_retry("save_tunnels", || async move {
+            let txn = connection.begin().await?;
+            for model in models {
+                upsert_tunnel(&txn, model.clone()).await?;
+            }
+            txn.commit().await?;
+            Ok(())
+        })
+        .await;
+        invalidate_tunnel_cache();
+        result.map_err(|e| {
+            error!("Failed to save {} tunnels due to: {:?}", tunnels.len(), e);
+            e
+        })
+    }
 
+    /// 记录隧道是否处于运行状态，用于下次启动时恢复
+    pub async fn set_was_running(id: &str, was_running: bool) -> Result<()> {
+        let connection = DB_POOL.get().context("Failed to get DB pool")?;
+        let _write = hold_off_maintenance().await;
+        let result = TunnelConfig::update_many()
+            .col_expr(tunnel_config::Column::WasRunning, Expr::value(was_running))
+            .filter(tunnel_config::Column::Id.eq(id))
+            .exec(connection)
+            .await;
+        invalidate_tunnel_cache();
+        result.context("Failed to update tunnel running state")?;
         Ok(())
     }
 
+    /// 上次关闭时仍在运行的隧道
+    pub async fn load_was_running_ids() -> Result<Vec<String>> {
+        let connection = DB_POOL.get().context("Failed to get DB pool")?;
+        let tunnels = with_retry("load_was_running_ids", || async move {
+            TunnelConfig::find()
+                .filter(tunnel_config::Column::WasRunning.eq(true))
+                .all(connection)
+                .await
+                .context("Failed to query running tunnels")
+        })
+        .await?;
+        Ok(tunnels.into_iter().map(|tunnel| tunnel.id).collect())
+    }
+
     pub async fn delete_tunnel(id: &str) -> Result<()> {
         debug!("Deleting tunnel: {}", id);
 
         let connection = DB_POOL.get().context("Failed to get DB pool")?;
-        let res = TunnelConfig::delete_by_id(id)
-            .exec(connection)
-            .await
-            .context("Failed to delete tunnel")?;
+        let _write = hold_off_maintenance().await;
+        let res = TunnelConfig::delete_by_id(id).exec(connection).await;
+        invalidate_tunnel_cache();
+        let res = res.context("Failed to delete tunnel")?;
 
         if res.rows_affected == 0 {
             warn!("Tunnel ID {} not found", id);
-            return Err(anyhow::anyhow!("Tunnel not found"));
+            return Err(AppError::not_found("Tunnel not found").into());
         }
 
         Ok(())
@@ -240,13 +419,460 @@ impl DB {
 
     pub async fn get_tunnel_by_id(id: &str) -> Result<Option<tunnel_config::Model>> {
         debug!("getting tunnel by id: {}", id);
+        let rows = tunnel_rows().await?;
+        Ok(rows.iter().find(|tunnel| tunnel.id == id).cloned())
+    }
+
+    /// 写入一批指标采样，并清理超过保留期的旧数据
+    pub async fn insert_metric_samples(samples: &[MetricSample], retain_after: u64) -> Result<()> {
+        let connection = DB_POOL.get().context("Failed to get DB pool")?;
+        let _write = hold_off_maintenance().await;
+
+        if !samples.is_empty() {
+            let models: Vec<metrics_history::ActiveModel> = samples
+                .iter()
+                .map(|sample| metrics_history::ActiveModel {
+                    tunnel_id: Set(sample.tunnel_id.clone()),
+                    timestamp: Set(sample.timestamp as i64),
+                    tx_bytes: Set(sample.tx_bytes as i64),
+                    rx_bytes: Set(sample.rx_bytes as i64),
+                    latency_ms: Set(sample.latency_ms.map(|ms| ms as i64)),
+                    ..Default::default()
+                })
+                .collect();
+            let models = &models;
+            with_retry("insert_metric_samples", || async move {
+                MetricsHistory::insert_many(models.clone())
+                    .exec(connection)
+                    .await
+                    .context("Failed to insert metric samples")
+            })
+            .await?;
+        }
+
+        MetricsHistory::delete_many()
+            .filter(metrics_history::Column::Timestamp.lt(retain_after as i64))
+            .exec(connection)
+            .await
+            .context("Failed to prune metric history")?;
+
+        Ok(())
+    }
+
+    /// 按时间顺序读取指标历史，`tunnel_id` 为空时返回所有隧道
+    pub async fn load_metric_history(tunnel_id: Option<&str>) -> Result<Vec<MetricSample>> {
+        let connection = DB_POOL.get().context("Failed to get DB pool")?;
+
+        let mut query = MetricsHistory::find()
+            .order_by_asc(metrics_history::Column::Timestamp)
+            .order_by_asc(metrics_history::Column::TunnelId);
+        if let Some(id) = tunnel_id {
+            query = query.filter(metrics_history::Column::TunnelId.eq(id));
+        }
+        let query = &query;
+        let rows = with_retry("load_metric_history", || async move {
+            query
+                .clone()
+                .all(connection)
+                .await
+                .context("Failed to query metric history")
+        })
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| MetricSample {
+                tunnel_id: row.tunnel_id,
+                timestamp: row.timestamp as u64,
+                tx_bytes: row.tx_bytes as u64,
+                rx_bytes: row.rx_bytes as u64,
+                latency_ms: row.latency_ms.map(|ms| ms as u64),
+            })
+            .collect())
+    }
+
+    /// 按隧道汇总 `[since, until)` 内的流量采样 (unix 毫秒)
+    pub async fn sum_traffic(since: u64, until: u64) -> Result<Vec<TrafficTotal>> {
+        let connection = DB_POOL.get().context("Failed to get DB pool")?;
+        let rows = with_retry("sum_traffic", || async move {
+            connection
+                .query_all(Statement::from_sql_and_values(
+                    DbBackend::Sqlite,
+                    "SELECT tunnel_id, SUM(tx_bytes) AS tx_bytes, SUM(rx_bytes) AS rx_bytes \
+                     FROM tunnel_metrics_history \
+                     WHERE timestamp >= ? AND timestamp < ? \
+                     GROUP BY tunnel_id",
+                    [(since as i64).into(), (until as i64).into()],
+                ))
+                .await
+                .context("Failed to sum traffic")
+        })
+        .await?;
+
+        rows.into_iter()
+            .map(|row| -> Result<TrafficTotal> {
+                Ok(TrafficTotal {
+                    tunnel_id: row.try_get("", "tunnel_id")?,
+                    tx_bytes: row.try_get::<i64>("", "tx_bytes")?.max(0) as u64,
+                    rx_bytes: row.try_get::<i64>("", "rx_bytes")?.max(0) as u64,
+                })
+            })
+            .collect()
+    }
+
+    /// 写入一条隧道事件，并清理超过保留期的旧事件
+    pub async fn insert_tunnel_event(event: &TunnelEvent, retain_after: u64) -> Result<()> {
+        let connection = DB_POOL.get().context("Failed to get DB pool")?;
+        let _write = hold_off_maintenance().await;
+
+        let model = tunnel_events::ActiveModel {
+            tunnel_id: Set(event.tunnel_id.clone()),
+            kind: Set(event.kind.clone()),
+            detail: Set(event.detail.clone()),
+            timestamp: Set(event.timestamp as i64),
+            ..Default::default()
+        };
+        let model = &model;
+        with_retry("insert_tunnel_event", || async move {
+            TunnelEvents::insert(model.clone())
+                .exec(connection)
+                .await
+                .context("Failed to insert tunnel event")
+        })
+        .await?;
+
+        TunnelEvents::delete_many()
+            .filter(tunnel_events::Column::Timestamp.lt(retain_after as i64))
+            .exec(connection)
+            .await
+            .context("Failed to prune tunnel events")?;
+
+        Ok(())
+    }
+
+    /// 最近的隧道事件，按时间倒序
+    pub async fn load_recent_events(limit: u64) -> Result<Vec<TunnelEvent>> {
+        let connection = DB_POOL.get().context("Failed to get DB pool")?;
+
+        let rows = with_retry("load_recent_events", || async move {
+            TunnelEvents::find()
+                .order_by_desc(tunnel_events::Column::Timestamp)
+                .order_by_desc(tunnel_events::Column::Id)
+                .limit(limit)
+                .all(connection)
+                .await
+                .context("Failed to query tunnel events")
+        })
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| TunnelEvent {
+                id: row.id,
+                tunnel_id: row.tunnel_id,
+                kind: row.kind,
+                detail: row.detail,
+                timestamp: row.timestamp as u64,
+            })
+            .collect())
+    }
+
+    pub async fn delete_metric_history(tunnel_id: &str) -> Result<()> {
+        let connection = DB_POOL.get().context("Failed to get DB pool")?;
+        let _write = hold_off_maintenance().await;
+        MetricsHistory::delete_many()
+            .filter(metrics_history::Column::TunnelId.eq(tunnel_id))
+            .exec(connection)
+            .await
+            .context("Failed to delete metric history")?;
+        Ok(())
+    }
+
+    /// 把一个采样周期内的连接数增量累加到持久化的计数
+    pub async fn add_tunnel_counters(deltas: &[TunnelCountersModel]) -> Result<()> {
+        let connection = DB_POOL.get().context("Failed to get DB pool")?;
+        let _write = hold_off_maintenance().await;
+        for delta in deltas {
+            connection
+                .execute(Statement::from_sql_and_values(
+                    DbBackend::Sqlite,
+                    "INSERT INTO tunnel_counters (tunnel_id, connections, connection_errors) \
+                     VALUES (?, ?, ?) \
+                     ON CONFLICT(tunnel_id) DO UPDATE SET \
+                     connections = connections + excluded.connections, \
+                     connection_errors = connection_errors + excluded.connection_errors",
+                    [
+                        delta.tunnel_id.clone().into(),
+                        (delta.connections as i64).into(),
+                        (delta.connection_errors as i64).into(),
+                    ],
+                ))
+                .await
+                .context("Failed to update tunnel counters")?;
+        }
+        Ok(())
+    }
+
+    pub async fn load_tunnel_counters(tunnel_id: &str) -> Result<TunnelCountersModel> {
+        let connection = DB_POOL.get().context("Failed to get DB pool")?;
+        let counters = TunnelCounters::find_by_id(tunnel_id.to_string())
+            .one(connection)
+            .await
+            .context("Failed to load tunnel counters")?;
+        Ok(counters
+            .map(|c| TunnelCountersModel {
+                tunnel_id: c.tunnel_id,
+                connections: c.connections as u64,
+                connection_errors: c.connection_errors as u64,
+            })
+            .unwrap_or_else(|| TunnelCountersModel {
+                tunnel_id: tunnel_id.to_string(),
+                ..Default::default()
+            }))
+    }
+
+    pub async fn delete_tunnel_counters(tunnel_id: &str) -> Result<()> {
+        let connection = DB_POOL.get().context("Failed to get DB pool")?;
+        let _write = hold_off_maintenance().await;
+        TunnelCounters::delete_many()
+            .filter(tunnel_counters::Column::TunnelId.eq(tunnel_id))
+            .exec(connection)
+            .await
+            .context("Failed to delete tunnel counters")?;
+        Ok(())
+    }
+
+    /// 写入主机公钥，已存在的相同条目跳过，返回新增数量
+    pub async fn insert_known_hosts(hosts: &[KnownHost]) -> Result<u64> {
+        let connection = DB_POOL.get().context("Failed to get DB pool")?;
+        let _write = hold_off_maintenance().await;
+        let added_at = chrono::Utc::now().timestamp_millis();
+        let mut inserted = 0;
+        for host in hosts {
+            let model = known_hosts::ActiveModel {
+                host_pattern: Set(host.host_pattern.clone()),
+                key_type: Set(host.key_type.clone()),
+                key_base64: Set(host.key_base64.clone()),
+                added_at: Set(added_at),
+                ..Default::default()
+            };
+            inserted += KnownHosts::insert(model)
+                .on_conflict(
+                    OnConflict::columns([
+                        known_hosts::Column::HostPattern,
+                        known_hosts::Column::KeyType,
+                        known_hosts::Column::KeyBase64,
+                    ])
+                    .do_nothing()
+                    .to_owned(),
+                )
+                .exec_without_returning(connection)
+                .await
+                .context("Failed to insert known host")?;
+        }
+        Ok(inserted)
+    }
+
+    /// 所有主机公钥，按添加顺序
+    pub async fn load_known_hosts() -> Result<Vec<KnownHost>> {
+        let connection = DB_POOL.get().context("Failed to get DB pool")?;
+        let hosts = KnownHosts::find()
+            .order_by_asc(known_hosts::Column::Id)
+            .all(connection)
+            .await
+            .context("Failed to load known hosts")?;
+        Ok(hosts
+            .into_iter()
+            .map(|h| KnownHost {
+                host_pattern: h.host_pattern,
+                key_type: h.key_type,
+                key_base64: h.key_base64,
+            })
+            .collect())
+    }
+
+    /// 记录一次看到的主机公钥
+    ///
+    /// 同类型的上一个指纹不同时返回它，首次见到该主机或指纹未变时返回 None。
+    pub async fn record_host_key(
+        host: &str,
+        port: u16,
+        key_type: &str,
+        fingerprint: &str,
+    ) -> Result<Option<HostKeyRecord>> {
+        let connection = DB_POOL.get().context("Failed to get DB pool")?;
+        let _write = hold_off_maintenance().await;
+        let now = chrono::Utc::now().timestamp_millis();
+
+        let previous = HostKeyHistory::find()
+            .filter(host_key_history::Column::Host.eq(host))
+            .filter(host_key_history::Column::Port.eq(port))
+            .filter(host_key_history::Column::KeyType.eq(key_type))
+            .order_by_desc(host_key_history::Column::LastSeen)
+            .one(connection)
+            .await
+            .context("Failed to load host key history")?;
+
+        let model = host_key_history::ActiveModel {
+            host: Set(host.to_string()),
+            port: Set(port),
+            key_type: Set(key_type.to_string()),
+            fingerprint: Set(fingerprint.to_string()),
+            first_seen: Set(now),
+            last_seen: Set(now),
+            ..Default::default()
+        };
+        HostKeyHistory::insert(model)
+            .on_conflict(
+                OnConflict::columns([
+                    host_key_history::Column::Host,
+                    host_key_history::Column::Port,
+                    host_key_history::Column::Fingerprint,
+                ])
+                .update_column(host_key_history::Column::LastSeen)
+                .to_owned(),
+            )
+            .exec_without_returning(connection)
+            .await
+            .context("Failed to record host key")?;
+
+        Ok(previous
+            .filter(|previous| previous.fingerprint != fingerprint)
+            .map(host_key_record))
+    }
+
+    /// 主机出现过的所有公钥指纹，按首次出现时间排序
+    pub async fn load_host_key_history(host: &str, port: u16) -> Result<Vec<HostKeyRecord>> {
+        let connection = DB_POOL.get().context("Failed to get DB pool")?;
+        let records = HostKeyHistory::find()
+            .filter(host_key_history::Column::Host.eq(host))
+            .filter(host_key_history::Column::Port.eq(port))
+            .order_by_asc(host_key_history::Column::FirstSeen)
+            .all(connection)
+            .await
+            .context("Failed to load host key history")?;
+        Ok(records.into_iter().map(host_key_record).collect())
+    }
+
+    /// 检查并补齐 tunnels_v2 缺失的列，返回补上的列名
+    pub async fn repair_schema() -> Result<Vec<String>> {
+        let connection = DB_POOL.get().context("Failed to get DB pool")?;
+        let _write = hold_off_maintenance().await;
+        repair::ensure_columns(connection, "tunnels_v2", repair::TUNNEL_COLUMNS).await
+    }
+
+    /// 通过 `VACUUM INTO` 将数据库一致地备份到目标路径，返回备份文件大小
+    ///
+    /// 备份期间隧道的指标、事件等写入会等待，不会因数据库被占用而失败。
+    pub async fn backup(dest: &Path) -> Result<u64> {
+        let _guard = MAINTENANCE_LOCK
+            .try_lock()
+            .map_err(|_| anyhow!("Another database maintenance task is running"))?;
+        let _writes = WRITE_GATE.write().await;
+
+        if dest.exists() {
+            return Err(anyhow!(
+                "Backup destination already exists: {}",
+                dest.display()
+            ));
+        }
+
+        info!("Backing up database to {}", dest.display());
+        let connection = DB_POOL.get().context("Failed to get DB pool")?;
+        let sql = format!(
+            "VACUUM INTO '{}'",
+            dest.display().to_string().replace('\'', "''")
+        );
+        let sql = &sql;
+        with_retry("backup_database", || async move {
+            connection
+                .execute_unprepared(sql)
+                .await
+                .context("Failed to back up database")
+        })
+        .await?;
+
+        let size = std::fs::metadata(dest)
+            .context("Failed to read backup file")?
+            .len();
+        info!("Database backup written ({} bytes)", size);
+        Ok(size)
+    }
+
+    /// 通过 `VACUUM` 回收空闲页，返回压缩后的数据库文件大小
+    ///
+    /// 与备份一样，压缩期间的写入会等待压缩结束。
+    pub async fn compact() -> Result<u64> {
+        let _guard = MAINTENANCE_LOCK
+            .try_lock()
+            .map_err(|_| anyhow!("Another database maintenance task is running"))?;
+        let _writes = WRITE_GATE.write().await;
+
+        let db_path = DB_PATH.get().context("Database not initialized")?;
+        let before = std::fs::metadata(db_path).map(|m| m.len()).unwrap_or(0);
+
         let connection = DB_POOL.get().context("Failed to get DB pool")?;
-        let result = TunnelConfig::find_by_id(id).one(connection).await?;
+        with_retry("compact_database", || async move {
+            connection
+                .execute_unprepared("VACUUM")
+                .await
+                .context("Failed to compact database")
+        })
+        .await?;
 
-        Ok(result)
+        let after = std::fs::metadata(db_path)
+            .context("Failed to read database file")?
+            .len();
+        info!("Database compacted: {} -> {} bytes", before, after);
+        Ok(after)
     }
 }
 
+/// 写入前调用，备份或压缩进行中时等待其结束
+async fn hold_off_maintenance() -> tokio::sync::RwLockReadGuard<'static, ()> {
+    WRITE_GATE.read().await
+}
+
+/// 端口映射以 JSON 保存，解析失败时忽略并记录日志
+fn parse_port_map(id: &str, json: Option<&str>) -> Vec<models::PortMapping> {
+    let Some(json) = json else {
+        return Vec::new();
+    };
+    serde_json::from_str(json).unwrap_or_else(|e| {
+        warn!("Ignoring invalid port map of tunnel {}: {}", id, e);
+        Vec::new()
+    })
+}
+
+/// 额外的 ssh 选项以 JSON 保存，解析失败时忽略并记录日志
+fn parse_extra_ssh_options(id: &str, json: Option<&str>) -> Vec<String> {
+    let Some(json) = json else {
+        return Vec::new();
+    };
+    serde_json::from_str(json).unwrap_or_else(|e| {
+        warn!("Ignoring invalid extra ssh options of tunnel {}: {}", id, e);
+        Vec::new()
+    })
+}
+
+/// 诊断命令白名单以 JSON 保存，未设置或解析失败时使用默认列表
+fn parse_diagnostic_commands(json: Option<&str>) -> Vec<String> {
+    let Some(json) = json else {
+        return models::default_diagnostic_commands();
+    };
+    serde_json::from_str(json).unwrap_or_else(|e| {
+        warn!("Ignoring invalid diagnostic command allow-list: {}", e);
+        models::default_diagnostic_commands()
+    })
+}
+
+/// 时间段以 JSON 保存，解析失败时视为未设置
+fn parse_schedule(id: &str, json: Option<&str>) -> Option<models::TunnelSchedule> {
+    serde_json::from_str(json?)
+        .map_err(|e| warn!("Ignoring invalid schedule of tunnel {}: {}", id, e))
+        .ok()
+}
+
 async fn run_migrations(db_path: &std::path::Path) -> Result<()> {
     info!("Running database migrations");
 
@@ -266,3 +892,191 @@ async fn run_migrations(db_path: &std::path::Path) -> Result<()> {
     info!("Database migrations complete");
     Ok(())
 }
+
+fn host_key_record(model: host_key_history::Model) -> HostKeyRecord {
+    HostKeyRecord {
+        host: model.host,
+        port: model.port,
+        key_type: model.key_type,
+        fingerprint: model.fingerprint,
+        first_seen: model.first_seen as u64,
+        last_seen: model.last_seen as u64,
+    }
+}
+
+/// 全部隧道行，缓存失效时重新查询
+async fn tunnel_rows() -> Result<Arc<Vec<tunnel_config::Model>>> {
+    let generation = {
+        let cache = TUNNEL_CACHE.lock().unwrap();
+        if let Some(rows) = &cache.rows {
+            return Ok(rows.clone());
+        }
+        cache.generation
+    };
+
+    let connection = DB_POOL.get().context("Failed to get DB pool")?;
+    let rows = with_retry("load_tunnels", || async move {
+        TunnelConfig::find()
+            .all(connection)
+            .await
+            .map_err(anyhow::Error::from)
+    })
+    .await
+    .map_err(|e| {
+        error!("Database query failed for tunnels: {:?}", e);
+        e
+    })?;
+
+    let rows = Arc::new(rows);
+    let mut cache = TUNNEL_CACHE.lock().unwrap();
+    if cache.generation == generation {
+        cache.rows = Some(rows.clone());
+    }
+    Ok(rows)
+}
+
+/// 写入隧道表后调用，无论写入是否成功
+fn invalidate_tunnel_cache() {
+    let mut cache = TUNNEL_CACHE.lock().unwrap();
+    cache.generation += 1;
+    cache.rows = None;
+}
+
+fn tunnel_active_model(tunnel: &TunnelConfigModel) -> Result<tunnel_config::ActiveModel> {
+    Ok(tunnel_config::ActiveModel {
+        id: Set(tunnel.id.clone()),
+        name: Set(tunnel.name.clone()),
+        mode: Set(tunnel.mode.clone()),
+        ssh_host: Set(tunnel.ssh_host.clone()),
+        ssh_port: Set(tunnel.ssh_port),
+        ssh_username: Set(tunnel.ssh_username.clone()),
+        auth_type: Set(tunnel.auth_type.clone()),
+        ssh_password: Set(tunnel.ssh_password.clone()),
+        ssh_key_path: Set(tunnel.ssh_key_path.clone()),
+        ssh_key_passphrase: Set(tunnel.ssh_key_passphrase.clone()),
+        identities_only: Set(tunnel.identities_only),
+        source_addr: Set(tunnel.source_addr.clone()),
+        bypass_proxy: Set(tunnel.bypass_proxy),
+        auth_fallback: Set(tunnel.auth_fallback),
+        jump_host: Set(tunnel.jump_host.clone()),
+        jump_port: Set(tunnel.jump_port),
+        jump_user: Set(tunnel.jump_user.clone()),
+        jump_auth: Set(tunnel.jump_auth.clone()),
+        forward_type: Set(if tunnel.mode == "docker" {
+            "container".to_string()
+        } else if tunnel.mode == "socks" {
+            "dynamic".to_string()
+        } else if tunnel.direction.as_deref() == Some("remote") {
+            "remote".to_string()
+        } else {
+            "direct".to_string()
+        }),
+        local_port: Set(tunnel.local_port),
+        auto_remap_port: Set(tunnel.auto_remap_port),
+        target_host: Set(tunnel.target_host.clone()),
+        target_port: Set(tunnel.target_port),
+        target_resolver_command: Set(tunnel.target_resolver_command.clone()),
+        proxy_protocol: Set(tunnel.proxy_protocol.clone()),
+        io_buffer_size: Set(tunnel.io_buffer_size),
+        keepalive_idle_secs: Set(tunnel.keepalive_idle_secs),
+        fail_fast: Set(tunnel.fail_fast),
+        target_check_interval_secs: Set(tunnel.target_check_interval_secs),
+        target_unreachable_action: Set(tunnel.target_unreachable_action.clone()),
+        max_connections: Set(tunnel.max_connections),
+        queue_timeout_secs: Set(tunnel.queue_timeout_secs),
+        pause_when_locked: Set(tunnel.pause_when_locked),
+        dscp: Set(tunnel.dscp),
+        port_map: Set(if tunnel.port_map.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_string(&tunnel.port_map)?)
+        }),
+        container_name: Set(tunnel.container_name.clone()),
+        container_port: Set(tunnel.container_port),
+        container_runtime: Set(tunnel.container_runtime.clone()),
+        docker_forward_via: Set(tunnel.docker_forward_via.clone()),
+        wait_for_healthy: Set(tunnel.wait_for_healthy),
+        remote_shell: Set(tunnel.remote_shell.clone()),
+        control_path: Set(tunnel.control_path.clone()),
+        backend: Set(tunnel.backend.clone()),
+        extra_ssh_options: Set(if tunnel.extra_ssh_options.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_string(&tunnel.extra_ssh_options)?)
+        }),
+        auto_reconnect: Set(tunnel.auto_reconnect),
+        on_failure_command: Set(tunnel.on_failure_command.clone()),
+        check_command: Set(tunnel.check_command.clone()),
+        wol_mac: Set(tunnel.wol_mac.clone()),
+        schedule: Set(tunnel
+            .schedule
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()?),
+        // 运行状态由 set_was_running 单独维护，保存配置时不覆盖
+        was_running: NotSet,
+    })
+}
+
+async fn upsert_tunnel<C: ConnectionTrait>(
+    connection: &C,
+    model: tunnel_config::ActiveModel,
+) -> Result<(), sea_orm::DbErr> {
+    TunnelConfig::insert(model)
+        .on_conflict(
+            OnConflict::column(tunnel_config::Column::Id)
+                .update_columns([
+                    tunnel_config::Column::Name,
+                    tunnel_config::Column::Mode,
+                    tunnel_config::Column::SshHost,
+                    tunnel_config::Column::SshPort,
+                    tunnel_config::Column::SshUsername,
+                    tunnel_config::Column::AuthType,
+                    tunnel_config::Column::SshPassword,
+                    tunnel_config::Column::SshKeyPath,
+                    tunnel_config::Column::SshKeyPassphrase,
+                    tunnel_config::Column::IdentitiesOnly,
+                    tunnel_config::Column::SourceAddr,
+                    tunnel_config::Column::BypassProxy,
+                    tunnel_config::Column::AuthFallback,
+                    tunnel_config::Column::JumpHost,
+                    tunnel_config::Column::JumpPort,
+                    tunnel_config::Column::JumpUser,
+                    tunnel_config::Column::JumpAuth,
+                    tunnel_config::Column::LocalPort,
+                    tunnel_config::Column::AutoRemapPort,
+                    tunnel_config::Column::TargetHost,
+                    tunnel_config::Column::TargetPort,
+                    tunnel_config::Column::TargetResolverCommand,
+                    tunnel_config::Column::ProxyProtocol,
+                    tunnel_config::Column::PortMap,
+                    tunnel_config::Column::IoBufferSize,
+                    tunnel_config::Column::KeepaliveIdleSecs,
+                    tunnel_config::Column::FailFast,
+                    tunnel_config::Column::TargetCheckIntervalSecs,
+                    tunnel_config::Column::TargetUnreachableAction,
+                    tunnel_config::Column::MaxConnections,
+                    tunnel_config::Column::QueueTimeoutSecs,
+                    tunnel_config::Column::PauseWhenLocked,
+                    tunnel_config::Column::Dscp,
+                    tunnel_config::Column::ContainerName,
+                    tunnel_config::Column::ContainerPort,
+                    tunnel_config::Column::ContainerRuntime,
+                    tunnel_config::Column::DockerForwardVia,
+                    tunnel_config::Column::WaitForHealthy,
+                    tunnel_config::Column::RemoteShell,
+                    tunnel_config::Column::ControlPath,
+                    tunnel_config::Column::Backend,
+                    tunnel_config::Column::ExtraSshOptions,
+                    tunnel_config::Column::AutoReconnect,
+                    tunnel_config::Column::OnFailureCommand,
+                    tunnel_config::Column::CheckCommand,
+                    tunnel_config::Column::WolMac,
+                    tunnel_config::Column::Schedule,
+                ])
+                .to_owned(),
+        )
+        .exec(connection)
+        .await?;
+    Ok(())
+}
@@ -1,32 +1,197 @@
 use serde::{Deserialize, Serialize};
 
+fn default_true() -> bool {
+    true
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TunnelConfig {
     pub id: String,
     pub name: String,
-    pub mode: String, // "standard" | "docker"
+    pub mode: String, // "standard" | "docker" | "socks"
+    // Standard mode: "local" (default, ssh -L) or "remote" (ssh -R), stored in forward_type
+    pub direction: Option<String>,
 
     // SSH Connection
     pub ssh_host: String,
     pub ssh_port: u16,
     pub ssh_username: String,
-    pub auth_type: String, // "password" | "key"
+    pub auth_type: String, // "password" | "key" | "agent"
     pub ssh_password: Option<String>,
     pub ssh_key_path: Option<String>,
+    // 加密私钥的口令，为空时按未加密读取
+    #[serde(default)]
+    pub ssh_key_passphrase: Option<String>,
+    // false 时在配置的密钥失败后继续尝试 ~/.ssh 下的默认密钥
+    #[serde(default = "default_true")]
+    pub identities_only: bool,
+    // Local IP the outgoing SSH connection binds to, None lets the OS pick the route
+    pub source_addr: Option<String>,
+    // Connect directly instead of through the detected system proxy
+    #[serde(default)]
+    pub bypass_proxy: bool,
+    // 配置的认证方式被拒绝时尝试其它已保存的凭据
+    #[serde(default)]
+    pub auth_fallback: bool,
+    // 跳板机 (ssh -J)，只支持一跳；端口默认 22，用户默认与 ssh_username 相同
+    pub jump_host: Option<String>,
+    pub jump_port: Option<u16>,
+    pub jump_user: Option<String>,
+    // 跳板机的认证方式，None 时与隧道相同，凭据使用隧道保存的密码或私钥
+    pub jump_auth: Option<String>,
 
     // Forwarding
     pub local_port: Option<u16>,
+    // Use the next free port when local_port is taken, the saved port is kept
+    #[serde(default)]
+    pub auto_remap_port: bool,
     pub target_host: Option<String>,
     pub target_port: Option<u16>,
+    // Remote command printing "host:port", run on every connect and used instead of target_host/port
+    pub target_resolver_command: Option<String>,
+    pub proxy_protocol: Option<String>, // "v1" | "v2", PROXY header sent to the target
+    // Extra local port ranges forwarded to consecutive remote ports on the target host
+    #[serde(default)]
+    pub port_map: Vec<PortMapping>,
+    // Read buffer size in bytes for each forwarded connection, None uses 64KB
+    pub io_buffer_size: Option<u32>,
+    // Idle seconds before keepalive probes are sent on a forwarded connection, None disables
+    pub keepalive_idle_secs: Option<u32>,
+    // Open a probe channel to the target on start and fail the start when it is unreachable
+    #[serde(default)]
+    pub fail_fast: bool,
+    // Seconds between target reachability probes while running, None disables the check
+    pub target_check_interval_secs: Option<u32>,
+    // "mark" (default) shows the tunnel as unstable, "reconnect" drops the session and reconnects
+    pub target_unreachable_action: Option<String>,
+    // Concurrent forwarded connections allowed, extra ones wait in a queue; None is unlimited
+    pub max_connections: Option<u32>,
+    // Seconds a queued connection waits for a free slot before it is closed, None uses 10s
+    pub queue_timeout_secs: Option<u32>,
+    // Refuse new connections while the screen is locked, the SSH session stays up
+    #[serde(default)]
+    pub pause_when_locked: bool,
+    // DSCP code point (0-63) for the SSH connection and forwarded sockets, None leaves it unset
+    pub dscp: Option<u8>,
 
     // Docker Info
     pub container_name: Option<String>,
     pub container_port: Option<u16>,
+    pub container_runtime: Option<String>,
+    // Docker mode: "container_ip" (default) or "published_port" to use 127.0.0.1:<host port> on the server
+    pub docker_forward_via: Option<String>,
+    // Docker mode: wait for the container healthcheck before forwarding
+    #[serde(default)]
+    pub wait_for_healthy: bool,
+    // How remote commands run on the host: None uses the login shell, "none" runs them without a
+    // shell or sudo, any other value (e.g. "sh -c") wraps them
+    pub remote_shell: Option<String>, // None | "none" | "sh -c"
+
+    // Multiplexing: path to an existing OpenSSH ControlMaster socket
+    pub control_path: Option<String>,
+    // "auto" (default, same as russh), "russh" or "system_ssh" to run the forward through the ssh binary
+    pub backend: Option<String>,
+    // "Key=Value" options appended to the system ssh command line as -o, system_ssh backend only
+    #[serde(default)]
+    pub extra_ssh_options: Vec<String>,
+
+    // Reconnect: overrides AppSettings.auto_reconnect, None follows the global setting
+    pub auto_reconnect: Option<bool>,
+    // Local shell command run when the tunnel fails (debounced), None disables
+    pub on_failure_command: Option<String>,
+    // Command run on the SSH host by run_check_command, independent of the forward
+    pub check_command: Option<String>,
+    // MAC address woken from the SSH host before forwarding
+    pub wol_mac: Option<String>,
+
+    // Time window the tunnel may run in, started and stopped automatically; None is always allowed
+    #[serde(default)]
+    pub schedule: Option<TunnelSchedule>,
+}
+
+/// 活动记录中的一条隧道事件
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TunnelEvent {
+    pub id: i64,
+    pub tunnel_id: String,
+    pub kind: String,
+    pub detail: Option<String>,
+    pub timestamp: u64, // unix millis
+}
+
+/// 某台主机出现过的一个公钥指纹
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HostKeyRecord {
+    pub host: String,
+    pub port: u16,
+    pub key_type: String,
+    pub fingerprint: String,
+    pub first_seen: u64, // unix millis
+    pub last_seen: u64,  // unix millis
+}
+
+/// 固定的主机公钥，对应 OpenSSH known_hosts 中的一行
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct KnownHost {
+    /// 原样保存的主机列表，可能是 `[host]:port` 或散列后的 `|1|salt|hash`
+    pub host_pattern: String,
+    pub key_type: String,
+    pub key_base64: String,
 }
 
+/// 一个采样周期内的流量与延迟
 #[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MetricSample {
+    pub tunnel_id: String,
+    pub timestamp: u64, // unix millis
+    pub tx_bytes: u64,
+    pub rx_bytes: u64,
+    pub latency_ms: Option<u64>,
+}
+
+/// 一个隧道在某段时间内的流量合计
+#[derive(Debug, Clone)]
+pub struct TrafficTotal {
+    pub tunnel_id: String,
+    pub tx_bytes: u64,
+    pub rx_bytes: u64,
+}
+
+/// 隧道累计接受的连接数和失败数
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TunnelCounters {
+    pub tunnel_id: String,
+    pub connections: u64,
+    pub connection_errors: u64,
+}
+
+/// 允许隧道运行的时间段
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct TunnelSchedule {
+    // "mon".."sun", empty means every day
+    #[serde(default)]
+    pub days: Vec<String>,
+    // "HH:MM"; an end before the start means the window ends the next day
+    pub start: String,
+    pub end: String,
+    // None or "local" uses the system timezone, otherwise "UTC" or a fixed offset like "+08:00".
+    // Fixed offsets don't follow daylight saving time and zone names are rejected.
+    pub timezone: Option<String>,
+}
+
+/// `local_start..=local_end` 依次转发到 `remote_base` 起的远端端口
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct PortMapping {
+    pub local_start: u16,
+    pub local_end: u16,
+    pub remote_base: u16,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct AppSettings {
     pub launch_at_login: bool,
+    // Superseded by close_button_action, only read by the migration that introduced it
     pub minimize_to_tray_on_close: bool,
     pub keep_alive_interval: u32,
     pub default_ssh_key: Option<String>,
@@ -35,6 +200,39 @@ pub struct AppSettings {
     pub auto_reconnect: bool,
     pub theme: String,
     pub language: String,
+    // Local WebSocket metrics server, loopback only unless remote access is allowed
+    pub metrics_ws_enabled: bool,
+    pub metrics_ws_addr: String,
+    pub metrics_ws_allow_remote: bool,
+    // Tray left-click action: "show" | "toggle" | "menu" | "none"
+    pub tray_left_click: String,
+    // Log level kept across restarts, None uses the build default
+    pub log_level: Option<String>,
+    // Attempts for resolving the SSH host on connect, 1 disables the retry
+    pub dns_retry_attempts: u32,
+    // Read-only commands run_diagnostic_command may run, matched as whole lines
+    pub diagnostic_commands: Vec<String>,
+    // Seconds keepalives must keep failing before a session is marked failed, 0 is immediate
+    pub failure_grace_secs: u32,
+    // Window close button: "minimize" to the tray, "quit", or "ask" each time
+    pub close_button_action: String,
+}
+
+/// 默认的诊断命令白名单
+pub fn default_diagnostic_commands() -> Vec<String> {
+    [
+        "uptime",
+        "uname -a",
+        "hostname",
+        "whoami",
+        "date",
+        "df -h",
+        "free -m",
+        "ss -tln",
+        "docker ps",
+    ]
+    .map(str::to_string)
+    .to_vec()
 }
 
 impl Default for AppSettings {
@@ -49,6 +247,15 @@ impl Default for AppSettings {
             auto_reconnect: true,
             theme: "system".to_string(),
             language: "en".to_string(),
+            metrics_ws_enabled: false,
+            metrics_ws_addr: "127.0.0.1:9477".to_string(),
+            metrics_ws_allow_remote: false,
+            tray_left_click: "show".to_string(),
+            log_level: None,
+            dns_retry_attempts: 3,
+            diagnostic_commands: default_diagnostic_commands(),
+            failure_grace_secs: 0,
+            close_button_action: "minimize".to_string(),
         }
     }
 }
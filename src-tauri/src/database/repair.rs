@@ -0,0 +1,114 @@
+use anyhow::{Context, Result};
+use log::{info, warn};
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbBackend, Statement, Value};
+use std::collections::HashSet;
+
+/// `tunnels_v2` 在 001 中定义的列
+///
+/// 旧版本用不同的模型提前建过同名表时，`CREATE TABLE IF NOT EXISTS` 会直接跳过，
+/// 导致这些列缺失。补列时 NOT NULL 列必须带默认值。
+pub const TUNNEL_COLUMNS: &[(&str, &str)] = &[
+    ("name", "TEXT NOT NULL DEFAULT ''"),
+    ("mode", "TEXT NOT NULL DEFAULT 'standard'"),
+    ("ssh_host", "TEXT NOT NULL DEFAULT ''"),
+    ("ssh_port", "INTEGER NOT NULL DEFAULT 22"),
+    ("ssh_username", "TEXT NOT NULL DEFAULT ''"),
+    ("auth_type", "TEXT NOT NULL DEFAULT 'password'"),
+    ("ssh_password", "TEXT"),
+    ("ssh_key_path", "TEXT"),
+    ("forward_type", "TEXT NOT NULL DEFAULT 'direct'"),
+    ("local_port", "INTEGER"),
+    ("target_host", "TEXT"),
+    ("target_port", "INTEGER"),
+    ("container_name", "TEXT"),
+    ("container_port", "INTEGER"),
+];
+
+/// `app_settings` 的所有列，默认值与各迁移一致
+pub const SETTINGS_COLUMNS: &[(&str, &str)] = &[
+    ("launch_at_login", "BOOLEAN NOT NULL DEFAULT 0"),
+    ("minimize_to_tray_on_close", "BOOLEAN NOT NULL DEFAULT 1"),
+    ("keep_alive_interval", "INTEGER NOT NULL DEFAULT 60"),
+    ("default_ssh_key", "TEXT"),
+    ("strict_host_key_checking", "BOOLEAN NOT NULL DEFAULT 0"),
+    ("connection_timeout", "INTEGER NOT NULL DEFAULT 10"),
+    ("auto_reconnect", "BOOLEAN NOT NULL DEFAULT 1"),
+    ("theme", "TEXT NOT NULL DEFAULT 'system'"),
+    ("language", "TEXT NOT NULL DEFAULT 'en'"),
+    ("metrics_ws_enabled", "BOOLEAN NOT NULL DEFAULT 0"),
+    ("metrics_ws_addr", "TEXT NOT NULL DEFAULT '127.0.0.1:9477'"),
+    ("metrics_ws_allow_remote", "BOOLEAN NOT NULL DEFAULT 0"),
+    ("tray_left_click", "TEXT NOT NULL DEFAULT 'show'"),
+    ("log_level", "TEXT"),
+    ("dns_retry_attempts", "INTEGER NOT NULL DEFAULT 3"),
+    ("diagnostic_commands", "TEXT"),
+    ("failure_grace_secs", "INTEGER NOT NULL DEFAULT 0"),
+    ("close_button_action", "TEXT NOT NULL DEFAULT 'minimize'"),
+];
+
+/// 把值为 NULL 的必填列改为给定的默认值，其它列保持不变，返回修复的列名
+pub async fn backfill_nulls(
+    connection: &DatabaseConnection,
+    table: &str,
+    defaults: Vec<(&str, Value)>,
+) -> Result<Vec<String>> {
+    let mut repaired = Vec::new();
+    for (name, value) in defaults {
+        let result = connection
+            .execute(Statement::from_sql_and_values(
+                DbBackend::Sqlite,
+                format!("UPDATE {} SET {} = ? WHERE {} IS NULL", table, name, name),
+                [value],
+            ))
+            .await
+            .with_context(|| format!("Failed to backfill {}.{}", table, name))?;
+        if result.rows_affected() > 0 {
+            warn!("Column {}.{} was NULL, reset to the default", table, name);
+            repaired.push(name.to_string());
+        }
+    }
+    Ok(repaired)
+}
+
+/// 对比 `PRAGMA table_info` 补齐缺失的列，返回补上的列名
+pub async fn ensure_columns(
+    connection: &DatabaseConnection,
+    table: &str,
+    columns: &[(&str, &str)],
+) -> Result<Vec<String>> {
+    let rows = connection
+        .query_all(Statement::from_string(
+            DbBackend::Sqlite,
+            format!("PRAGMA table_info({})", table),
+        ))
+        .await
+        .with_context(|| format!("Failed to inspect table {}", table))?;
+
+    let existing: HashSet<String> = rows
+        .iter()
+        .filter_map(|row| row.try_get::<String>("", "name").ok())
+        .collect();
+
+    let mut repaired = Vec::new();
+    for (name, definition) in columns {
+        if existing.contains(*name) {
+            continue;
+        }
+
+        warn!("Table {} is missing column {}, adding it", table, name);
+        connection
+            .execute_unprepared(&format!(
+                "ALTER TABLE {} ADD COLUMN {} {}",
+                table, name, definition
+            ))
+            .await
+            .with_context(|| format!("Failed to add column {}.{}", table, name))?;
+        repaired.push(name.to_string());
+    }
+
+    if !repaired.is_empty() {
+        info!("Repaired table {}: added {:?}", table, repaired);
+    }
+
+    Ok(repaired)
+}
@@ -0,0 +1,239 @@
+use anyhow::{anyhow, Result};
+use log::{error, warn};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const MAX_ATTEMPTS: u32 = 3;
+const BASE_BACKOFF: Duration = Duration::from_millis(100);
+
+// 连续多次瞬时失败后熔断一段时间，直接返回 "database busy"
+const BREAKER_THRESHOLD: u32 = 5;
+const BREAKER_COOLDOWN: Duration = Duration::from_secs(10);
+
+static BREAKER: CircuitBreaker = CircuitBreaker::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DbErrorKind {
+    /// 文件被锁、连接池繁忙等，可以重试
+    Transient,
+    /// 数据库文件损坏，重试没有意义
+    Fatal,
+    Other,
+}
+
+pub fn classify(err: &anyhow::Error) -> DbErrorKind {
+    let message = format!("{:#}", err).to_lowercase();
+
+    if message.contains("database is locked")
+        || message.contains("database table is locked")
+        || message.contains("database is busy")
+        || message.contains("pool timed out")
+    {
+        DbErrorKind::Transient
+    } else if message.contains("malformed")
+        || message.contains("not a database")
+        || message.contains("corrupt")
+        || message.contains("disk i/o error")
+    {
+        DbErrorKind::Fatal
+    } else {
+        DbErrorKind::Other
+    }
+}
+
+/// 执行数据库操作，瞬时错误按指数退避重试
+pub async fn with_retry<T, F, Fut>(op: &str, mut f: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    if !BREAKER.allow(Instant::now()) {
+        return Err(anyhow!(
+            "Database busy: {} skipped after repeated failures, please try again shortly",
+            op
+        ));
+    }
+
+    let mut attempt = 1;
+    loop {
+        match f().await {
+            Ok(value) => {
+                BREAKER.record_success();
+                return Ok(value);
+            }
+            Err(e) => match classify(&e) {
+                DbErrorKind::Transient if attempt < MAX_ATTEMPTS => {
+                    let backoff = BASE_BACKOFF * 2u32.pow(attempt - 1);
+                    warn!(
+                        "{} hit a transient database error (attempt {}/{}), retrying in {:?}: {:#}",
+                        op, attempt, MAX_ATTEMPTS, backoff, e
+                    );
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                DbErrorKind::Transient => {
+                    BREAKER.record_failure(Instant::now());
+                    return Err(e.context("Database busy, please try again shortly"));
+                }
+                DbErrorKind::Fatal => {
+                    error!("{} failed with a fatal database error: {:#}", op, e);
+                    return Err(e.context("Database file appears to be corrupted"));
+                }
+                DbErrorKind::Other => return Err(e),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    failures: u32,
+    open_until: Option<Instant>,
+    half_open: bool,
+}
+
+/// 熔断器：连续失败达到阈值后打开，冷却结束后半开放行，半开时成功则关闭，失败则立即重新打开
+struct CircuitBreaker {
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    const fn new() -> Self {
+        Self {
+            state: Mutex::new(BreakerState {
+                failures: 0,
+                open_until: None,
+                half_open: false,
+            }),
+        }
+    }
+
+    /// 是否允许访问数据库，冷却结束时转为半开
+    fn allow(&self, now: Instant) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match state.open_until {
+            Some(until) if now < until => false,
+            Some(_) => {
+                state.open_until = None;
+                state.half_open = true;
+                true
+            }
+            None => true,
+        }
+    }
+
+    fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.failures = 0;
+        state.half_open = false;
+    }
+
+    fn record_failure(&self, now: Instant) {
+        let mut state = self.state.lock().unwrap();
+        state.failures += 1;
+        if state.half_open || state.failures >= BREAKER_THRESHOLD {
+            warn!(
+                "Database failed {} times in a row, pausing access for {:?}",
+                state.failures, BREAKER_COOLDOWN
+            );
+            *state = BreakerState {
+                open_until: Some(now + BREAKER_COOLDOWN),
+                ..BreakerState::default()
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn classify_separates_transient_and_fatal_errors() {
+        for message in [
+            "error returned from database: database is locked",
+            "Database table is locked: tunnels_v2",
+            "pool timed out while waiting for an open connection",
+        ] {
+            assert_eq!(
+                classify(&anyhow!(message.to_string())),
+                DbErrorKind::Transient
+            );
+        }
+        for message in [
+            "database disk image is malformed",
+            "file is not a database",
+            "disk I/O error",
+        ] {
+            assert_eq!(classify(&anyhow!(message.to_string())), DbErrorKind::Fatal);
+        }
+        assert_eq!(
+            classify(&anyhow!("UNIQUE constraint failed: tunnels_v2.id")),
+            DbErrorKind::Other
+        );
+        // 上下文链中的原因也参与分类
+        let wrapped = anyhow!("database is locked").context("Failed to save tunnel");
+        assert_eq!(classify(&wrapped), DbErrorKind::Transient);
+    }
+
+    #[test]
+    fn breaker_opens_after_threshold_and_closes_after_cooldown() {
+        let breaker = CircuitBreaker::new();
+        let now = Instant::now();
+        for _ in 1..BREAKER_THRESHOLD {
+            breaker.record_failure(now);
+            assert!(breaker.allow(now));
+        }
+        breaker.record_failure(now);
+        assert!(!breaker.allow(now));
+        assert!(!breaker.allow(now + BREAKER_COOLDOWN - Duration::from_millis(1)));
+
+        // 冷却结束后半开放行，一次成功即关闭，之后需要再次累计到阈值才会打开
+        let later = now + BREAKER_COOLDOWN;
+        assert!(breaker.allow(later));
+        breaker.record_success();
+        breaker.record_failure(later);
+        assert!(breaker.allow(later));
+    }
+
+    #[test]
+    fn half_open_breaker_reopens_on_first_failure() {
+        let breaker = CircuitBreaker::new();
+        let now = Instant::now();
+        for _ in 0..BREAKER_THRESHOLD {
+            breaker.record_failure(now);
+        }
+        let later = now + BREAKER_COOLDOWN;
+        assert!(breaker.allow(later));
+        breaker.record_failure(later);
+        assert!(!breaker.allow(later));
+        assert!(breaker.allow(later + BREAKER_COOLDOWN));
+    }
+
+    #[tokio::test]
+    async fn transient_errors_are_retried() {
+        let attempts = AtomicU32::new(0);
+        let result = with_retry("test", || async {
+            if attempts.fetch_add(1, Ordering::Relaxed) == 0 {
+                Err(anyhow!("database is locked"))
+            } else {
+                Ok(42)
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 42);
+        assert_eq!(attempts.load(Ordering::Relaxed), 2);
+
+        // 其它错误不重试
+        let attempts = AtomicU32::new(0);
+        let result: Result<()> = with_retry("test", || async {
+            attempts.fetch_add(1, Ordering::Relaxed);
+            Err(anyhow!("UNIQUE constraint failed"))
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::Relaxed), 1);
+    }
+}
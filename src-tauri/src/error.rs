@@ -1,26 +1,139 @@
-use serde::{Serialize, Serializer};
+use serde::Serialize;
+use std::fmt;
 
-// 1. 定义一个元组结构体包裹 anyhow::Error
+/// 错误类别，前端据此分支处理而不是匹配错误文本
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    Auth,
+    Network,
+    NotFound,
+    Validation,
+    Database,
+    Internal,
+}
+
+/// 带错误码的业务错误，放进 anyhow 错误链后由 CommandError 识别
 #[derive(Debug)]
-pub struct CommandError(anyhow::Error);
+pub struct AppError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl AppError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::NotFound, message)
+    }
+
+    pub fn validation(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Validation, message)
+    }
+
+    pub fn auth(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Auth, message)
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
 
-// 2. 实现 From trait，允许直接使用 `?` 操作符将 anyhow::Error 转换过来
+impl std::error::Error for AppError {}
+
+/// 返回给前端的错误：`{ code, message, detail }`
+#[derive(Debug, Serialize)]
+pub struct CommandError {
+    pub code: ErrorCode,
+    pub message: String,
+    /// 完整的错误链，和 message 相同时省略
+    pub detail: Option<String>,
+}
+
+// 允许直接使用 `?` 操作符将 anyhow::Error 转换过来
 impl From<anyhow::Error> for CommandError {
     fn from(err: anyhow::Error) -> Self {
-        CommandError(err)
+        let message = err.to_string();
+        let detail = format!("{:#}", err);
+        CommandError {
+            code: classify(&err),
+            detail: (detail != message).then_some(detail),
+            message,
+        }
     }
 }
 
-// 3. 实现 Serialize，将错误转化为字符串传给前端
-impl Serialize for CommandError {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
+impl From<AppError> for CommandError {
+    fn from(err: AppError) -> Self {
+        CommandError {
+            code: err.code,
+            message: err.message,
+            detail: None,
+        }
+    }
+}
+
+/// 优先使用错误链中显式标注的错误码，其次按错误类型和文本推断
+fn classify(err: &anyhow::Error) -> ErrorCode {
+    for cause in err.chain() {
+        if let Some(app_error) = cause.downcast_ref::<AppError>() {
+            return app_error.code;
+        }
+    }
+
+    for cause in err.chain() {
+        if cause.downcast_ref::<sea_orm::DbErr>().is_some()
+            || cause.downcast_ref::<sqlx::Error>().is_some()
+        {
+            return ErrorCode::Database;
+        }
+        if cause.downcast_ref::<russh::Error>().is_some() {
+            return ErrorCode::Network;
+        }
+        if let Some(io_error) = cause.downcast_ref::<std::io::Error>() {
+            use std::io::ErrorKind::*;
+            match io_error.kind() {
+                ConnectionRefused | ConnectionReset | ConnectionAborted | NotConnected
+                | AddrInUse | AddrNotAvailable | TimedOut | BrokenPipe | UnexpectedEof => {
+                    return ErrorCode::Network
+                }
+                NotFound => return ErrorCode::NotFound,
+                PermissionDenied => return ErrorCode::Auth,
+                _ => {}
+            }
+        }
+    }
+
+    let message = format!("{:#}", err).to_lowercase();
+    if message.contains("authenticat") || message.contains("permission denied") {
+        ErrorCode::Auth
+    } else if message.contains("not found") {
+        ErrorCode::NotFound
+    } else if message.contains("timed out")
+        || message.contains("connect")
+        || message.contains("resolve")
+        || message.contains("unreachable")
+    {
+        ErrorCode::Network
+    } else if message.contains("invalid")
+        || message.contains("missing")
+        || message.contains("unsupported")
+        || message.contains("must ")
+        || message.contains("is empty")
     {
-        // 这里可以选择只返回 error message，或者包含 chain
-        serializer.serialize_str(&self.0.to_string())
+        ErrorCode::Validation
+    } else {
+        ErrorCode::Internal
     }
 }
 
-// 4. 定义一个简化的 Result 类型别名
+// 定义一个简化的 Result 类型别名
 pub type CommandResult<T> = Result<T, CommandError>;
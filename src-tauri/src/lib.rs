@@ -1,46 +1,80 @@
 mod commands;
 mod database;
 mod error;
+mod logging;
 mod server;
 mod service;
 mod settings;
 mod state;
 
-use crate::commands::docker::fetch_containers;
-use crate::commands::settings::{get_settings, save_settings};
+use crate::commands::app::{answer_close_request, get_app_info, quit_app};
+use crate::commands::database::{
+    backup_database, compact_database, repair_database_schema, repair_settings,
+};
+use crate::commands::diagnostics::{
+    capture_trace, check_time_skew, create_diagnostic_bundle, get_auth_methods,
+    get_host_key_history, is_key_authorized, measure_link_quality, measure_tunnel_overhead,
+    probe_path_mtu, resolve_ssh_host, run_check_command, run_diagnostic_command, snapshot_metrics,
+    stop_remote_tail, tail_remote_file, test_all_tunnels, who_has_port,
+};
+use crate::commands::docker::{
+    cancel_discovery, check_container_port, check_target_reachable, fetch_containers,
+    fetch_remote_ports, get_container_ports_structured,
+};
+use crate::commands::known_hosts::{answer_host_key, export_known_hosts, import_known_hosts};
+use crate::commands::settings::{
+    get_autostart_state, get_log_level, get_maintenance_mode, get_settings, save_settings,
+    set_autostart, set_log_level, set_maintenance_mode,
+};
 use crate::commands::tunnel::{
-    delete_tunnel, get_tunnel_status, get_tunnels, save_tunnel, start_tunnel, stop_tunnel,
+    apply_auth_fallback, audit_key_permissions, batch_update, clear_all_failed,
+    clear_failed_tunnel, create_tunnel_from_uri, delete_tunnel, diff_tunnels, export_metrics_csv,
+    find_duplicate_tunnels, fix_key_permissions, get_active_bindings, get_activity_feed,
+    get_all_tunnel_statuses, get_failed_tunnels, get_recent_logs, get_tunnel_counters,
+    get_tunnel_latency, get_tunnel_status, get_tunnels, get_usage_summary, migrate_key_paths,
+    pause_stats, preview_tunnel, resume_stats, rotate_tunnel_key, save_tunnel,
+    shutdown_tunnel_debug, start_tunnel, stop_tunnel, to_ssh_command, update_running_tunnel,
 };
+use crate::server::maintenance;
 use crate::server::model::{TunnelMetric, TunnelState};
 use crate::service::tunnel::TunnelService;
 use crate::state::AppState;
 use log::debug;
 use std::collections::HashMap;
+use std::sync::Arc;
 use tauri::{
     image::Image,
     menu::{Menu, MenuItem, PredefinedMenuItem},
     tray::{MouseButton, TrayIconBuilder, TrayIconEvent},
     Emitter, Listener, Manager,
 };
-use tauri_plugin_log::{Target, TargetKind};
+use tauri_plugin_log::TargetKind;
 
-#[derive(serde::Deserialize, serde::Serialize, Default, Debug)]
+#[derive(serde::Deserialize, serde::Serialize, Default, Debug, Clone, PartialEq, Eq)]
 struct TrayStatusPayload {
     active_count: usize,
+    /// 已监听但还没有确认转发成功
+    listening_count: usize,
     unavailable_count: usize,
     error_count: usize,
+    /// 维护模式下不显示错误
+    maintenance: bool,
 }
 
 impl TrayStatusPayload {
     fn from_tunnel_metric_map<K>(map: &HashMap<K, TunnelMetric>) -> Self {
-        map.values()
+        let payload = map
+            .values()
             .fold(TrayStatusPayload::default(), |mut acc, metric| {
                 match metric.tunnel_state {
-                    // 只有 Healthy 算作 Active
+                    // 只有确认转发成功 (Ready) 的算作 Active
                     TunnelState::Running(_) => {
                         acc.active_count += 1;
                     }
-                    TunnelState::Error(_) => {
+                    TunnelState::Listening(_) => {
+                        acc.listening_count += 1;
+                    }
+                    TunnelState::Error(_) | TunnelState::Unstable(_) => {
                         acc.error_count += 1;
                     }
                     _ => {
@@ -48,7 +82,24 @@ impl TrayStatusPayload {
                     }
                 }
                 acc
-            })
+            });
+        TrayStatusPayload {
+            maintenance: maintenance::is_active(),
+            ..payload
+        }
+    }
+}
+
+/// 托盘左键可选的行为
+pub const TRAY_LEFT_CLICK_ACTIONS: [&str; 4] = ["show", "toggle", "menu", "none"];
+
+/// 窗口关闭按钮可选的行为
+pub const CLOSE_BUTTON_ACTIONS: [&str; 3] = ["minimize", "quit", "ask"];
+
+/// 设置变更后同步托盘左键是否弹出菜单
+pub fn apply_tray_left_click(app: &tauri::AppHandle, action: &str) {
+    if let Some(tray) = app.tray_by_id("tray") {
+        let _ = tray.set_show_menu_on_left_click(action == "menu");
     }
 }
 
@@ -61,21 +112,19 @@ pub fn run() {
             Some(vec![]),
         ))
         .setup(|app| {
-            let log_plugin = tauri_plugin_log::Builder::default();
-            let log_plugin = if cfg!(dev) {
-                log_plugin.level(log::LevelFilter::Debug)
-            } else {
-                log_plugin.level(log::LevelFilter::Info)
-            };
-            let log_plugin = log_plugin
-                .target(Target::new(TargetKind::Stdout))
-                .target(Target::new(TargetKind::Webview))
-                .target(Target::new(TargetKind::LogDir {
+            // 插件本身放行全部级别，由各 target 按配置级别过滤，便于抓取连接 trace
+            let log_plugin = tauri_plugin_log::Builder::default()
+                .level(log::LevelFilter::Trace)
+                .target(logging::standard_target(TargetKind::Stdout))
+                .target(logging::standard_target(TargetKind::Webview))
+                .target(logging::standard_target(TargetKind::LogDir {
                     file_name: Some("ciconia".to_string()),
                 }))
+                .target(logging::capture_target())
                 .build();
 
             app.handle().plugin(log_plugin)?;
+            logging::set_configured_level(logging::default_level());
 
             // Initialize App State
             #[cfg(debug_assertions)] // only include this code on debug builds
@@ -96,9 +145,21 @@ pub fn run() {
 
             let settings = tauri::async_runtime::block_on(async {
                 let settings = settings::SettingsManager::new().await;
-                settings
+                Arc::new(settings)
             });
-            let tunnel_service = TunnelService::new();
+            // 使用上次保存的日志级别
+            if let Some(level) = settings
+                .get_settings()
+                .log_level
+                .as_deref()
+                .and_then(logging::parse_level)
+            {
+                logging::set_configured_level(level);
+            }
+            server::dns::set_retry_attempts(settings.get_settings().dns_retry_attempts);
+            server::ssh::set_failure_grace(settings.get_settings().failure_grace_secs);
+            server::known_hosts::set_strict(settings.get_settings().strict_host_key_checking);
+            let tunnel_service = TunnelService::new(settings.clone());
 
             let app_state = AppState::new(tunnel_service.clone(), settings);
             let app_handle = app.handle();
@@ -108,6 +169,43 @@ pub fn run() {
                 let _ = tunnel_service
                     .monitor_health_status(&app_handle.clone())
                     .await;
+                if let Err(e) = tunnel_service.apply_metrics_server().await {
+                    log::error!("Failed to start metrics server: {:#}", e);
+                }
+            });
+
+            // 恢复上次关闭时仍在运行的隧道
+            let restore_service = tunnel_service.clone();
+            tauri::async_runtime::spawn(async move {
+                match restore_service.restore_running_tunnels().await {
+                    Ok(0) => {}
+                    Ok(count) => log::info!("Restored {} tunnel(s) from the last session", count),
+                    Err(e) => log::error!("Failed to restore running tunnels: {:#}", e),
+                }
+            });
+
+            // 严格模式下首次见到的主机公钥交给窗口确认
+            let prompt_handle = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                let mut prompts = server::known_hosts::subscribe();
+                loop {
+                    match prompts.recv().await {
+                        Ok(prompt) => {
+                            let _ = prompt_handle.emit("host-key-prompt", &prompt);
+                        }
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+
+            // 锁屏状态，供 pause_when_locked 的隧道使用
+            tauri::async_runtime::spawn(server::screen_lock::watch_screen_lock());
+
+            // 按时间段自动启停隧道
+            let schedule_service = tunnel_service.clone();
+            tauri::async_runtime::spawn(async move {
+                schedule_service.run_schedules().await;
             });
 
             app.manage(app_state);
@@ -143,7 +241,14 @@ pub fn run() {
 
             app.listen("update-tray-status", move |event| {
                 if let Ok(payload) = serde_json::from_str::<TrayStatusPayload>(event.payload()) {
-                    let text = if payload.error_count > 0 {
+                    let text = if payload.maintenance {
+                        // 维护期间预期会有失败，只显示中性的状态
+                        if payload.active_count > 0 {
+                            format!("🛠 Maintenance, {} Active", payload.active_count)
+                        } else {
+                            "🛠 Maintenance".to_string()
+                        }
+                    } else if payload.error_count > 0 {
                         if payload.active_count > 0 {
                             format!(
                                 "🔴 {} Active, {} Failed",
@@ -152,8 +257,15 @@ pub fn run() {
                         } else {
                             format!("🔴 {} Tunnels Failed", payload.error_count)
                         }
+                    } else if payload.active_count > 0 && payload.listening_count > 0 {
+                        format!(
+                            "🟢 {} Active, {} Listening",
+                            payload.active_count, payload.listening_count
+                        )
                     } else if payload.active_count > 0 {
                         format!("🟢 {} Active Tunnels", payload.active_count)
+                    } else if payload.listening_count > 0 {
+                        format!("🟡 {} Listening", payload.listening_count)
                     } else {
                         "⚪️ No Active Tunnels".to_string()
                     };
@@ -162,16 +274,19 @@ pub fn run() {
                 }
             });
 
+            let tray_left_click = app
+                .state::<AppState>()
+                .settings
+                .get_settings()
+                .tray_left_click;
             let icon = Image::from_bytes(include_bytes!("../icons/tray.png"))
                 .expect("failed to load gray icon");
             let _tray = TrayIconBuilder::with_id("tray")
                 .icon(icon)
                 .menu(&menu)
-                .show_menu_on_left_click(false)
+                .show_menu_on_left_click(tray_left_click == "menu")
                 .on_menu_event(|app, event| match event.id().as_ref() {
-                    "quit" => {
-                        app.exit(0);
-                    }
+                    "quit" => commands::app::request_quit(app),
                     "show" => {
                         if let Some(window) = app.get_webview_window("main") {
                             if window.is_visible().unwrap_or(false) {
@@ -205,9 +320,24 @@ pub fn run() {
                     } = event
                     {
                         let app = tray.app_handle();
-                        if let Some(window) = app.get_webview_window("main") {
-                            let _ = window.show();
-                            let _ = window.set_focus();
+                        let action = app
+                            .state::<AppState>()
+                            .settings
+                            .get_settings()
+                            .tray_left_click;
+                        let Some(window) = app.get_webview_window("main") else {
+                            return;
+                        };
+                        match action.as_str() {
+                            "toggle" if window.is_visible().unwrap_or(false) => {
+                                let _ = window.hide();
+                            }
+                            "show" | "toggle" => {
+                                let _ = window.show();
+                                let _ = window.set_focus();
+                            }
+                            // "menu" 由系统弹出菜单，"none" 不做处理
+                            _ => {}
                         }
                     }
                 })
@@ -220,22 +350,94 @@ pub fn run() {
             if let tauri::WindowEvent::CloseRequested { api, .. } = event {
                 let app_handle = window.app_handle();
                 let state = app_handle.state::<AppState>();
-                if state.settings.get_settings().minimize_to_tray_on_close {
-                    let _ = window.hide();
-                    api.prevent_close();
+                api.prevent_close();
+                match state.settings.get_settings().close_button_action.as_str() {
+                    // 关闭窗口即退出，有传输进行时先确认
+                    "quit" => commands::app::request_quit(app_handle),
+                    // 由前端询问，结果通过 answer_close_request 返回
+                    "ask" => {
+                        let _ = window.emit("close-requested", ());
+                    }
+                    _ => {
+                        let _ = window.hide();
+                    }
                 }
             }
         })
         .invoke_handler(tauri::generate_handler![
             get_tunnels,
             save_tunnel,
+            update_running_tunnel,
+            create_tunnel_from_uri,
             delete_tunnel,
+            migrate_key_paths,
+            audit_key_permissions,
+            fix_key_permissions,
+            rotate_tunnel_key,
+            apply_auth_fallback,
+            batch_update,
+            find_duplicate_tunnels,
+            diff_tunnels,
             start_tunnel,
             stop_tunnel,
+            get_failed_tunnels,
+            clear_failed_tunnel,
+            clear_all_failed,
+            shutdown_tunnel_debug,
             get_tunnel_status,
+            get_all_tunnel_statuses,
+            get_tunnel_latency,
+            pause_stats,
+            resume_stats,
+            get_active_bindings,
+            get_tunnel_counters,
+            export_metrics_csv,
+            get_recent_logs,
+            get_activity_feed,
+            get_usage_summary,
+            to_ssh_command,
+            preview_tunnel,
             fetch_containers,
+            fetch_remote_ports,
+            get_container_ports_structured,
+            check_target_reachable,
+            check_container_port,
+            cancel_discovery,
             get_settings,
-            save_settings
+            save_settings,
+            get_autostart_state,
+            set_autostart,
+            get_log_level,
+            set_log_level,
+            get_maintenance_mode,
+            set_maintenance_mode,
+            capture_trace,
+            probe_path_mtu,
+            get_auth_methods,
+            get_host_key_history,
+            measure_link_quality,
+            measure_tunnel_overhead,
+            resolve_ssh_host,
+            run_diagnostic_command,
+            run_check_command,
+            tail_remote_file,
+            stop_remote_tail,
+            check_time_skew,
+            is_key_authorized,
+            test_all_tunnels,
+            create_diagnostic_bundle,
+            snapshot_metrics,
+            who_has_port,
+            backup_database,
+            compact_database,
+            import_known_hosts,
+            export_known_hosts,
+            answer_host_key,
+            repair_database_schema,
+            repair_settings,
+            get_app_info,
+            answer_close_request,
+            quit_app
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,151 @@
+use log::LevelFilter;
+use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+use tauri_plugin_log::{Target, TargetKind};
+
+/// 单次抓取最多保留的字节数，超出部分丢弃
+const MAX_CAPTURE_BYTES: usize = 256 * 1024;
+
+/// 用户配置的日志级别，常规 target 按它过滤
+static CONFIGURED_LEVEL: AtomicUsize = AtomicUsize::new(LevelFilter::Info as usize);
+
+static CAPTURE: Lazy<CaptureBuffer> = Lazy::new(CaptureBuffer::default);
+
+#[derive(Default)]
+struct CaptureBuffer {
+    active: AtomicBool,
+    started_at: Mutex<Option<Instant>>,
+    lines: Mutex<Vec<String>>,
+    size: AtomicUsize,
+    truncated: AtomicBool,
+}
+
+impl CaptureBuffer {
+    fn push(&self, line: String) {
+        if self.size.fetch_add(line.len(), Ordering::Relaxed) + line.len() > MAX_CAPTURE_BYTES {
+            self.truncated.store(true, Ordering::Relaxed);
+            return;
+        }
+        self.lines.lock().unwrap().push(line);
+    }
+
+    fn elapsed_ms(&self) -> u128 {
+        self.started_at
+            .lock()
+            .unwrap()
+            .map(|start| start.elapsed().as_millis())
+            .unwrap_or_default()
+    }
+}
+
+fn level_from_usize(value: usize) -> LevelFilter {
+    match value {
+        0 => LevelFilter::Off,
+        1 => LevelFilter::Error,
+        2 => LevelFilter::Warn,
+        3 => LevelFilter::Info,
+        4 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+/// 未设置日志级别时的默认值，开发构建为 Debug
+pub fn default_level() -> LevelFilter {
+    if cfg!(dev) {
+        LevelFilter::Debug
+    } else {
+        LevelFilter::Info
+    }
+}
+
+/// 解析设置中的级别名称，大小写不敏感
+pub fn parse_level(level: &str) -> Option<LevelFilter> {
+    level.trim().parse().ok()
+}
+
+pub fn configured_level() -> LevelFilter {
+    level_from_usize(CONFIGURED_LEVEL.load(Ordering::Relaxed))
+}
+
+/// 设置常规日志级别，抓取进行中时不降低全局级别
+pub fn set_configured_level(level: LevelFilter) {
+    CONFIGURED_LEVEL.store(level as usize, Ordering::Relaxed);
+    if !CAPTURE.active.load(Ordering::Relaxed) {
+        log::set_max_level(level);
+    }
+}
+
+/// 常规输出 (stdout / webview / 文件)，只接收配置级别以内的日志
+pub fn standard_target(kind: TargetKind) -> Target {
+    Target::new(kind).filter(|metadata| metadata.level() <= configured_level())
+}
+
+/// 抓取专用 target，仅在抓取期间收集 russh 与本应用的日志
+pub fn capture_target() -> Target {
+    let dispatch = fern::Dispatch::new()
+        .level(LevelFilter::Trace)
+        .filter(|metadata| {
+            CAPTURE.active.load(Ordering::Relaxed)
+                && (metadata.target().starts_with("russh")
+                    || metadata.target().starts_with("app_lib"))
+        })
+        .chain(fern::Output::call(|record| {
+            CAPTURE.push(format!(
+                "+{}ms {:<5} [{}] {}",
+                CAPTURE.elapsed_ms(),
+                record.level(),
+                record.target(),
+                record.args()
+            ));
+        }));
+
+    Target::new(TargetKind::Dispatch(dispatch))
+}
+
+/// 一次日志抓取，期间全局级别临时提升到 Trace，结束 (drop) 时恢复
+///
+/// 同一时间只允许一个抓取；抓取期间其它连接产生的日志也会被收集。
+pub struct CaptureSession {
+    _private: (),
+}
+
+impl CaptureSession {
+    pub fn start() -> Option<Self> {
+        if CAPTURE
+            .active
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Relaxed)
+            .is_err()
+        {
+            return None;
+        }
+
+        CAPTURE.lines.lock().unwrap().clear();
+        CAPTURE.size.store(0, Ordering::Relaxed);
+        CAPTURE.truncated.store(false, Ordering::Relaxed);
+        *CAPTURE.started_at.lock().unwrap() = Some(Instant::now());
+        log::set_max_level(LevelFilter::Trace);
+
+        Some(Self { _private: () })
+    }
+
+    /// 结束抓取并返回收集到的日志
+    pub fn finish(self) -> String {
+        let mut lines = std::mem::take(&mut *CAPTURE.lines.lock().unwrap());
+        if CAPTURE.truncated.load(Ordering::Relaxed) {
+            lines.push(format!(
+                "... trace truncated at {} bytes",
+                MAX_CAPTURE_BYTES
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+impl Drop for CaptureSession {
+    fn drop(&mut self) {
+        CAPTURE.active.store(false, Ordering::Relaxed);
+        log::set_max_level(configured_level());
+    }
+}
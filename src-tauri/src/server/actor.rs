@@ -1,20 +1,65 @@
 use crate::database::entity::tunnel_config::Model as TunnelModel;
+use crate::server::control_master::{self, ControlMaster};
+use crate::server::events::{self, TunnelEventKind};
+use crate::server::failure_hook::FailureHook;
+use crate::server::logs::TunnelLogger;
+use crate::server::maintenance;
 use crate::server::model::{
-    SshConnectConfig, SshForwardConfig, TunnelCommand, TunnelMetric, TunnelState,
+    format_host_port, io_buffer_size_from, keepalive_idle_from, port_map_from, queue_timeout_from,
+    ActorTasks, ForwardDirection, SSHStatus, SshConnectConfig, SshForwardConfig, Traffic,
+    TunnelCommand, TunnelMetric, TunnelState,
+};
+use crate::server::preview::{self, TargetSource};
+use crate::server::proxy_protocol::ProxyProtocolVersion;
+use crate::server::remote_cmd::{
+    normalize_mac, ContainerRuntime, GetContainerHealthCmd, GetTargetReachableCmd, WakeOnLanCmd,
 };
-use crate::server::remote_cmd::GetContainerAddrCmd;
 use crate::server::ssh::Ssh;
+use crate::server::system_ssh::{self, SshBackend};
+use crate::server::throttle;
+use crate::settings::SettingsManager;
 use anyhow::anyhow;
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::io::AsyncReadExt;
 use tokio::sync::{mpsc, watch};
 use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+// 自动重连的退避区间
+const RECONNECT_BASE_DELAY: Duration = Duration::from_secs(2);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(60);
+// WoL 后等待目标启动的最长时间
+const WOL_WAIT: Duration = Duration::from_secs(60);
+const WOL_POLL_INTERVAL: Duration = Duration::from_secs(5);
+// 等待容器健康检查通过的最长时间
+const HEALTH_WAIT: Duration = Duration::from_secs(120);
+const HEALTH_POLL_INTERVAL: Duration = Duration::from_secs(2);
+// 服务器限流 (MaxStartups) 时的退避倍数和上限，立即重试只会再次被丢弃
+const THROTTLED_BACKOFF_FACTOR: u32 = 4;
+const THROTTLED_MAX_DELAY: Duration = Duration::from_secs(300);
+// 同一隧道记录意外来源事件的最短间隔
+const UNEXPECTED_SOURCE_INTERVAL: Duration = Duration::from_secs(60);
+// 系统 ssh 尚未确认能转发到目标时重新确认的间隔
+const SYSTEM_SSH_CONFIRM_INTERVAL: Duration = Duration::from_secs(10);
 
 pub struct TunnelActor {
     config: TunnelModel,
     cmd_rx: mpsc::Receiver<TunnelCommand>,
     metric_tx: watch::Sender<TunnelMetric>,
+    logger: TunnelLogger,
     ssh: Option<Ssh>,
+    control_forward: Option<(ControlMaster, SshForwardConfig)>,
     running_task: Option<JoinHandle<()>>,
+    // 主动停止时取消，用于区分正常关闭与意外断开
+    stop_token: CancellationToken,
+    settings: Arc<SettingsManager>,
+    reconnect_at: Option<Instant>,
+    reconnect_attempts: u32,
+    // 上次连接失败是否因为服务器在认证前断开 (限流)
+    throttled: bool,
+    failure_hook: Option<FailureHook>,
 }
 
 impl TunnelActor {
@@ -22,30 +67,124 @@ impl TunnelActor {
         config: TunnelModel,
         cmd_rx: mpsc::Receiver<TunnelCommand>,
         metric_tx: watch::Sender<TunnelMetric>,
+        logger: TunnelLogger,
+        settings: Arc<SettingsManager>,
     ) -> Self {
         Self {
-            config,
             cmd_rx,
             metric_tx,
+            logger,
             ssh: None,
+            control_forward: None,
             running_task: None,
+            stop_token: CancellationToken::new(),
+            settings,
+            failure_hook: FailureHook::new(config.on_failure_command.as_deref()),
+            reconnect_at: None,
+            reconnect_attempts: 0,
+            throttled: false,
+            config,
         }
     }
     pub async fn run(mut self) {
+        let mut metric_rx = self.metric_tx.subscribe();
+        let mut in_error = false;
         loop {
+            let reconnect_at = self.reconnect_at;
             tokio::select! {
                 Some(cmd) = self.cmd_rx.recv() => {
+                    // 手动操作会取消待执行的重连
+                    if matches!(
+                        cmd,
+                        TunnelCommand::Start
+                            | TunnelCommand::Stop
+                            | TunnelCommand::Remove
+                            | TunnelCommand::RemoveWithReport(_)
+                    ) {
+                        self.cancel_reconnect();
+                    }
                     match cmd {
                         TunnelCommand::Start => {
                             self.handle_start().await;
+                            if self.running_task.is_some() {
+                                self.record_started(TunnelEventKind::Started);
+                            }
                         }
                         TunnelCommand::Stop => {
+                            let was_running = self.running_task.is_some();
                             self.handle_stop().await;
+                            if was_running {
+                                events::record(&self.config.id, TunnelEventKind::Stopped, None);
+                            }
                         }
                         TunnelCommand::Remove => {
                             self.handle_stop().await;
                             break;
                         }
+                        TunnelCommand::RemoveWithReport(reply) => {
+                            let ssh = self.ssh.as_ref().map(Ssh::tasks);
+                            // 不 abort Metrics 任务，检查它能否随 stop_token 自行退出
+                            let metrics_task = self.running_task.take();
+                            self.handle_stop().await;
+                            let _ = reply.send(ActorTasks { metrics_task, ssh });
+                            break;
+                        }
+                        TunnelCommand::UpdateConfig(config) => {
+                            self.failure_hook =
+                                FailureHook::new(config.on_failure_command.as_deref());
+                            // 认证配置改变后不再提示保存回退的方式
+                            if config.auth_type != self.config.auth_type
+                                || config.ssh_key_path != self.config.ssh_key_path
+                            {
+                                self.metric_tx.send_modify(|s| s.auth_fallback = None);
+                            }
+                            self.config = *config;
+                            self.logger.info("Configuration updated");
+                        }
+                        TunnelCommand::PauseStats => {
+                            self.metric_tx.send_modify(|s| {
+                                s.stats_paused = true;
+                                s.window_traffic = Traffic::default();
+                            });
+                            self.logger.info("Traffic statistics paused");
+                        }
+                        TunnelCommand::ResumeStats => {
+                            self.metric_tx.send_modify(|s| s.stats_paused = false);
+                            self.logger.info("Traffic statistics resumed");
+                        }
+                    }
+                }
+
+                // 进入错误状态时执行失败钩子
+                Ok(()) = metric_rx.changed() => {
+                    let state = metric_rx.borrow_and_update().tunnel_state.clone();
+                    match state {
+                        TunnelState::Error(reason) if !in_error => {
+                            in_error = true;
+                            events::record(&self.config.id, TunnelEventKind::Failed, Some(reason.clone()));
+                            self.run_failure_hook(&reason);
+                        }
+                        TunnelState::Error(_) => {}
+                        _ => in_error = false,
+                    }
+                }
+
+                // 到达重连时间
+                _ = async {
+                    match reconnect_at {
+                        Some(deadline) => tokio::time::sleep_until(deadline).await,
+                        None => std::future::pending::<()>().await,
+                    }
+                }, if reconnect_at.is_some() => {
+                    self.reconnect_at = None;
+                    self.reconnect_attempts += 1;
+                    self.logger.info(format!("Reconnecting (attempt {})", self.reconnect_attempts));
+                    self.handle_start().await;
+                    if self.running_task.is_some() {
+                        self.record_started(TunnelEventKind::Reconnected);
+                        self.reconnect_attempts = 0;
+                    } else {
+                        self.schedule_reconnect();
                     }
                 }
 
@@ -59,10 +198,12 @@ impl TunnelActor {
                     }
                 }, if self.running_task.is_some() => {
                     // 任务意外结束
-                    self.metric_tx.send_modify(|s| s.tunnel_state = TunnelState::Error("Connection Dropped".into()));
+                    self.set_error("Connection Dropped");
                     self.running_task = None;
                     if let Some(ssh) = &self.ssh { ssh.shutdown(); }
                     self.ssh = None;
+                    self.control_forward = None;
+                    self.schedule_reconnect();
                 }
                 else => {
                     // 当没有任务运行时，继续循环等待命令
@@ -72,97 +213,226 @@ impl TunnelActor {
         }
     }
 
+    /// 启动成功后记录事件，重连时 detail 为尝试次数，否则为实际监听地址
+    fn record_started(&self, kind: TunnelEventKind) {
+        let detail = match kind {
+            TunnelEventKind::Reconnected => Some(self.reconnect_attempts.to_string()),
+            _ => self
+                .metric_tx
+                .borrow()
+                .local_addr
+                .map(|addr| addr.to_string()),
+        };
+        events::record(&self.config.id, kind, detail);
+    }
+
+    fn run_failure_hook(&mut self, reason: &str) {
+        if maintenance::is_active() {
+            if self.failure_hook.is_some() {
+                self.logger
+                    .info("Failure hook skipped, maintenance mode is on");
+            }
+            return;
+        }
+        if let Some(hook) = &mut self.failure_hook {
+            hook.trigger(&self.config.id, &self.config.name, reason, &self.logger);
+        }
+    }
+
+    /// 隧道配置优先，未设置时使用全局的 auto_reconnect
+    fn should_reconnect(&self) -> bool {
+        self.config
+            .auto_reconnect
+            .unwrap_or_else(|| self.settings.get_settings().auto_reconnect)
+    }
+
+    /// 按指数退避安排下一次重连
+    fn schedule_reconnect(&mut self) {
+        if !self.should_reconnect() {
+            return;
+        }
+
+        let delay = RECONNECT_BASE_DELAY
+            .saturating_mul(2u32.saturating_pow(self.reconnect_attempts.min(6)))
+            .min(RECONNECT_MAX_DELAY);
+        let delay = if self.throttled {
+            // 按隧道 id 错开，避免共用跳板机的隧道同时重试
+            let stagger = self.config.id.bytes().map(u64::from).sum::<u64>() % 10;
+            (delay.saturating_mul(THROTTLED_BACKOFF_FACTOR) + Duration::from_secs(stagger))
+                .min(THROTTLED_MAX_DELAY)
+        } else {
+            delay
+        };
+        if self.throttled {
+            self.logger.warn(format!(
+                "Server is throttling new connections, reconnecting in {:?}",
+                delay
+            ));
+        } else {
+            self.logger
+                .warn(format!("Connection lost, reconnecting in {:?}", delay));
+        }
+        self.reconnect_at = Some(Instant::now() + delay);
+    }
+
+    fn cancel_reconnect(&mut self) {
+        self.reconnect_at = None;
+        self.reconnect_attempts = 0;
+    }
+
+    /// 进入错误状态并记录日志
+    fn set_error(&self, reason: impl Into<String>) {
+        let reason = reason.into();
+        self.logger.error(format!("Tunnel failed: {}", reason));
+        self.metric_tx
+            .send_modify(|s| s.tunnel_state = TunnelState::Error(reason));
+    }
+
     async fn handle_start(&mut self) {
-        if self.ssh.is_some() || self.running_task.is_some() {
+        if self.ssh.is_some() || self.control_forward.is_some() || self.running_task.is_some() {
             println!("Tunnel already running, stopping first");
             self.handle_stop().await;
         }
 
-        self.metric_tx
-            .send_modify(|s| s.tunnel_state = TunnelState::Starting);
+        self.metric_tx.send_modify(|s| {
+            s.tunnel_state = TunnelState::Starting;
+            s.last_latency = None;
+            s.local_addr = None;
+            s.started_at = Some(Instant::now());
+            s.auth_fallback = None;
+        });
+        self.logger.info(format!(
+            "Starting tunnel '{}' via {}@{}:{}",
+            self.config.name, self.config.ssh_username, self.config.ssh_host, self.config.ssh_port
+        ));
+
+        // 0. 优先复用已有的 ControlMaster 连接，失败则回退到直接连接
+        if self.try_start_with_control_master().await {
+            return;
+        }
+
+        match SshBackend::parse(self.config.backend.as_deref()) {
+            Ok(SshBackend::SystemSsh) => {
+                self.start_with_system_ssh().await;
+                return;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                self.set_error(e.to_string());
+                return;
+            }
+        }
 
         // 1. 初始化 SSH
         let ssh_connect_config = match SshConnectConfig::try_from(&self.config) {
             Ok(cfg) => cfg,
             Err(e) => {
-                self.metric_tx
-                    .send_modify(|s| s.tunnel_state = TunnelState::Error(e.to_string()));
+                self.set_error(e.to_string());
                 return;
             }
         };
 
-        let ssh_res = Ssh::init(ssh_connect_config).await;
-        if let Err(e) = ssh_res {
-            self.metric_tx
-                .send_modify(|s| s.tunnel_state = TunnelState::Error(e.to_string()));
-            return;
+        let mut ssh_instance = match Ssh::init(ssh_connect_config).await {
+            Ok(ssh) => ssh,
+            Err(e) => {
+                self.throttled = throttle::is_pre_auth_disconnect(&e)
+                    && throttle::is_throttling(&self.config.ssh_host, self.config.ssh_port);
+                self.set_error(e.to_string());
+                return;
+            }
+        };
+        self.throttled = false;
+        self.logger.info("SSH session established");
+        if let Some(used) = &ssh_instance.auth_fallback {
+            self.logger.warn(format!(
+                "Configured {} auth was rejected, authenticated with the saved {}",
+                self.config.auth_type,
+                used.describe()
+            ));
+            events::record(
+                &self.config.id,
+                TunnelEventKind::AuthFallback,
+                Some(used.describe()),
+            );
         }
-        let mut ssh_instance = ssh_res.unwrap();
-
-        println!("config.mode: {:?}", self.config.mode);
+        let auth_fallback = ssh_instance.auth_fallback.clone();
+        self.metric_tx
+            .send_modify(|s| s.auth_fallback = auth_fallback);
 
         // 2. Prepare Forward Config
-        let forward_config = if self.config.mode == "docker" {
-            // Resolve Container IP
-            let container_name = match self
-                .config
-                .container_name
-                .clone()
-                .ok_or(anyhow!("Container name missing"))
-            {
-                Ok(name) => name,
-                Err(e) => {
-                    self.metric_tx
-                        .send_modify(|s| s.tunnel_state = TunnelState::Error(e.to_string()));
-                    return;
-                }
-            };
-
-            let cmd = GetContainerAddrCmd { container_name };
-            let ip_res = ssh_instance.exec_cmd(&cmd, Duration::from_secs(10)).await;
-
-            let ip = match ip_res {
-                Ok(Some(ip)) => ip,
-                Ok(None) => {
-                    self.metric_tx.send_modify(|s| {
-                        s.tunnel_state = TunnelState::Error("Container IP not found".into())
-                    });
-                    return;
-                }
-                Err(e) => {
-                    self.metric_tx
-                        .send_modify(|s| s.tunnel_state = TunnelState::Error(e.to_string()));
-                    return;
-                }
-            };
-
-            let remote_port = self.config.container_port.unwrap_or(80);
-
-            SshForwardConfig {
-                local_host: "127.0.0.1".to_string(),
-                local_port: self.config.local_port.unwrap_or(0),
-                remote_host: ip,
-                remote_port,
-            }
-        } else {
-            // Standard mode
-            match SshForwardConfig::try_from(&self.config) {
-                Ok(cfg) => cfg,
-                Err(e) => {
-                    self.metric_tx
-                        .send_modify(|s| s.tunnel_state = TunnelState::Error(e.to_string()));
-                    return;
-                }
+        let forward_config = match self.resolve_forward_config(&ssh_instance).await {
+            Ok(cfg) => cfg,
+            Err(e) => {
+                self.set_error(e.to_string());
+                return;
             }
         };
 
-        println!("forward_config: {:?}", forward_config);
+        // 唤醒休眠的目标主机
+        if let Some(mac) = self.config.wol_mac.clone() {
+            if let Err(e) = self.wake_target(&ssh_instance, &mac, &forward_config).await {
+                ssh_instance.shutdown();
+                self.set_error(e.to_string());
+                return;
+            }
+        }
+
+        // 快速失败：目标不可达时不绑定本地端口 (反向转发的目标在本机，不需要探测)
+        if self.config.fail_fast && forward_config.direction == ForwardDirection::Local {
+            if let Err(e) = ssh_instance
+                .probe_target(&forward_config.remote_host, forward_config.remote_port)
+                .await
+            {
+                ssh_instance.shutdown();
+                self.set_error(e.to_string());
+                return;
+            }
+            self.logger.info(format!(
+                "Target {} is reachable",
+                format_host_port(&forward_config.remote_host, forward_config.remote_port)
+            ));
+        }
 
         // 3. 启动 SSH 内部任务
-        if let Err(e) = ssh_instance.ssh_forward(&forward_config).await {
-            self.metric_tx
-                .send_modify(|s| s.tunnel_state = TunnelState::Error(e.to_string()));
-            return;
+        let local_addr = match ssh_instance.ssh_forward(&forward_config).await {
+            Ok(addr) => addr,
+            Err(e) => {
+                self.set_error(e.to_string());
+                return;
+            }
+        };
+        if local_addr.port() != forward_config.local_port {
+            self.logger.warn(format!(
+                "Local port {} is in use, remapped to {}",
+                forward_config.local_port,
+                local_addr.port()
+            ));
+        }
+        self.metric_tx
+            .send_modify(|s| s.local_addr = Some(local_addr));
+        let remote_addr = format_host_port(&forward_config.remote_host, forward_config.remote_port);
+        match forward_config.direction {
+            ForwardDirection::Local => self
+                .logger
+                .info(format!("Forwarding {} -> {}", local_addr, remote_addr)),
+            ForwardDirection::Remote => self.logger.info(format!(
+                "Forwarding {} on the SSH host -> {}",
+                remote_addr, local_addr
+            )),
+            ForwardDirection::Dynamic => self
+                .logger
+                .info(format!("SOCKS5 proxy listening on {}", local_addr)),
+        }
+        for mapping in &forward_config.port_map {
+            self.logger.info(format!(
+                "Forwarding {}:{}-{} -> {}:{}-{}",
+                forward_config.local_host,
+                mapping.local_start,
+                mapping.local_end,
+                forward_config.remote_host,
+                mapping.remote_base,
+                mapping.remote_base + (mapping.local_end - mapping.local_start)
+            ));
         }
 
         // 4. 提取 RX 通道 (Clone)
@@ -174,30 +444,124 @@ impl TunnelActor {
             .expect("Event RX must be initialized")
             .clone();
 
+        // 运行期间定期探测目标，会话正常但目标不可达时标记为 Unstable
+        let target_check = self
+            .config
+            .target_check_interval_secs
+            .filter(|secs| *secs > 0)
+            .map(|secs| Duration::from_secs(secs as u64));
+        if let Some(every) =
+            target_check.filter(|_| forward_config.direction == ForwardDirection::Local)
+        {
+            ssh_instance.spawn_target_monitor(
+                forward_config.remote_host.clone(),
+                forward_config.remote_port,
+                every,
+            );
+        }
+        let reconnect_on_unreachable =
+            self.config.target_unreachable_action.as_deref() == Some("reconnect");
+
         // 5. 保存 SSH 实例
         self.ssh = Some(ssh_instance);
 
         let metric_tx = self.metric_tx.clone();
+        let logger = self.logger.clone();
+        self.stop_token = CancellationToken::new();
+        let stop_token = self.stop_token.clone();
 
         // 6. 启动 Metrics 更新任务
+        // 快速失败的探测已确认目标可达时直接 Ready，否则等第一次转发成功
+        let mut ready =
+            self.config.fail_fast && forward_config.direction == ForwardDirection::Local;
+        let tunnel_id = self.config.id.clone();
         let task = tokio::spawn(async move {
+            let mut target_down = false;
+            // 意外来源的连接最多每分钟记录一次事件，避免被扫描时刷屏
+            let mut unexpected_seen = 0;
+            let mut unexpected_recorded: Option<Instant> = None;
             loop {
-                if event_rx.changed().await.is_err() {
+                let changed = tokio::select! {
+                    _ = stop_token.cancelled() => break,
+                    changed = event_rx.changed() => changed,
+                };
+
+                if changed.is_err() {
+                    // 主动停止时通道关闭属于正常流程，不上报错误
+                    if stop_token.is_cancelled() {
+                        break;
+                    }
+                    logger.error("SSH event channel closed");
                     metric_tx.send_modify(|s| {
                         s.tunnel_state = TunnelState::Error("Channel closed".into())
                     });
                     break;
                 } else {
                     let event = event_rx.borrow_and_update().clone();
+                    if event.unexpected_sources > unexpected_seen
+                        && !matches!(unexpected_recorded, Some(at) if at.elapsed() < UNEXPECTED_SOURCE_INTERVAL)
+                    {
+                        let detail = event.last_unexpected_source.clone().unwrap_or_default();
+                        let count = event.unexpected_sources - unexpected_seen;
+                        logger.warn(format!("{} ({} such connection(s))", detail, count));
+                        events::record(&tunnel_id, TunnelEventKind::UnexpectedSource, Some(detail));
+                        unexpected_seen = event.unexpected_sources;
+                        unexpected_recorded = Some(Instant::now());
+                    }
                     let mut is_disconnected = false;
                     metric_tx.send_modify(|s| {
                         println!("actor send event: {:?}", event);
+                        let (tx, rx) = event.traffic.delta_since(&s.traffic);
+                        if tx > 0 || rx > 0 {
+                            s.last_traffic_at = Some(Instant::now());
+                        }
+                        if s.stats_paused {
+                            s.window_traffic.append_traffic(tx, rx);
+                        }
                         s.traffic
                             .set(event.traffic.send_bytes, event.traffic.recv_bytes);
-                        let status = TunnelState::from(&event.ssh_status);
+                        s.active_connections = event.active_connections;
+                        s.queued_connections = event.queued_connections;
+                        s.connections = event.connections;
+                        s.connection_errors = event.connection_errors;
+                        s.unexpected_sources = event.unexpected_sources;
+                        if let SSHStatus::Healthy { latency } = event.ssh_status {
+                            s.last_latency = Some(latency);
+                        }
+                        let mut status = TunnelState::from(&event.ssh_status);
+                        if event.target_confirmed && !ready {
+                            ready = true;
+                            logger.info("Forward to the target confirmed, tunnel is ready");
+                        }
+                        if let (TunnelState::Running(latency), false) = (&status, ready) {
+                            status = TunnelState::Listening(*latency);
+                        }
+                        if let (TunnelState::Running(_) | TunnelState::Listening(_), Some(reason)) =
+                            (&status, &event.target_error)
+                        {
+                            status =
+                                TunnelState::Unstable(format!("Target unreachable: {}", reason));
+                        }
+                        if event.target_error.is_some() != target_down {
+                            target_down = event.target_error.is_some();
+                            match &event.target_error {
+                                Some(reason) => {
+                                    logger.warn(format!("Target unreachable: {}", reason))
+                                }
+                                None => logger.info("Target is reachable again"),
+                            }
+                        }
+                        if let TunnelState::Unstable(reason) = &status {
+                            if reconnect_on_unreachable {
+                                logger.warn(
+                                    "Dropping the session to reconnect and re-resolve the target",
+                                );
+                                status = TunnelState::Error(reason.clone());
+                            }
+                        }
                         s.tunnel_state = status.clone();
                         if let TunnelState::Error(e) = status {
-                            println!("actor send error: {:?}", e.clone());
+                            logger.error(format!("SSH session unhealthy: {}", e));
                             is_disconnected = true;
                         }
                     });
@@ -211,23 +575,368 @@ impl TunnelActor {
         self.running_task = Some(task);
     }
 
+    /// 通过 SSH 主机发送 WoL 魔术包，并等待目标端口可连接
+    async fn wake_target(
+        &self,
+        ssh: &Ssh,
+        mac: &str,
+        forward_config: &SshForwardConfig,
+    ) -> anyhow::Result<()> {
+        let mac = normalize_mac(mac)?;
+        let tool = ssh
+            .exec_cmd(&WakeOnLanCmd { mac: mac.clone() }, Duration::from_secs(10))
+            .await?
+            .flatten()
+            .ok_or_else(|| {
+                anyhow!(
+                    "No Wake-on-LAN tool on the SSH host, install wakeonlan, etherwake or python3"
+                )
+            })?;
+        self.logger
+            .info(format!("Sent Wake-on-LAN packet to {} using {}", mac, tool));
+
+        let check = GetTargetReachableCmd {
+            host: forward_config.remote_host.clone(),
+            port: forward_config.remote_port,
+        };
+        let deadline = Instant::now() + WOL_WAIT;
+        while Instant::now() < deadline {
+            match ssh.exec_cmd(&check, Duration::from_secs(15)).await {
+                Ok(Some(result)) if result.reachable => {
+                    self.logger.info(format!(
+                        "{}:{} is up",
+                        forward_config.remote_host, forward_config.remote_port
+                    ));
+                    return Ok(());
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    // 无法检测时不阻塞启动
+                    self.logger
+                        .warn(format!("Cannot check whether the target is up: {}", e));
+                    return Ok(());
+                }
+            }
+            tokio::time::sleep(WOL_POLL_INTERVAL).await;
+        }
+
+        self.logger.warn(format!(
+            "{}:{} did not come up within {:?}, forwarding anyway",
+            forward_config.remote_host, forward_config.remote_port, WOL_WAIT
+        ));
+        Ok(())
+    }
+
+    /// 轮询容器健康状态直到 healthy，没有健康检查时直接继续
+    async fn wait_until_healthy(
+        &self,
+        ssh: &Ssh,
+        container_name: &str,
+        runtime: ContainerRuntime,
+    ) -> anyhow::Result<()> {
+        let cmd = GetContainerHealthCmd {
+            container_name: container_name.to_string(),
+            runtime,
+        };
+        let deadline = Instant::now() + HEALTH_WAIT;
+        let mut last_status = String::new();
+
+        loop {
+            let status = ssh
+                .exec_cmd(&cmd, Duration::from_secs(10))
+                .await?
+                .unwrap_or_default();
+            match status.as_str() {
+                "healthy" => break,
+                "none" => {
+                    self.logger.warn(format!(
+                        "Container {} has no healthcheck, forwarding without waiting",
+                        container_name
+                    ));
+                    break;
+                }
+                _ => {}
+            }
+
+            if status != last_status {
+                self.logger.info(format!(
+                    "Waiting for container {} to become healthy (currently {})",
+                    container_name, status
+                ));
+                self.metric_tx
+                    .send_modify(|s| s.tunnel_state = TunnelState::WaitingForHealth);
+                last_status = status;
+            }
+            if Instant::now() >= deadline {
+                return Err(anyhow!(
+                    "Container {} did not become healthy within {:?} (last status: {})",
+                    container_name,
+                    HEALTH_WAIT,
+                    last_status
+                ));
+            }
+            tokio::time::sleep(HEALTH_POLL_INTERVAL).await;
+        }
+
+        self.metric_tx
+            .send_modify(|s| s.tunnel_state = TunnelState::Starting);
+        Ok(())
+    }
+
+    /// 根据隧道模式计算转发目标，docker 模式需要先解析容器 IP
+    async fn resolve_forward_config(&self, ssh: &Ssh) -> anyhow::Result<SshForwardConfig> {
+        if self.config.mode != "docker" {
+            // Standard mode，配置了解析命令时每次连接重新获取目标
+            let Some(command) = self.config.target_resolver_command.clone() else {
+                return SshForwardConfig::try_from(&self.config);
+            };
+            let (host, port) = preview::run_target_resolver(ssh, command).await?;
+            self.logger
+                .info(format!("Resolved target {}:{}", host, port));
+
+            let mut resolved = self.config.clone();
+            resolved.target_host = Some(host);
+            resolved.target_port = Some(port);
+            return SshForwardConfig::try_from(&resolved);
+        }
+
+        // Resolve Container IP
+        let container_name = self
+            .config
+            .container_name
+            .clone()
+            .ok_or(anyhow!("Container name missing"))?;
+        let runtime = ContainerRuntime::parse(self.config.container_runtime.as_deref())?;
+
+        if self.config.wait_for_healthy {
+            self.wait_until_healthy(ssh, &container_name, runtime)
+                .await?;
+        }
+
+        let target = preview::resolve_container_target(ssh, &self.config).await?;
+        match target.source {
+            TargetSource::PublishedPort => self.logger.info(format!(
+                "Resolved published port {}:{}",
+                target.host, target.port
+            )),
+            _ => self
+                .logger
+                .info(format!("Resolved container address {}", target.host)),
+        }
+
+        Ok(SshForwardConfig {
+            direction: ForwardDirection::Local,
+            local_host: "127.0.0.1".to_string(),
+            local_port: self.config.local_port.unwrap_or(0),
+            auto_remap_port: self.config.auto_remap_port,
+            remote_host: target.host,
+            remote_port: target.port,
+            proxy_protocol: ProxyProtocolVersion::parse(self.config.proxy_protocol.as_deref())?,
+            port_map: port_map_from(&self.config)?,
+            io_buffer_size: io_buffer_size_from(&self.config),
+            keepalive_idle: keepalive_idle_from(&self.config),
+            max_connections: self.config.max_connections,
+            queue_timeout: queue_timeout_from(&self.config),
+            pause_when_locked: self.config.pause_when_locked,
+            dscp: self.config.dscp,
+        })
+    }
+
+    /// 以 `ssh -N -L` 子进程转发，子进程退出视为连接断开
+    async fn start_with_system_ssh(&mut self) {
+        let forward_config = match SshForwardConfig::try_from(&self.config) {
+            Ok(cfg) => cfg,
+            Err(e) => {
+                self.set_error(e.to_string());
+                return;
+            }
+        };
+        let mut child = match system_ssh::spawn(&self.config, &forward_config).await {
+            Ok(child) => child,
+            Err(e) => {
+                self.set_error(format!("{:#}", e));
+                return;
+            }
+        };
+
+        let local_addr = format_host_port(&forward_config.local_host, forward_config.local_port);
+        if forward_config.direction == ForwardDirection::Dynamic {
+            self.logger.info(format!(
+                "SOCKS5 proxy listening on {} through the system ssh",
+                local_addr
+            ));
+        } else {
+            self.logger.info(format!(
+                "Forwarding {} -> {} through the system ssh",
+                local_addr,
+                format_host_port(&forward_config.remote_host, forward_config.remote_port)
+            ));
+        }
+        // 与 russh 相同，先 Listening，确认能转发到目标后再 Running
+        self.metric_tx.send_modify(|s| {
+            s.local_addr = local_addr.parse().ok();
+            s.tunnel_state = TunnelState::Listening(Duration::ZERO);
+        });
+
+        // 停止时任务被 abort，子进程随 Child 一起结束
+        let logger = self.logger.clone();
+        let metric_tx = self.metric_tx.clone();
+        let task = tokio::spawn(async move {
+            let confirm = async {
+                while !system_ssh::confirm_forward(&forward_config).await {
+                    tokio::time::sleep(SYSTEM_SSH_CONFIRM_INTERVAL).await;
+                }
+                logger.info("Forward to the target confirmed, tunnel is ready");
+                metric_tx.send_modify(|s| {
+                    if let TunnelState::Listening(latency) = s.tunnel_state {
+                        s.tunnel_state = TunnelState::Running(latency);
+                    }
+                });
+            };
+            let mut stderr = String::new();
+            let mut pipe = child.stderr.take();
+            let read_stderr = async {
+                if let Some(pipe) = pipe.as_mut() {
+                    let _ = pipe.read_to_string(&mut stderr).await;
+                }
+            };
+            let status = {
+                let exited = async { tokio::join!(child.wait(), read_stderr).0 };
+                tokio::pin!(exited);
+                tokio::select! {
+                    status = &mut exited => status,
+                    _ = confirm => (&mut exited).await,
+                }
+            };
+            let status = status
+                .map(|s| s.to_string())
+                .unwrap_or_else(|e| e.to_string());
+            logger.warn(format!("ssh exited with {}: {}", status, stderr.trim()));
+        });
+        self.running_task = Some(task);
+    }
+
+    /// 尝试通过 ControlMaster socket 建立转发，成功时返回 true
+    async fn try_start_with_control_master(&mut self) -> bool {
+        let control_path = match self.config.control_path.as_deref() {
+            Some(path) if !path.trim().is_empty() => path.trim().to_string(),
+            _ => return false,
+        };
+
+        if self.config.mode != "standard" {
+            self.logger.warn(
+                "Control socket is only supported in standard mode, using a direct connection",
+            );
+            return false;
+        }
+
+        // 配置错误交给常规流程上报
+        let forward_config = match SshForwardConfig::try_from(&self.config) {
+            Ok(cfg) => cfg,
+            Err(_) => return false,
+        };
+
+        if let Some(feature) = control_master::unsupported_feature(&self.config, &forward_config) {
+            self.logger.warn(format!(
+                "{} requires a direct connection, ignoring the control socket",
+                feature
+            ));
+            return false;
+        }
+
+        let control = ControlMaster::new(
+            &control_path,
+            &self.config.ssh_username,
+            &self.config.ssh_host,
+            self.config.ssh_port,
+        );
+        if !control.is_alive().await {
+            self.logger.warn(format!(
+                "Control socket {} is stale or dead, falling back to a direct connection",
+                control_path
+            ));
+            return false;
+        }
+
+        if let Err(e) = control.request_forward(&forward_config).await {
+            self.logger.warn(format!(
+                "Failed to forward through control socket {}, falling back: {:#}",
+                control_path, e
+            ));
+            return false;
+        }
+
+        self.logger.info(format!(
+            "Forwarding through control socket {}",
+            control_path
+        ));
+        // 与 russh 相同，先 Listening，确认能转发到目标后再 Running
+        self.metric_tx
+            .send_modify(|s| s.tunnel_state = TunnelState::Listening(Duration::ZERO));
+
+        // 监控主连接，失效后任务退出，由 run() 标记为 Connection Dropped
+        let watcher = control.clone();
+        let logger = self.logger.clone();
+        let metric_tx = self.metric_tx.clone();
+        let probe_config = forward_config.clone();
+        let task = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(5));
+            let mut ready = false;
+            loop {
+                interval.tick().await;
+                if !watcher.is_alive().await {
+                    break;
+                }
+                if !ready && system_ssh::confirm_forward(&probe_config).await {
+                    ready = true;
+                    logger.info("Forward to the target confirmed, tunnel is ready");
+                    metric_tx.send_modify(|s| {
+                        if let TunnelState::Listening(latency) = s.tunnel_state {
+                            s.tunnel_state = TunnelState::Running(latency);
+                        }
+                    });
+                }
+            }
+        });
+
+        self.control_forward = Some((control, forward_config));
+        self.running_task = Some(task);
+        true
+    }
+
     async fn handle_stop(&mut self) {
         self.metric_tx
             .send_modify(|s| s.tunnel_state = TunnelState::Stopping);
 
         println!("actor handle stopping");
-        if let Some(ssh) = &self.ssh {
-            ssh.shutdown(); // 这会 cancel 内部的 token
-        }
-
+        // 先标记为主动停止并结束 Metrics 任务，避免关闭通道时闪现错误状态
+        self.stop_token.cancel();
         if let Some(task) = self.running_task.take() {
             println!("actor handle stopping, task aborted");
             task.abort();
         }
 
-        self.ssh = None;
+        if let Some(mut ssh) = self.ssh.take() {
+            // 只停止本隧道的转发，会话在最后一个转发停止时关闭
+            let local_port = self.metric_tx.borrow().local_addr.map(|addr| addr.port());
+            if !local_port.is_some_and(|port| ssh.stop_forward(port)) {
+                ssh.shutdown(); // 这会 cancel 内部的 token
+            }
+        }
 
-        self.metric_tx
-            .send_modify(|s| s.tunnel_state = TunnelState::Stopped);
+        if let Some((control, forward_config)) = self.control_forward.take() {
+            if let Err(e) = control.cancel_forward(&forward_config).await {
+                self.logger.warn(format!(
+                    "Failed to cancel forward on control socket: {:#}",
+                    e
+                ));
+            }
+        }
+
+        self.metric_tx.send_modify(|s| {
+            s.tunnel_state = TunnelState::Stopped;
+            s.started_at = None;
+        });
+        self.logger.info("Tunnel stopped");
     }
 }
@@ -0,0 +1,106 @@
+use crate::server::model::SSHEvent;
+use anyhow::{anyhow, Result};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use tokio::sync::{watch, OwnedSemaphorePermit, Semaphore, TryAcquireError};
+use tokio::time::{timeout, Duration};
+
+/// 达到并发上限后最多排队的连接数，超出时直接拒绝
+pub const MAX_QUEUED_CONNECTIONS: u32 = 128;
+/// 未配置时连接在队列中的最长等待时间
+pub const DEFAULT_QUEUE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 单个隧道的并发连接限制，所有端口映射共用
+///
+/// 未设置上限时只统计活动连接数。达到上限的新连接进入有界队列等待空位，
+/// 超时或队列已满时断开。活动数和排队数通过 `SSHEvent` 上报。
+pub struct ConnectionLimiter {
+    semaphore: Option<Arc<Semaphore>>,
+    queue_timeout: Duration,
+    active: AtomicU32,
+    queued: AtomicU32,
+    event_tx: watch::Sender<SSHEvent>,
+}
+
+/// 持有期间占用一个连接名额，drop 时归还
+pub struct ConnectionPermit {
+    limiter: Arc<ConnectionLimiter>,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl ConnectionLimiter {
+    pub fn new(
+        max_connections: Option<u32>,
+        queue_timeout: Duration,
+        event_tx: watch::Sender<SSHEvent>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            semaphore: max_connections
+                .filter(|max| *max > 0)
+                .map(|max| Arc::new(Semaphore::new(max as usize))),
+            queue_timeout,
+            active: AtomicU32::new(0),
+            queued: AtomicU32::new(0),
+            event_tx,
+        })
+    }
+
+    /// 获取连接名额，必要时排队等待
+    pub async fn acquire(self: &Arc<Self>) -> Result<ConnectionPermit> {
+        let permit = match &self.semaphore {
+            None => None,
+            Some(semaphore) => Some(self.acquire_slot(semaphore).await?),
+        };
+        self.active.fetch_add(1, Ordering::SeqCst);
+        self.publish();
+        Ok(ConnectionPermit {
+            limiter: self.clone(),
+            _permit: permit,
+        })
+    }
+
+    async fn acquire_slot(&self, semaphore: &Arc<Semaphore>) -> Result<OwnedSemaphorePermit> {
+        match semaphore.clone().try_acquire_owned() {
+            Ok(permit) => return Ok(permit),
+            Err(TryAcquireError::Closed) => return Err(anyhow!("Connection limiter closed")),
+            Err(TryAcquireError::NoPermits) => {}
+        }
+
+        if self.queued.fetch_add(1, Ordering::SeqCst) >= MAX_QUEUED_CONNECTIONS {
+            self.queued.fetch_sub(1, Ordering::SeqCst);
+            return Err(anyhow!(
+                "Connection queue is full ({} waiting)",
+                MAX_QUEUED_CONNECTIONS
+            ));
+        }
+        self.publish();
+        let result = timeout(self.queue_timeout, semaphore.clone().acquire_owned()).await;
+        self.queued.fetch_sub(1, Ordering::SeqCst);
+        self.publish();
+
+        match result {
+            Ok(Ok(permit)) => Ok(permit),
+            Ok(Err(_)) => Err(anyhow!("Connection limiter closed")),
+            Err(_) => Err(anyhow!(
+                "Timed out after {:?} waiting for a free connection slot",
+                self.queue_timeout
+            )),
+        }
+    }
+
+    fn publish(&self) {
+        let active = self.active.load(Ordering::SeqCst);
+        let queued = self.queued.load(Ordering::SeqCst);
+        self.event_tx.send_modify(|event| {
+            event.active_connections = active;
+            event.queued_connections = queued;
+        });
+    }
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        self.limiter.active.fetch_sub(1, Ordering::SeqCst);
+        self.limiter.publish();
+    }
+}
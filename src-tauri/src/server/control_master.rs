@@ -0,0 +1,138 @@
+use crate::database::entity::tunnel_config::Model as TunnelModel;
+use crate::server::model::{ForwardDirection, SshForwardConfig};
+#[cfg(windows)]
+use crate::server::CREATE_NO_WINDOW;
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info};
+use std::path::PathBuf;
+use std::process::Stdio;
+use tokio::process::Command;
+use tokio::time::{timeout, Duration};
+
+/// 只能通过直接连接完成的配置，返回功能名称，控制连接可用时为 None
+pub fn unsupported_feature(
+    tunnel: &TunnelModel,
+    forward_config: &SshForwardConfig,
+) -> Option<&'static str> {
+    if forward_config.direction != ForwardDirection::Local {
+        // ssh -O forward 只请求本地转发
+        Some("Remote forwarding")
+    } else if forward_config.auto_remap_port {
+        // 控制连接无法探测端口占用后换端口
+        Some("Port remapping")
+    } else if forward_config.proxy_protocol.is_some() {
+        // ssh -O forward 无法注入 PROXY 头
+        Some("PROXY protocol")
+    } else if !forward_config.port_map.is_empty() {
+        Some("Port mapping")
+    } else if tunnel.wol_mac.is_some() {
+        Some("Wake-on-LAN")
+    } else if tunnel.target_resolver_command.is_some() {
+        Some("Target resolver command")
+    } else {
+        None
+    }
+}
+
+/// 通过已有的 OpenSSH ControlMaster socket 请求端口转发
+///
+/// 复用用户已经建立好的主连接，无需重新认证 (也就不会再次触发 2FA)。
+/// 仅在 Unix 上可用，其它平台上 `is_alive` 始终返回 `false`。
+#[derive(Clone, Debug)]
+pub struct ControlMaster {
+    socket_path: PathBuf,
+    destination: String,
+    port: u16,
+}
+
+impl ControlMaster {
+    pub fn new(socket_path: impl Into<PathBuf>, user: &str, host: &str, port: u16) -> Self {
+        Self {
+            socket_path: socket_path.into(),
+            destination: format!("{}@{}", user, host),
+            port,
+        }
+    }
+
+    /// 检查主连接是否仍然存活 (`ssh -O check`)
+    pub async fn is_alive(&self) -> bool {
+        if !cfg!(unix) || !self.socket_path.exists() {
+            return false;
+        }
+
+        match self.run_control("check", None).await {
+            Ok(()) => true,
+            Err(e) => {
+                debug!(
+                    "Control socket {} is not usable: {:#}",
+                    self.socket_path.display(),
+                    e
+                );
+                false
+            }
+        }
+    }
+
+    /// 通过主连接请求一个本地转发 (`ssh -O forward -L ...`)
+    pub async fn request_forward(&self, forward_config: &SshForwardConfig) -> Result<()> {
+        let spec = Self::forward_spec(forward_config);
+        self.run_control("forward", Some(&spec)).await?;
+        info!(
+            "Forward {} requested through control socket {}",
+            spec,
+            self.socket_path.display()
+        );
+        Ok(())
+    }
+
+    /// 取消之前请求的转发 (`ssh -O cancel -L ...`)
+    pub async fn cancel_forward(&self, forward_config: &SshForwardConfig) -> Result<()> {
+        let spec = Self::forward_spec(forward_config);
+        self.run_control("cancel", Some(&spec)).await
+    }
+
+    fn forward_spec(forward_config: &SshForwardConfig) -> String {
+        format!(
+            "{}:{}:{}:{}",
+            forward_config.local_host,
+            forward_config.local_port,
+            forward_config.remote_host,
+            forward_config.remote_port
+        )
+    }
+
+    async fn run_control(&self, op: &str, forward_spec: Option<&str>) -> Result<()> {
+        let mut cmd = Command::new("ssh");
+        cmd.arg("-S")
+            .arg(&self.socket_path)
+            .arg("-O")
+            .arg(op)
+            .arg("-p")
+            .arg(self.port.to_string());
+        if let Some(spec) = forward_spec {
+            cmd.arg("-L").arg(spec);
+        }
+        cmd.arg(&self.destination)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        #[cfg(windows)]
+        cmd.creation_flags(CREATE_NO_WINDOW);
+
+        let output = timeout(Duration::from_secs(5), cmd.output())
+            .await
+            .with_context(|| format!("ssh -O {} timed out", op))?
+            .context("Failed to run ssh")?;
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "ssh -O {} failed: {}",
+                op,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use crate::server::model::{SshConnectConfig, TunnelAuth};
+use crate::server::ssh::Ssh;
+use anyhow::Result;
+use log::debug;
+use once_cell::sync::Lazy;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// 进行中的远程发现操作 (容器列表、监听端口等)，用于在对话框关闭时统一取消
+static IN_FLIGHT: Lazy<Mutex<HashMap<u64, CancellationToken>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// 登记一个发现操作，guard 释放时自动注销
+pub struct DiscoveryGuard {
+    id: u64,
+    pub token: CancellationToken,
+}
+
+impl Drop for DiscoveryGuard {
+    fn drop(&mut self) {
+        IN_FLIGHT.lock().unwrap().remove(&self.id);
+    }
+}
+
+pub fn register() -> DiscoveryGuard {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let token = CancellationToken::new();
+    IN_FLIGHT.lock().unwrap().insert(id, token.clone());
+    DiscoveryGuard { id, token }
+}
+
+/// 取消所有进行中的发现操作，返回取消的数量
+pub fn cancel_all() -> usize {
+    let tokens: Vec<CancellationToken> =
+        IN_FLIGHT.lock().unwrap().drain().map(|(_, t)| t).collect();
+    for token in &tokens {
+        token.cancel();
+    }
+    tokens.len()
+}
+
+/// 发现命令复用的 SSH 会话空闲多久后关闭
+const SESSION_TTL: Duration = Duration::from_secs(60);
+const REAP_INTERVAL: Duration = Duration::from_secs(15);
+
+struct WarmSession {
+    ssh: Arc<Ssh>,
+    /// 主机、端口和用户相同但认证不同的会话视为过期
+    host_key: (String, u16, String),
+    expires_at: Instant,
+}
+
+/// 按连接配置缓存的会话，创建隧道流程中的多次发现共用一个连接
+static WARM_SESSIONS: Lazy<Mutex<HashMap<u64, WarmSession>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static REAPER_RUNNING: AtomicBool = AtomicBool::new(false);
+
+fn hash_auth(auth: &TunnelAuth, hasher: &mut DefaultHasher) {
+    match auth {
+        TunnelAuth::Password(password) => ("password", password).hash(hasher),
+        TunnelAuth::Key(path, passphrase) => ("key", path, passphrase).hash(hasher),
+        TunnelAuth::Agent => "agent".hash(hasher),
+    }
+}
+
+fn session_key(config: &SshConnectConfig) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    config.ssh_host.hash(&mut hasher);
+    config.ssh_port.hash(&mut hasher);
+    config.ssh_user.hash(&mut hasher);
+    hash_auth(&config.auth, &mut hasher);
+    // 经不同跳板机到同一主机的会话不能混用
+    if let Some(jump) = &config.jump {
+        (&jump.host, jump.port, &jump.user, &jump.proxy).hash(&mut hasher);
+        hash_auth(&jump.auth, &mut hasher);
+    }
+    config.identities_only.hash(&mut hasher);
+    config.source_addr.hash(&mut hasher);
+    config.proxy.hash(&mut hasher);
+    format!("{:?}", config.remote_shell).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// 取得可复用的会话，没有或已断开时新建
+pub async fn warm_session(config: &SshConnectConfig) -> Result<Arc<Ssh>> {
+    let key = session_key(config);
+    {
+        let mut sessions = WARM_SESSIONS.lock().unwrap();
+        if let Some(session) = sessions.get_mut(&key) {
+            if !session.ssh.is_closed() && session.expires_at > Instant::now() {
+                session.expires_at = Instant::now() + SESSION_TTL;
+                debug!("Reusing warm SSH session to {}", config.ssh_host);
+                return Ok(session.ssh.clone());
+            }
+        }
+    }
+
+    let ssh = Arc::new(Ssh::init(config.clone()).await?);
+    let host_key = (
+        config.ssh_host.clone(),
+        config.ssh_port,
+        config.ssh_user.clone(),
+    );
+
+    let stale: Vec<Arc<Ssh>> = {
+        let mut sessions = WARM_SESSIONS.lock().unwrap();
+        let stale_keys: Vec<u64> = sessions
+            .iter()
+            .filter(|(k, s)| **k == key || s.host_key == host_key)
+            .map(|(k, _)| *k)
+            .collect();
+        let stale = stale_keys
+            .iter()
+            .filter_map(|k| sessions.remove(k))
+            .map(|s| s.ssh)
+            .collect();
+        sessions.insert(
+            key,
+            WarmSession {
+                ssh: ssh.clone(),
+                host_key,
+                expires_at: Instant::now() + SESSION_TTL,
+            },
+        );
+        stale
+    };
+    for old in stale {
+        old.disconnect().await;
+    }
+
+    ensure_reaper();
+    Ok(ssh)
+}
+
+/// 会话执行失败后丢弃，下次重新连接
+pub async fn invalidate_session(config: &SshConnectConfig) {
+    let removed = WARM_SESSIONS.lock().unwrap().remove(&session_key(config));
+    if let Some(session) = removed {
+        session.ssh.disconnect().await;
+    }
+}
+
+/// 关闭所有缓存的会话
+pub async fn close_warm_sessions() -> usize {
+    let sessions: Vec<WarmSession> = WARM_SESSIONS
+        .lock()
+        .unwrap()
+        .drain()
+        .map(|(_, s)| s)
+        .collect();
+    for session in &sessions {
+        session.ssh.disconnect().await;
+    }
+    sessions.len()
+}
+
+/// 定期关闭过期会话，缓存为空时退出
+fn ensure_reaper() {
+    if REAPER_RUNNING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(REAP_INTERVAL).await;
+            let (expired, empty) = {
+                let mut sessions = WARM_SESSIONS.lock().unwrap();
+                let now = Instant::now();
+                let expired_keys: Vec<u64> = sessions
+                    .iter()
+                    .filter(|(_, s)| s.expires_at <= now || s.ssh.is_closed())
+                    .map(|(k, _)| *k)
+                    .collect();
+                let expired: Vec<Arc<Ssh>> = expired_keys
+                    .iter()
+                    .filter_map(|k| sessions.remove(k))
+                    .map(|s| s.ssh)
+                    .collect();
+                (expired, sessions.is_empty())
+            };
+            for ssh in expired {
+                debug!("Closing idle discovery session");
+                ssh.disconnect().await;
+            }
+            if empty {
+                REAPER_RUNNING.store(false, Ordering::SeqCst);
+                break;
+            }
+        }
+    });
+}
@@ -0,0 +1,126 @@
+use crate::error::{AppError, ErrorCode};
+use anyhow::Result;
+use log::warn;
+use serde::Serialize;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use tokio::net::lookup_host;
+use tokio::time::{sleep, Duration};
+
+pub const DEFAULT_RETRY_ATTEMPTS: u32 = 3;
+pub const MAX_RETRY_ATTEMPTS: u32 = 10;
+/// 第一次重试前的等待，之后每次翻倍
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+static RETRY_ATTEMPTS: AtomicU32 = AtomicU32::new(DEFAULT_RETRY_ATTEMPTS);
+static FAILURES: AtomicU64 = AtomicU64::new(0);
+static RECOVERED: AtomicU64 = AtomicU64::new(0);
+static GAVE_UP: AtomicU64 = AtomicU64::new(0);
+
+/// 建立连接时 DNS 解析的失败统计，与连接失败分开计数
+#[derive(Debug, Serialize, Clone, Copy, Default)]
+pub struct DnsStats {
+    pub retry_attempts: u32,
+    /// 解析失败的次数，包括之后重试成功的
+    pub failures: u64,
+    /// 重试后解析成功的次数
+    pub recovered: u64,
+    /// 用完所有尝试仍失败的次数
+    pub gave_up: u64,
+}
+
+/// 按设置修改解析的尝试次数
+pub fn set_retry_attempts(attempts: u32) {
+    RETRY_ATTEMPTS.store(attempts.clamp(1, MAX_RETRY_ATTEMPTS), Ordering::Relaxed);
+}
+
+pub fn stats() -> DnsStats {
+    DnsStats {
+        retry_attempts: RETRY_ATTEMPTS.load(Ordering::Relaxed),
+        failures: FAILURES.load(Ordering::Relaxed),
+        recovered: RECOVERED.load(Ordering::Relaxed),
+        gave_up: GAVE_UP.load(Ordering::Relaxed),
+    }
+}
+
+/// 解析 `host:port`，失败时短暂等待后重试，用于刚唤醒或切换网络后 DNS 尚未就绪的情况
+///
+/// 只重试解析出错，解析成功但没有地址直接返回空列表。
+pub async fn lookup_with_retry(host: &str, port: u16) -> std::io::Result<Vec<SocketAddr>> {
+    let attempts = RETRY_ATTEMPTS.load(Ordering::Relaxed);
+    let mut delay = RETRY_DELAY;
+    let mut attempt = 1;
+    loop {
+        match lookup_host((host, port)).await {
+            Ok(addrs) => {
+                if attempt > 1 {
+                    RECOVERED.fetch_add(1, Ordering::Relaxed);
+                }
+                return Ok(addrs.collect());
+            }
+            Err(e) => {
+                FAILURES.fetch_add(1, Ordering::Relaxed);
+                if attempt >= attempts {
+                    GAVE_UP.fetch_add(1, Ordering::Relaxed);
+                    return Err(e);
+                }
+                warn!(
+                    "Failed to resolve {} (attempt {}/{}): {}, retrying in {:?}",
+                    host, attempt, attempts, e, delay
+                );
+                sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// 解析主机名的全部地址 (IPv4 和 IPv6)，去重并保持系统解析器返回的顺序
+///
+/// 主机名不存在时返回 NotFound；解析超时、服务器故障或能解析但没有任何地址时返回 Network 错误。
+pub async fn resolve_all(host: &str) -> Result<Vec<IpAddr>> {
+    let host = host.trim();
+    if host.is_empty() {
+        return Err(AppError::validation("Host is empty").into());
+    }
+
+    let resolved = lookup_host((host, 0)).await.map_err(|e| {
+        if is_no_such_host(&e) {
+            AppError::not_found(format!("No such host {}: {}", host, e))
+        } else {
+            AppError::new(
+                ErrorCode::Network,
+                format!("Failed to resolve {}: {}", host, e),
+            )
+        }
+    })?;
+
+    let mut addrs: Vec<IpAddr> = Vec::new();
+    for addr in resolved {
+        if !addrs.contains(&addr.ip()) {
+            addrs.push(addr.ip());
+        }
+    }
+    if addrs.is_empty() {
+        return Err(AppError::new(
+            ErrorCode::Network,
+            format!("{} resolved but has no addresses", host),
+        )
+        .into());
+    }
+    Ok(addrs)
+}
+
+/// getaddrinfo 报告主机名不存在 (EAI_NONAME/EAI_NODATA)，临时失败 (EAI_AGAIN) 和 SERVFAIL 不算
+///
+/// 标准库不保留 EAI 错误码，unix 上只能按 gai_strerror 的文字判断。
+fn is_no_such_host(e: &std::io::Error) -> bool {
+    // WSAHOST_NOT_FOUND、WSANO_DATA
+    #[cfg(windows)]
+    if matches!(e.raw_os_error(), Some(11001 | 11004)) {
+        return true;
+    }
+    let message = e.to_string().to_ascii_lowercase();
+    message.contains("not known") || message.contains("no address associated")
+}
@@ -0,0 +1,61 @@
+use crate::database::models::TunnelEvent;
+use crate::database::DB;
+use crate::server::model::unix_millis;
+use log::warn;
+use std::time::Duration;
+
+/// 事件保留 30 天
+const RETENTION: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// 一分钟内超过该流量记为 high_traffic 事件
+pub const HIGH_TRAFFIC_BYTES: u64 = 512 * 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TunnelEventKind {
+    Started,
+    Stopped,
+    Failed,
+    Reconnected,
+    HighTraffic,
+    /// 主机公钥与上次看到的不同，不属于某个隧道，tunnel_id 为 `host:port`
+    HostKeyChanged,
+    /// 本地端口收到来自非回环地址的连接
+    UnexpectedSource,
+    /// 配置的认证方式被拒绝，改用其它已保存的凭据登录
+    AuthFallback,
+}
+
+impl TunnelEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Started => "started",
+            Self::Stopped => "stopped",
+            Self::Failed => "failed",
+            Self::Reconnected => "reconnected",
+            Self::HighTraffic => "high_traffic",
+            Self::HostKeyChanged => "host_key_changed",
+            Self::UnexpectedSource => "unexpected_source",
+            Self::AuthFallback => "auth_fallback",
+        }
+    }
+}
+
+/// 后台写入一条事件，失败只记录日志
+pub fn record(tunnel_id: &str, kind: TunnelEventKind, detail: Option<String>) {
+    let event = TunnelEvent {
+        id: 0,
+        tunnel_id: tunnel_id.to_string(),
+        kind: kind.as_str().to_string(),
+        detail,
+        timestamp: unix_millis(),
+    };
+    let retain_after = event.timestamp.saturating_sub(RETENTION.as_millis() as u64);
+    tokio::spawn(async move {
+        if let Err(e) = DB::insert_tunnel_event(&event, retain_after).await {
+            warn!(
+                "Failed to record {} event for tunnel {}: {:#}",
+                event.kind, event.tunnel_id, e
+            );
+        }
+    });
+}
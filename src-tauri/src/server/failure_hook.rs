@@ -0,0 +1,89 @@
+use crate::server::logs::TunnelLogger;
+#[cfg(windows)]
+use crate::server::CREATE_NO_WINDOW;
+use std::process::Stdio;
+use tokio::process::Command;
+use tokio::time::{timeout, Duration, Instant};
+
+/// 同一隧道两次执行之间的最短间隔，避免频繁断开时反复触发
+const DEBOUNCE: Duration = Duration::from_secs(60);
+const HOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 隧道进入错误状态时在本机执行的命令
+///
+/// 命令由用户配置，通过系统 shell 原样执行 (unix 为 `sh -c`，Windows 为 `cmd /C`)，
+/// 隧道信息通过环境变量 `CICONIA_TUNNEL_ID`、`CICONIA_TUNNEL_NAME`、`CICONIA_FAILURE_REASON`
+/// 传入，unix 上同时作为位置参数 `$1` (id) 和 `$2` (原因)。
+pub struct FailureHook {
+    command: String,
+    last_run: Option<Instant>,
+}
+
+impl FailureHook {
+    pub fn new(command: Option<&str>) -> Option<Self> {
+        let command = command.map(str::trim).filter(|c| !c.is_empty())?;
+        Some(Self {
+            command: command.to_string(),
+            last_run: None,
+        })
+    }
+
+    pub fn trigger(
+        &mut self,
+        tunnel_id: &str,
+        tunnel_name: &str,
+        reason: &str,
+        logger: &TunnelLogger,
+    ) {
+        if let Some(last_run) = self.last_run {
+            if last_run.elapsed() < DEBOUNCE {
+                logger.info("Failure hook skipped, it ran less than a minute ago");
+                return;
+            }
+        }
+        self.last_run = Some(Instant::now());
+
+        let mut cmd = if cfg!(windows) {
+            let mut cmd = Command::new("cmd");
+            cmd.arg("/C").arg(&self.command);
+            cmd
+        } else {
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c")
+                .arg(&self.command)
+                .arg("ciconia-hook")
+                .arg(tunnel_id)
+                .arg(reason);
+            cmd
+        };
+        cmd.env("CICONIA_TUNNEL_ID", tunnel_id)
+            .env("CICONIA_TUNNEL_NAME", tunnel_name)
+            .env("CICONIA_FAILURE_REASON", reason)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        #[cfg(windows)]
+        cmd.creation_flags(CREATE_NO_WINDOW);
+
+        logger.info(format!("Running failure hook: {}", self.command));
+        let logger = logger.clone();
+        tokio::spawn(async move {
+            match timeout(HOOK_TIMEOUT, cmd.output()).await {
+                Ok(Ok(output)) if output.status.success() => {
+                    logger.info("Failure hook finished");
+                }
+                Ok(Ok(output)) => logger.warn(format!(
+                    "Failure hook exited with {}: {}",
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )),
+                Ok(Err(e)) => logger.error(format!("Failed to run failure hook: {}", e)),
+                Err(_) => logger.warn(format!(
+                    "Failure hook did not finish within {:?} and was killed",
+                    HOOK_TIMEOUT
+                )),
+            }
+        });
+    }
+}
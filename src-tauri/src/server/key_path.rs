@@ -0,0 +1,91 @@
+use anyhow::{anyhow, Context, Result};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// 当前用户的 home 目录
+pub fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+/// 展开 `~`，相对路径视为相对 home 目录，最后取规范化的绝对路径
+pub fn normalize_key_path(path: &str) -> Result<PathBuf> {
+    let path = path.trim();
+    if path.is_empty() {
+        return Err(anyhow!("Key path is empty"));
+    }
+
+    let expanded = if path == "~" {
+        home_dir().ok_or_else(|| anyhow!("Cannot resolve ~ without a home directory"))?
+    } else if let Some(rest) = path.strip_prefix("~/").or_else(|| path.strip_prefix("~\\")) {
+        home_dir()
+            .ok_or_else(|| anyhow!("Cannot resolve ~ without a home directory"))?
+            .join(rest)
+    } else if Path::new(path).is_relative() {
+        home_dir()
+            .ok_or_else(|| anyhow!("Cannot resolve a relative key path without a home directory"))?
+            .join(path)
+    } else {
+        PathBuf::from(path)
+    };
+
+    std::fs::canonicalize(&expanded)
+        .with_context(|| format!("Key file not found: {}", expanded.display()))
+}
+
+/// 规范化路径并确认文件看起来是私钥
+pub fn validate_key_path(path: &str) -> Result<PathBuf> {
+    let normalized = normalize_key_path(path)?;
+    if !normalized.is_file() {
+        return Err(anyhow!("Key path is not a file: {}", normalized.display()));
+    }
+
+    // 只读取开头判断格式，避免误选公钥或其它大文件
+    let mut head = [0u8; 64];
+    let read = std::fs::File::open(&normalized)
+        .and_then(|mut file| file.read(&mut head))
+        .with_context(|| format!("Failed to read key file: {}", normalized.display()))?;
+    let head = String::from_utf8_lossy(&head[..read]);
+
+    let is_private_key = head.starts_with("-----BEGIN") && head.contains("PRIVATE KEY");
+    let is_putty_key = head.starts_with("PuTTY-User-Key-File");
+    if !is_private_key && !is_putty_key {
+        return Err(anyhow!(
+            "{} does not look like a private key (did you pick the .pub file?)",
+            normalized.display()
+        ));
+    }
+
+    Ok(normalized)
+}
+
+/// 组或其他用户可以访问时返回当前权限位 (如 0o644)，非 Unix 平台不检查
+#[cfg(unix)]
+pub fn loose_permissions(path: &Path) -> Result<Option<u32>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = std::fs::metadata(path)
+        .with_context(|| format!("Failed to read key file metadata: {}", path.display()))?
+        .permissions()
+        .mode()
+        & 0o777;
+    Ok((mode & 0o077 != 0).then_some(mode))
+}
+
+#[cfg(not(unix))]
+pub fn loose_permissions(_path: &Path) -> Result<Option<u32>> {
+    Ok(None)
+}
+
+/// 把私钥权限改为 0600，只处理看起来是私钥的文件
+pub fn fix_key_permissions(path: &str) -> Result<PathBuf> {
+    let normalized = validate_key_path(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&normalized, std::fs::Permissions::from_mode(0o600))
+            .with_context(|| format!("Failed to change permissions of {}", normalized.display()))?;
+    }
+    Ok(normalized)
+}
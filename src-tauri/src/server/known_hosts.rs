@@ -0,0 +1,533 @@
+use crate::database::models::KnownHost;
+use crate::server::key_path::home_dir;
+use anyhow::{anyhow, Context, Result};
+use hmac::{Hmac, Mac};
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use russh::keys::{HashAlg, PublicKey};
+use serde::Serialize;
+use sha1::Sha1;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tokio::sync::{broadcast, oneshot};
+use tokio::time::{timeout, Duration};
+
+/// 散列主机名的前缀，`|1|base64(salt)|base64(hmac-sha1)`
+pub const HASHED_PREFIX: &str = "|1|";
+
+/// 解析 known_hosts 文件的结果
+#[derive(Debug, Default)]
+pub struct ParsedKnownHosts {
+    pub entries: Vec<KnownHost>,
+    /// 带 `@cert-authority`/`@revoked` 标记或无法解析的行数
+    pub skipped: usize,
+}
+
+/// 等待用户确认未知主机公钥的最长时间
+const APPROVAL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 严格模式下按 known_hosts 校验主机公钥，否则接受所有公钥
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+/// 发给前端的确认请求
+static PROMPTS: Lazy<broadcast::Sender<HostKeyPrompt>> = Lazy::new(|| broadcast::channel(16).0);
+
+/// 按 (主机, 端口) 等待确认的连接
+type PendingApprovals = HashMap<(String, u16), Vec<oneshot::Sender<bool>>>;
+
+/// 等待确认的连接，同一主机的并发连接共用一次确认
+static PENDING: Lazy<Mutex<PendingApprovals>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 首次连接时请用户确认的主机公钥
+#[derive(Debug, Clone, Serialize)]
+pub struct HostKeyPrompt {
+    pub host: String,
+    pub port: u16,
+    pub key_type: String,
+    /// SHA256 指纹
+    pub fingerprint: String,
+}
+
+/// 主机公钥在 known_hosts 中的查找结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostKeyStatus {
+    Trusted,
+    /// 记录的公钥与服务器出示的不同，`line` 为 known_hosts 中的行号
+    Changed {
+        line: usize,
+    },
+    /// 公钥被 `@revoked` 行吊销，与 OpenSSH 一样总是拒绝
+    Revoked {
+        line: usize,
+    },
+    Unknown,
+}
+
+/// 按设置 strict_host_key_checking 开关校验
+pub fn set_strict(enabled: bool) {
+    STRICT.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_strict() -> bool {
+    STRICT.load(Ordering::Relaxed)
+}
+
+/// 订阅确认请求，由前端窗口回复 `answer`
+pub fn subscribe() -> broadcast::Receiver<HostKeyPrompt> {
+    PROMPTS.subscribe()
+}
+
+/// 按 OpenSSH 的规则 (散列主机名、`[host]:port`) 查找主机公钥，文件不存在时为 Unknown
+pub fn lookup(path: &Path, host: &str, port: u16, key: &PublicKey) -> Result<HostKeyStatus> {
+    // russh 跳过带标记的行，吊销记录单独检查
+    if let Ok(content) = std::fs::read_to_string(path) {
+        if let Some(line) = revoked_line(&content, host, port, key) {
+            return Ok(HostKeyStatus::Revoked { line });
+        }
+    }
+    match russh::keys::check_known_hosts_path(host, port, key, path) {
+        Ok(true) => Ok(HostKeyStatus::Trusted),
+        Ok(false) => Ok(HostKeyStatus::Unknown),
+        Err(russh::keys::Error::KeyChanged { line }) => Ok(HostKeyStatus::Changed {
+            line: file_line(path, line),
+        }),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+/// 吊销该主机公钥的 `@revoked` 行号
+fn revoked_line(content: &str, host: &str, port: u16, key: &PublicKey) -> Option<usize> {
+    let host_port = if port == 22 {
+        host.to_string()
+    } else {
+        format!("[{}]:{}", host, port)
+    };
+    content.lines().enumerate().find_map(|(index, line)| {
+        let mut fields = line.split_whitespace();
+        if fields.next() != Some("@revoked") {
+            return None;
+        }
+        let patterns = fields.next()?;
+        let _key_type = fields.next()?;
+        let revoked = russh::keys::parse_public_key_base64(fields.next()?).ok()?;
+        (revoked == *key && host_matches(&host_port, patterns)).then_some(index + 1)
+    })
+}
+
+/// OpenSSH 的主机模式：逗号分隔，支持 `*`、`?`、`!` 否定和散列主机名，任一否定匹配即不匹配
+fn host_matches(host_port: &str, patterns: &str) -> bool {
+    let host_port = host_port.to_ascii_lowercase();
+    let mut matched = false;
+    for pattern in patterns.split(',') {
+        let (negated, pattern) = match pattern.strip_prefix('!') {
+            Some(pattern) => (true, pattern),
+            None => (false, pattern),
+        };
+        let hit = if pattern.starts_with(HASHED_PREFIX) {
+            hashed_matches(&host_port, pattern)
+        } else {
+            wildcard_matches(&host_port, &pattern.to_ascii_lowercase())
+        };
+        if hit && negated {
+            return false;
+        }
+        matched |= hit;
+    }
+    matched
+}
+
+/// `|1|base64(salt)|base64(hmac-sha1)` 是否为该主机名的散列
+fn hashed_matches(host_port: &str, pattern: &str) -> bool {
+    let mut parts = pattern.split('|').skip(2);
+    let (Some(salt), Some(hash)) = (parts.next(), parts.next()) else {
+        return false;
+    };
+    let (Ok(salt), Ok(hash)) = (
+        data_encoding::BASE64.decode(salt.as_bytes()),
+        data_encoding::BASE64.decode(hash.as_bytes()),
+    ) else {
+        return false;
+    };
+    Hmac::<Sha1>::new_from_slice(&salt)
+        .map(|mac| mac.chain_update(host_port).verify_slice(&hash).is_ok())
+        .unwrap_or(false)
+}
+
+fn wildcard_matches(text: &str, pattern: &str) -> bool {
+    let (text, pattern): (Vec<char>, Vec<char>) =
+        (text.chars().collect(), pattern.chars().collect());
+    // 回溯到最近一个 `*` 重新匹配
+    let (mut t, mut p) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            t += 1;
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// russh 的行号不计注释行，换算成文件中的实际行号
+fn file_line(path: &Path, counted: usize) -> usize {
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return counted;
+    };
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.starts_with('#'))
+        .nth(counted.saturating_sub(1))
+        .map_or(counted, |(index, _)| index + 1)
+}
+
+/// 正在等待确认的连接，结束等待 (包括被取消) 时从 PENDING 中移除
+struct Waiter {
+    key: (String, u16),
+    rx: oneshot::Receiver<bool>,
+}
+
+impl Drop for Waiter {
+    fn drop(&mut self) {
+        self.rx.close();
+        let mut pending = PENDING.lock().unwrap();
+        if let Some(waiters) = pending.get_mut(&self.key) {
+            waiters.retain(|tx| !tx.is_closed());
+            if waiters.is_empty() {
+                pending.remove(&self.key);
+            }
+        }
+    }
+}
+
+/// 串行写入 known_hosts，同一公钥被多个连接同时确认时只写一行
+static LEARN: Mutex<()> = Mutex::new(());
+
+/// 请用户确认首次见到的主机公钥，同意后写入 known_hosts
+///
+/// 没有窗口接收请求或超时未回复时视为拒绝。
+pub async fn request_approval(path: &Path, host: &str, port: u16, key: &PublicKey) -> bool {
+    let pending_key = (host.to_string(), port);
+    let (tx, rx) = oneshot::channel();
+    let first = {
+        let mut pending = PENDING.lock().unwrap();
+        let waiters = pending.entry(pending_key.clone()).or_default();
+        // 已放弃等待的连接不算，否则不会再发出确认请求
+        waiters.retain(|tx| !tx.is_closed());
+        waiters.push(tx);
+        waiters.len() == 1
+    };
+    let mut waiter = Waiter {
+        key: pending_key.clone(),
+        rx,
+    };
+    if first {
+        let prompt = HostKeyPrompt {
+            host: host.to_string(),
+            port,
+            key_type: key.algorithm().as_str().to_string(),
+            fingerprint: key.fingerprint(HashAlg::Sha256).to_string(),
+        };
+        if PROMPTS.send(prompt).is_err() {
+            warn!("No window to confirm the host key of {}:{}", host, port);
+            PENDING.lock().unwrap().remove(&pending_key);
+            return false;
+        }
+    }
+
+    let approved = match timeout(APPROVAL_TIMEOUT, &mut waiter.rx).await {
+        Ok(Ok(approved)) => approved,
+        Ok(Err(_)) => false,
+        Err(_) => {
+            warn!(
+                "Host key of {}:{} was not confirmed within {:?}",
+                host, port, APPROVAL_TIMEOUT
+            );
+            false
+        }
+    };
+    drop(waiter);
+    // 发出请求的连接可能已被取消，由任一得到同意的连接写入，已记录时跳过
+    if approved {
+        let _learn = LEARN.lock().unwrap();
+        let trusted = matches!(lookup(path, host, port, key), Ok(HostKeyStatus::Trusted));
+        if !trusted {
+            if let Err(e) = learn(path, host, port, key) {
+                warn!("Failed to save the host key of {}:{}: {:#}", host, port, e);
+            }
+        }
+    }
+    approved
+}
+
+/// 前端对确认请求的回复，返回是否有连接在等待
+pub fn answer(host: &str, port: u16, approved: bool) -> bool {
+    let waiters = PENDING
+        .lock()
+        .unwrap()
+        .remove(&(host.to_string(), port))
+        .unwrap_or_default();
+    let waiting = !waiters.is_empty();
+    for waiter in waiters {
+        let _ = waiter.send(approved);
+    }
+    waiting
+}
+
+fn learn(path: &Path, host: &str, port: u16, key: &PublicKey) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    russh::keys::known_hosts::learn_known_hosts_path(host, port, key, path)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    info!(
+        "Added the host key of {}:{} to {}",
+        host,
+        port,
+        path.display()
+    );
+    Ok(())
+}
+
+/// 默认的 `~/.ssh/known_hosts`
+pub fn default_path() -> Result<PathBuf> {
+    home_dir()
+        .map(|home| home.join(".ssh").join("known_hosts"))
+        .ok_or_else(|| anyhow!("Cannot locate known_hosts without a home directory"))
+}
+
+/// 解析 OpenSSH known_hosts 内容，明文和散列的主机名都原样保留
+pub fn parse(content: &str) -> ParsedKnownHosts {
+    let mut parsed = ParsedKnownHosts::default();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_line(line) {
+            Some(entry) => parsed.entries.push(entry),
+            None => parsed.skipped += 1,
+        }
+    }
+    parsed
+}
+
+fn parse_line(line: &str) -> Option<KnownHost> {
+    // 证书颁发机构和吊销记录不是普通的主机公钥
+    if line.starts_with('@') {
+        return None;
+    }
+
+    let mut fields = line.split_whitespace();
+    let host_pattern = fields.next()?;
+    let key_type = fields.next()?;
+    let key_base64 = fields.next()?;
+
+    if host_pattern.starts_with(HASHED_PREFIX) && host_pattern.split('|').count() != 4 {
+        return None;
+    }
+    let key = russh::keys::parse_public_key_base64(key_base64).ok()?;
+    if key.algorithm().as_str() != key_type {
+        return None;
+    }
+
+    Some(KnownHost {
+        host_pattern: host_pattern.to_string(),
+        key_type: key_type.to_string(),
+        key_base64: key_base64.to_string(),
+    })
+}
+
+/// 格式化为 known_hosts 中的一行 (不含换行)
+pub fn format_line(host: &KnownHost) -> String {
+    format!(
+        "{} {} {}",
+        host.host_pattern, host.key_type, host.key_base64
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY_PLAIN: &str = "AAAAC3NzaC1lZDI1NTE5AAAAIAI1DnTTHLqL1bui+6KRTnX2GNzY4rF1YBqZCtoV9EMJ";
+    const KEY_PORTED: &str = "AAAAC3NzaC1lZDI1NTE5AAAAIAM2yuevmK1s+9qbxgcFFLaCVCRK+XSNp7De4iv0ED3U";
+    const KEY_HASHED: &str = "AAAAC3NzaC1lZDI1NTE5AAAAIGmz2Gfmq+8ZTxOKNoQXvndlUaDc9MOMyXPpMLNNYklD";
+    const KEY_OTHER: &str = "AAAAC3NzaC1lZDI1NTE5AAAAIF2ALqJgxkiiWrr/huOUzAEgC1oTAPL6DSDVrhS8o1o6";
+    const KEY_ECDSA: &str = "AAAAE2VjZHNhLXNoYTItbmlzdHAyNTYAAAAIbmlzdHAyNTYAAABBBPCYoOJLjGVQKi5+xysT13SlYdcIV046G4Mjm0CmmaF3fTVDdOgW/LEsj8bfUfngVmmJsLZvRqeHdHb85fhvO8o=";
+    /// `hashed.example` 以 salt 0..20 散列
+    const HASHED_HOST: &str = "|1|AAECAwQFBgcICQoLDA0ODxAREhM=|fdLE3hXPaTAOXV+bPV77OkdsKnU=";
+
+    fn sample() -> String {
+        [
+            "# comment".to_string(),
+            format!("plain.example ssh-ed25519 {}", KEY_PLAIN),
+            format!("[ported.example]:2222 ssh-ed25519 {}", KEY_PORTED),
+            format!("{} ssh-ed25519 {}", HASHED_HOST, KEY_HASHED),
+            format!("@cert-authority *.example ssh-ed25519 {}", KEY_OTHER),
+            format!("@revoked revoked.example ssh-ed25519 {}", KEY_OTHER),
+            format!("mismatch.example ssh-rsa {}", KEY_OTHER),
+            format!("ecdsa.example ecdsa-sha2-nistp256 {}", KEY_ECDSA),
+        ]
+        .join("\n")
+    }
+
+    fn key(base64: &str) -> PublicKey {
+        russh::keys::parse_public_key_base64(base64).unwrap()
+    }
+
+    /// 每个测试写入自己的文件，避免并行运行时互相覆盖
+    fn write_sample(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "ciconia-known-hosts-{}-{}",
+            std::process::id(),
+            name
+        ));
+        std::fs::write(&path, sample()).unwrap();
+        path
+    }
+
+    #[test]
+    fn parse_keeps_host_keys_and_skips_markers() {
+        let parsed = parse(&sample());
+        let hosts: Vec<&str> = parsed
+            .entries
+            .iter()
+            .map(|entry| entry.host_pattern.as_str())
+            .collect();
+        assert_eq!(
+            hosts,
+            [
+                "plain.example",
+                "[ported.example]:2222",
+                HASHED_HOST,
+                "ecdsa.example"
+            ]
+        );
+        // @cert-authority、@revoked 和类型不符的行
+        assert_eq!(parsed.skipped, 3);
+    }
+
+    #[test]
+    fn parse_line_round_trips() {
+        let line = format!("{} ssh-ed25519 {}", HASHED_HOST, KEY_HASHED);
+        let entry = parse_line(&line).unwrap();
+        assert_eq!(entry.key_type, "ssh-ed25519");
+        assert_eq!(format_line(&entry), line);
+
+        assert!(parse_line(&format!("|1|broken ssh-ed25519 {}", KEY_PLAIN)).is_none());
+        assert!(parse_line(&format!("host ssh-ed25519 {}", "not-base64")).is_none());
+        assert!(parse_line("host ssh-ed25519").is_none());
+    }
+
+    #[test]
+    fn lookup_trusts_recorded_keys() {
+        let path = write_sample("trusted");
+        let cases = [
+            ("plain.example", 22, KEY_PLAIN),
+            ("ported.example", 2222, KEY_PORTED),
+            ("hashed.example", 22, KEY_HASHED),
+            ("ecdsa.example", 22, KEY_ECDSA),
+        ];
+        for (host, port, base64) in cases {
+            let status = lookup(&path, host, port, &key(base64)).unwrap();
+            assert_eq!(status, HostKeyStatus::Trusted, "{}:{}", host, port);
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn lookup_reports_changed_key_with_file_line() {
+        let path = write_sample("changed");
+        let status = lookup(&path, "plain.example", 22, &key(KEY_OTHER)).unwrap();
+        // 第 1 行是注释，russh 不计入
+        assert_eq!(status, HostKeyStatus::Changed { line: 2 });
+        let status = lookup(&path, "ported.example", 2222, &key(KEY_OTHER)).unwrap();
+        assert_eq!(status, HostKeyStatus::Changed { line: 3 });
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn lookup_unknown_hosts() {
+        let path = write_sample("unknown");
+        let cases = [
+            // 端口不同时按 [host]:port 匹配
+            ("ported.example", 22, KEY_PORTED),
+            ("unknown.example", 22, KEY_PLAIN),
+            // 只记录了其他类型的公钥
+            ("ecdsa.example", 22, KEY_PLAIN),
+            // 吊销的是其他公钥
+            ("revoked.example", 22, KEY_PLAIN),
+        ];
+        for (host, port, base64) in cases {
+            let status = lookup(&path, host, port, &key(base64)).unwrap();
+            assert_eq!(status, HostKeyStatus::Unknown, "{}:{}", host, port);
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        let status = lookup(&path, "plain.example", 22, &key(KEY_PLAIN)).unwrap();
+        assert_eq!(status, HostKeyStatus::Unknown);
+    }
+
+    #[test]
+    fn lookup_rejects_revoked_keys() {
+        let path = write_sample("revoked");
+        let status = lookup(&path, "revoked.example", 22, &key(KEY_OTHER)).unwrap();
+        assert_eq!(status, HostKeyStatus::Revoked { line: 6 });
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn host_patterns_follow_openssh() {
+        assert!(host_matches("db.example", "web.example,DB.example"));
+        assert!(host_matches("db.example", "*.example"));
+        assert!(host_matches("[db.example]:2222", "[db?example]:2222"));
+        assert!(!host_matches("[db.example]:2222", "db.example"));
+        // 任一否定匹配即不匹配
+        assert!(!host_matches("db.example", "*.example,!db.*"));
+        assert!(!host_matches("db.example", "!web.example"));
+        assert!(host_matches("hashed.example", HASHED_HOST));
+        assert!(!host_matches("other.example", HASHED_HOST));
+    }
+
+    #[tokio::test]
+    async fn cancelled_request_does_not_block_approval() {
+        let path = write_sample("approval");
+        let mut prompts = subscribe();
+        let request = |path: PathBuf| {
+            tokio::spawn(async move {
+                request_approval(&path, "approval.example", 22, &key(KEY_PLAIN)).await
+            })
+        };
+
+        // 发出确认请求的连接被取消后，下一个连接重新请求确认
+        let first = request(path.clone());
+        assert_eq!(prompts.recv().await.unwrap().host, "approval.example");
+        first.abort();
+        let _ = first.await;
+        let second = request(path.clone());
+        assert_eq!(prompts.recv().await.unwrap().host, "approval.example");
+
+        // 同意后由仍在等待的连接写入
+        assert!(answer("approval.example", 22, true));
+        assert!(second.await.unwrap());
+        let status = lookup(&path, "approval.example", 22, &key(KEY_PLAIN)).unwrap();
+        assert_eq!(status, HostKeyStatus::Trusted);
+        assert!(!PENDING
+            .lock()
+            .unwrap()
+            .contains_key(&("approval.example".to_string(), 22)));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,100 @@
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use tokio::net::{lookup_host, TcpStream};
+use tokio::time::{sleep, timeout, Duration, Instant};
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+const PROBE_INTERVAL: Duration = Duration::from_millis(200);
+pub const DEFAULT_SAMPLES: u32 = 10;
+const MAX_SAMPLES: u32 = 100;
+
+/// 多次 TCP 建连测得的链路质量，延迟单位为毫秒
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkQuality {
+    pub host: String,
+    pub port: u16,
+    pub samples: u32,
+    pub failures: u32,
+    /// 失败次数占比，0.0 ~ 1.0
+    pub failure_rate: f64,
+    pub min_ms: Option<f64>,
+    pub avg_ms: Option<f64>,
+    pub max_ms: Option<f64>,
+    /// 延迟的标准差
+    pub jitter_ms: Option<f64>,
+    /// 0 ~ 100，丢包影响最大，其次是抖动和延迟
+    pub score: u8,
+    /// "excellent" | "good" | "fair" | "poor"
+    pub rating: &'static str,
+}
+
+/// 对 SSH 端口做 `samples` 次 TCP 建连，只解析一次地址，DNS 耗时不计入
+pub async fn measure_link_quality(host: String, port: u16, samples: u32) -> Result<LinkQuality> {
+    let samples = samples.clamp(1, MAX_SAMPLES);
+    let addr = lookup_host((host.as_str(), port))
+        .await
+        .with_context(|| format!("Failed to resolve {}", host))?
+        .next()
+        .ok_or_else(|| anyhow!("No address found for {}", host))?;
+
+    let mut latencies = Vec::with_capacity(samples as usize);
+    for i in 0..samples {
+        if i > 0 {
+            sleep(PROBE_INTERVAL).await;
+        }
+        let started = Instant::now();
+        if let Ok(Ok(stream)) = timeout(PROBE_TIMEOUT, TcpStream::connect(addr)).await {
+            latencies.push(started.elapsed().as_secs_f64() * 1000.0);
+            drop(stream);
+        }
+    }
+
+    let failures = samples - latencies.len() as u32;
+    let failure_rate = failures as f64 / samples as f64;
+    let (min_ms, avg_ms, max_ms, jitter_ms) = if latencies.is_empty() {
+        (None, None, None, None)
+    } else {
+        let count = latencies.len() as f64;
+        let avg = latencies.iter().sum::<f64>() / count;
+        let variance = latencies.iter().map(|l| (l - avg).powi(2)).sum::<f64>() / count;
+        (
+            latencies.iter().copied().reduce(f64::min),
+            Some(avg),
+            latencies.iter().copied().reduce(f64::max),
+            Some(variance.sqrt()),
+        )
+    };
+
+    let score = score(failure_rate, avg_ms, jitter_ms);
+    Ok(LinkQuality {
+        host,
+        port,
+        samples,
+        failures,
+        failure_rate,
+        min_ms,
+        avg_ms,
+        max_ms,
+        jitter_ms,
+        score,
+        rating: rating(score),
+    })
+}
+
+fn score(failure_rate: f64, avg_ms: Option<f64>, jitter_ms: Option<f64>) -> u8 {
+    let (Some(avg), Some(jitter)) = (avg_ms, jitter_ms) else {
+        return 0;
+    };
+    // 10% 失败扣 30 分，抖动每 2ms 扣 1 分，平均延迟每 20ms 扣 1 分
+    let penalty = failure_rate * 300.0 + jitter / 2.0 + avg / 20.0;
+    (100.0 - penalty).clamp(0.0, 100.0).round() as u8
+}
+
+fn rating(score: u8) -> &'static str {
+    match score {
+        90.. => "excellent",
+        70..=89 => "good",
+        40..=69 => "fair",
+        _ => "poor",
+    }
+}
@@ -0,0 +1,122 @@
+use crate::server::model::{unix_millis, LogPayload};
+use log::Level;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tokio::time::{timeout_at, Duration, Instant};
+
+/// 每个隧道最多保留的日志行数
+const MAX_LINES_PER_TUNNEL: usize = 500;
+
+/// 两次推送 `tunnel-log` 的最小间隔
+pub const LOG_BATCH_INTERVAL: Duration = Duration::from_millis(100);
+/// 每次推送的最大行数，达到后不再等待间隔
+pub const LOG_BATCH_MAX_LINES: usize = 200;
+
+/// 按隧道划分的最近日志环形缓冲区，供日志窗口打开时回填历史
+#[derive(Clone, Default)]
+pub struct TunnelLogBuffer {
+    inner: Arc<Mutex<HashMap<String, VecDeque<LogPayload>>>>,
+}
+
+impl TunnelLogBuffer {
+    pub fn push(&self, payload: LogPayload) {
+        let mut buffers = self.inner.lock().unwrap();
+        let lines = buffers.entry(payload.id.clone()).or_default();
+        if lines.len() >= MAX_LINES_PER_TUNNEL {
+            lines.pop_front();
+        }
+        lines.push_back(payload);
+    }
+
+    /// 最近的 `limit` 行日志，按时间升序；`id` 为空时合并所有隧道
+    pub fn recent(&self, id: Option<&str>, limit: usize) -> Vec<LogPayload> {
+        let buffers = self.inner.lock().unwrap();
+        let mut lines: Vec<LogPayload> = match id {
+            Some(id) => buffers
+                .get(id)
+                .map(|lines| lines.iter().cloned().collect())
+                .unwrap_or_default(),
+            None => {
+                let mut all: Vec<LogPayload> = buffers.values().flatten().cloned().collect();
+                all.sort_by_key(|line| line.timestamp);
+                all
+            }
+        };
+
+        let skip = lines.len().saturating_sub(limit);
+        lines.drain(..skip);
+        lines
+    }
+
+    pub fn clear(&self, id: &str) {
+        self.inner.lock().unwrap().remove(id);
+    }
+}
+
+/// 从通道读取日志并分批交给 emit，通道关闭时返回
+///
+/// 空闲时的第一行立即推送；距上次推送不足 interval 时继续收集，直到间隔结束或达到 max_lines。
+pub async fn batch_logs(
+    mut rx: mpsc::UnboundedReceiver<LogPayload>,
+    interval: Duration,
+    max_lines: usize,
+    mut emit: impl FnMut(Vec<LogPayload>),
+) {
+    let mut last_emit: Option<Instant> = None;
+    while let Some(first) = rx.recv().await {
+        let mut batch = vec![first];
+        if let Some(at) = last_emit {
+            let deadline = at + interval;
+            while batch.len() < max_lines {
+                match timeout_at(deadline, rx.recv()).await {
+                    Ok(Some(payload)) => batch.push(payload),
+                    Ok(None) | Err(_) => break,
+                }
+            }
+        }
+        while batch.len() < max_lines {
+            match rx.try_recv() {
+                Ok(payload) => batch.push(payload),
+                Err(_) => break,
+            }
+        }
+        emit(batch);
+        last_emit = Some(Instant::now());
+    }
+}
+
+/// Actor 使用的日志句柄：同时写入全局日志并发送给前端
+#[derive(Clone)]
+pub struct TunnelLogger {
+    tunnel_id: String,
+    log_tx: mpsc::UnboundedSender<LogPayload>,
+}
+
+impl TunnelLogger {
+    pub fn new(tunnel_id: String, log_tx: mpsc::UnboundedSender<LogPayload>) -> Self {
+        Self { tunnel_id, log_tx }
+    }
+
+    pub fn info(&self, line: impl Into<String>) {
+        self.emit(Level::Info, line.into());
+    }
+
+    pub fn warn(&self, line: impl Into<String>) {
+        self.emit(Level::Warn, line.into());
+    }
+
+    pub fn error(&self, line: impl Into<String>) {
+        self.emit(Level::Error, line.into());
+    }
+
+    fn emit(&self, level: Level, line: String) {
+        log::log!(level, "[tunnel {}] {}", self.tunnel_id, line);
+        let _ = self.log_tx.send(LogPayload {
+            id: self.tunnel_id.clone(),
+            line,
+            level: level.as_str().to_lowercase(),
+            timestamp: unix_millis(),
+        });
+    }
+}
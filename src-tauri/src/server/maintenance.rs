@@ -0,0 +1,53 @@
+use crate::server::model::unix_millis;
+use log::info;
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// 全局维护模式：期间不执行失败钩子，托盘不显示错误，自动重连照常进行
+///
+/// `Some(None)` 表示手动退出前一直有效，`Some(Some(t))` 在 unix 毫秒 t 时自动退出。
+static MAINTENANCE: Mutex<Option<Option<u64>>> = Mutex::new(None);
+
+#[derive(Debug, Serialize, Clone, Copy, Default)]
+pub struct MaintenanceStatus {
+    pub enabled: bool,
+    /// 自动退出的时间 (unix millis)，None 表示需要手动退出
+    pub until: Option<u64>,
+}
+
+/// 进入维护模式，`duration` 为 None 时需要手动退出
+pub fn enable(duration: Option<Duration>) -> MaintenanceStatus {
+    let until = duration.map(|d| unix_millis() + d.as_millis() as u64);
+    *MAINTENANCE.lock().unwrap() = Some(until);
+    info!("Maintenance mode enabled until {:?}", until);
+    status()
+}
+
+pub fn disable() -> MaintenanceStatus {
+    if MAINTENANCE.lock().unwrap().take().is_some() {
+        info!("Maintenance mode disabled");
+    }
+    MaintenanceStatus::default()
+}
+
+/// 当前状态，到期时顺带退出维护模式
+pub fn status() -> MaintenanceStatus {
+    let mut maintenance = MAINTENANCE.lock().unwrap();
+    match *maintenance {
+        None => MaintenanceStatus::default(),
+        Some(Some(until)) if unix_millis() >= until => {
+            *maintenance = None;
+            info!("Maintenance mode expired");
+            MaintenanceStatus::default()
+        }
+        Some(until) => MaintenanceStatus {
+            enabled: true,
+            until,
+        },
+    }
+}
+
+pub fn is_active() -> bool {
+    status().enabled
+}
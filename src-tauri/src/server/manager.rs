@@ -1,10 +1,14 @@
 use crate::database::entity::tunnel_config::Model as TunnelModel;
 use crate::server::actor::TunnelActor;
-use crate::server::model::{TunnelCommand, TunnelMetric};
+use crate::server::logs::TunnelLogger;
+use crate::server::model::{LogPayload, ShutdownReport, TunnelCommand, TunnelMetric};
+use crate::settings::SettingsManager;
 use anyhow::{anyhow, Result};
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::{mpsc, watch, RwLock};
+use tokio::sync::{mpsc, oneshot, watch, RwLock};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout_at, Duration, Instant};
 
 pub struct TunnelHandle {
     pub cmd_tx: mpsc::Sender<TunnelCommand>,
@@ -14,12 +18,16 @@ pub struct TunnelHandle {
 #[derive(Clone)]
 pub struct TunnelManager {
     tunnels: Arc<RwLock<HashMap<String, TunnelHandle>>>,
+    log_tx: mpsc::UnboundedSender<LogPayload>,
+    settings: Arc<SettingsManager>,
 }
 
 impl TunnelManager {
-    pub fn new() -> Self {
+    pub fn new(log_tx: mpsc::UnboundedSender<LogPayload>, settings: Arc<SettingsManager>) -> Self {
         Self {
             tunnels: Arc::new(RwLock::new(HashMap::new())),
+            log_tx,
+            settings,
         }
     }
 
@@ -29,7 +37,14 @@ impl TunnelManager {
 
         let id = config.id.clone();
 
-        let actor = TunnelActor::new(config.clone(), cmd_rx, tunnel_metric_tx);
+        let logger = TunnelLogger::new(id.clone(), self.log_tx.clone());
+        let actor = TunnelActor::new(
+            config.clone(),
+            cmd_rx,
+            tunnel_metric_tx,
+            logger,
+            self.settings.clone(),
+        );
         tokio::task::spawn(actor.run());
 
         let handle = TunnelHandle {
@@ -54,10 +69,92 @@ impl TunnelManager {
         self.send_command_to_tunnel(id, TunnelCommand::Stop).await
     }
 
+    pub async fn update_tunnel_config(&self, config: &TunnelModel) -> Result<()> {
+        self.send_command_to_tunnel(
+            &config.id,
+            TunnelCommand::UpdateConfig(Box::new(config.clone())),
+        )
+        .await
+    }
+
+    pub async fn pause_stats(&self, id: &String) -> Result<()> {
+        self.send_command_to_tunnel(id, TunnelCommand::PauseStats)
+            .await
+    }
+
+    pub async fn resume_stats(&self, id: &String) -> Result<()> {
+        self.send_command_to_tunnel(id, TunnelCommand::ResumeStats)
+            .await
+    }
+
     pub async fn remove_tunnel(&self, id: &String) -> Result<()> {
         self.send_command_to_tunnel(id, TunnelCommand::Remove).await
     }
 
+    /// 移除隧道并等待 actor 及其派生的任务退出，期限到达时报告仍在运行的任务
+    pub async fn shutdown_tunnel_debug(
+        &self,
+        id: &String,
+        deadline: Duration,
+    ) -> Result<ShutdownReport> {
+        let cmd_tx = self
+            .tunnels
+            .write()
+            .await
+            .remove(id)
+            .map(|handle| handle.cmd_tx)
+            .ok_or_else(|| anyhow!(format!("Tunnel with id {} not found", id)))?;
+
+        let started = Instant::now();
+        let end = started + deadline;
+        let (reply_tx, reply_rx) = oneshot::channel();
+        cmd_tx
+            .send(TunnelCommand::RemoveWithReport(reply_tx))
+            .await
+            .map_err(|e| anyhow!(format!("Actor died, {:?}", e)))?;
+        let tasks = timeout_at(end, reply_rx)
+            .await
+            .map_err(|_| anyhow!("Actor did not respond within {:?}", deadline))?
+            .map_err(|_| anyhow!("Actor exited without reporting its tasks"))?;
+        // actor 退出后命令通道的接收端随之释放
+        let actor_exited = timeout_at(end, cmd_tx.closed()).await.is_ok();
+
+        loop {
+            let metrics_task_exited = tasks
+                .metrics_task
+                .as_ref()
+                .map_or(true, JoinHandle::is_finished);
+            let (monitors_left, accept_loops_left, connections_left) = tasks
+                .ssh
+                .as_ref()
+                .map(|ssh| {
+                    (
+                        ssh.monitors.len(),
+                        ssh.accept_loops.len(),
+                        ssh.connections.len(),
+                    )
+                })
+                .unwrap_or_default();
+            let clean = actor_exited
+                && metrics_task_exited
+                && monitors_left + accept_loops_left + connections_left == 0;
+
+            if clean || Instant::now() >= end {
+                return Ok(ShutdownReport {
+                    id: id.clone(),
+                    actor_exited,
+                    metrics_task_exited,
+                    monitors_left,
+                    accept_loops_left,
+                    connections_left,
+                    elapsed_ms: started.elapsed().as_millis() as u64,
+                    clean,
+                });
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+    }
+
     pub async fn get_tunnel_metric(&self, id: &String) -> Option<TunnelMetric> {
         let tunnels = self.tunnels.read().await;
         if let Some(handle) = tunnels.get(id) {
@@ -70,16 +167,13 @@ impl TunnelManager {
         }
     }
 
+    /// 所有隧道的指标，只持有一次读锁，隧道很多时也不逐个加锁
     pub async fn get_all_tunnel_health_state(&self) -> HashMap<String, TunnelMetric> {
         let tunnels = self.tunnels.read().await;
-        let mut all_tunnel_metric_state = HashMap::new();
-        for id in tunnels.keys() {
-            if let Some(health_status) = self.get_tunnel_metric(id).await {
-                all_tunnel_metric_state.insert(id.clone(), health_status);
-            }
-        }
-
-        all_tunnel_metric_state
+        tunnels
+            .iter()
+            .map(|(id, handle)| (id.clone(), handle.tunnel_metric_rx.borrow().clone()))
+            .collect()
     }
 
     async fn send_command_to_tunnel(&self, id: &String, cmd: TunnelCommand) -> Result<()> {
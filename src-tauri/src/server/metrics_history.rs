@@ -0,0 +1,120 @@
+use crate::database::models::{MetricSample, TunnelCounters};
+use crate::server::events::{self, TunnelEventKind, HIGH_TRAFFIC_BYTES};
+use crate::server::model::{unix_millis, TunnelMetric, TunnelState};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// 采样周期
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(60);
+/// 历史数据保留 30 天
+pub const RETENTION: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// 把累计流量换算为每个采样周期的增量
+#[derive(Default)]
+pub struct MetricsSampler {
+    last_totals: HashMap<String, (u128, u128)>,
+    last_counters: HashMap<String, (u64, u64)>,
+}
+
+impl MetricsSampler {
+    pub fn sample(&mut self, metrics: &HashMap<String, TunnelMetric>) -> Vec<MetricSample> {
+        let timestamp = unix_millis();
+        let mut samples = Vec::new();
+
+        for (id, metric) in metrics {
+            let totals = (metric.traffic.send_bytes, metric.traffic.recv_bytes);
+            let (last_tx, last_rx) = self
+                .last_totals
+                .insert(id.clone(), totals)
+                .unwrap_or((0, 0));
+            // 隧道重启后计数归零，此时整段都算作本周期的流量
+            let tx = if totals.0 >= last_tx {
+                totals.0 - last_tx
+            } else {
+                totals.0
+            };
+            let rx = if totals.1 >= last_rx {
+                totals.1 - last_rx
+            } else {
+                totals.1
+            };
+
+            let total = tx.saturating_add(rx).min(u64::MAX as u128) as u64;
+            if total >= HIGH_TRAFFIC_BYTES {
+                events::record(id, TunnelEventKind::HighTraffic, Some(total.to_string()));
+            }
+
+            // 暂停统计期间只推进基线，不写入历史
+            if metric.stats_paused {
+                continue;
+            }
+
+            let running = matches!(
+                metric.tunnel_state,
+                TunnelState::Listening(_) | TunnelState::Running(_) | TunnelState::Unstable(_)
+            );
+            if !running && tx == 0 && rx == 0 {
+                continue;
+            }
+
+            samples.push(MetricSample {
+                tunnel_id: id.clone(),
+                timestamp,
+                tx_bytes: tx.min(u64::MAX as u128) as u64,
+                rx_bytes: rx.min(u64::MAX as u128) as u64,
+                latency_ms: metric.last_latency.map(|d| d.as_millis() as u64),
+            });
+        }
+
+        // 已删除的隧道不再跟踪
+        self.last_totals.retain(|id, _| metrics.contains_key(id));
+        samples
+    }
+
+    /// 连接数和失败数的增量，与流量一样在会话重建后按归零处理
+    pub fn counter_deltas(
+        &mut self,
+        metrics: &HashMap<String, TunnelMetric>,
+    ) -> Vec<TunnelCounters> {
+        let mut deltas = Vec::new();
+        for (id, metric) in metrics {
+            let current = (metric.connections, metric.connection_errors);
+            let last = self
+                .last_counters
+                .insert(id.clone(), current)
+                .unwrap_or((0, 0));
+            let (connections, connection_errors) = counter_delta(current, last);
+            if connections > 0 || connection_errors > 0 {
+                deltas.push(TunnelCounters {
+                    tunnel_id: id.clone(),
+                    connections,
+                    connection_errors,
+                });
+            }
+        }
+        self.last_counters.retain(|id, _| metrics.contains_key(id));
+        deltas
+    }
+
+    /// 尚未写入数据库的连接数和失败数
+    pub fn pending_counters(&self, id: &str, metric: &TunnelMetric) -> (u64, u64) {
+        let last = self.last_counters.get(id).copied().unwrap_or((0, 0));
+        counter_delta((metric.connections, metric.connection_errors), last)
+    }
+
+    /// 取出尚未写入的增量并清除基线，隧道移除后重新启动时从零开始计算
+    pub fn take_pending_counters(&mut self, id: &str, metric: &TunnelMetric) -> (u64, u64) {
+        let last = self.last_counters.remove(id).unwrap_or((0, 0));
+        counter_delta((metric.connections, metric.connection_errors), last)
+    }
+
+    /// 早于该时间戳的历史会被清理
+    pub fn retain_after() -> u64 {
+        unix_millis().saturating_sub(RETENTION.as_millis() as u64)
+    }
+}
+
+fn counter_delta(current: (u64, u64), last: (u64, u64)) -> (u64, u64) {
+    let delta = |now: u64, before: u64| if now >= before { now - before } else { now };
+    (delta(current.0, last.0), delta(current.1, last.1))
+}
@@ -0,0 +1,233 @@
+use crate::server::model::{unix_millis, TunnelMetric};
+use anyhow::{anyhow, Context, Result};
+use futures_util::{SinkExt, StreamExt};
+use log::{debug, info, warn};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+
+/// 单个隧道的指标快照，推送给外部看板
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TunnelMetricSnapshot {
+    pub id: String,
+    pub state: &'static str,
+    pub error: Option<String>,
+    pub latency_ms: Option<u128>,
+    pub send_bytes: u128,
+    pub recv_bytes: u128,
+    pub local_port: Option<u16>,
+}
+
+impl TunnelMetricSnapshot {
+    fn new(id: &str, metric: &TunnelMetric) -> Self {
+        let (state, error) = metric.tunnel_state.label();
+        Self {
+            id: id.to_string(),
+            state,
+            error,
+            latency_ms: metric.last_latency.map(|d| d.as_millis()),
+            send_bytes: metric.traffic.send_bytes,
+            recv_bytes: metric.traffic.recv_bytes,
+            local_port: metric.local_addr.map(|addr| addr.port()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+struct MetricsFrame {
+    timestamp: u64,
+    tunnels: Vec<TunnelMetricSnapshot>,
+}
+
+/// 本地 WebSocket 指标服务，默认关闭
+///
+/// 指标来自托盘使用的同一份状态轮询，有隧道的指标变化时向所有客户端推送一帧 JSON。
+#[derive(Clone)]
+pub struct MetricsWsServer {
+    frame_tx: Arc<watch::Sender<String>>,
+    /// 运行中的监听地址、是否允许远程连接和停止用的 token
+    running: Arc<Mutex<Option<(SocketAddr, bool, CancellationToken)>>>,
+    /// 上次推送的快照，没有变化时不再推送
+    last_tunnels: Arc<Mutex<Vec<TunnelMetricSnapshot>>>,
+}
+
+impl MetricsWsServer {
+    pub fn new() -> Self {
+        let (frame_tx, _) = watch::channel(String::new());
+        Self {
+            frame_tx: Arc::new(frame_tx),
+            running: Arc::new(Mutex::new(None)),
+            last_tunnels: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// 发布最新的全部隧道指标，返回是否推送了新的一帧
+    pub fn publish(&self, metrics: &HashMap<String, TunnelMetric>) -> bool {
+        if self.running.lock().unwrap().is_none() {
+            return false;
+        }
+
+        let mut tunnels: Vec<TunnelMetricSnapshot> = metrics
+            .iter()
+            .map(|(id, metric)| TunnelMetricSnapshot::new(id, metric))
+            .collect();
+        tunnels.sort_by(|a, b| a.id.cmp(&b.id));
+        {
+            let mut last = self.last_tunnels.lock().unwrap();
+            if *last == tunnels {
+                return false;
+            }
+            last.clone_from(&tunnels);
+        }
+        let frame = MetricsFrame {
+            timestamp: unix_millis(),
+            tunnels,
+        };
+
+        match serde_json::to_string(&frame) {
+            Ok(json) => {
+                self.frame_tx.send_replace(json);
+                true
+            }
+            Err(e) => {
+                warn!("Failed to serialize metrics frame: {}", e);
+                false
+            }
+        }
+    }
+
+    /// 校验监听地址，非 loopback 地址需要允许远程连接
+    pub fn validate_addr(bind_addr: &str, allow_remote: bool) -> Result<SocketAddr> {
+        let addr: SocketAddr = bind_addr
+            .parse()
+            .with_context(|| format!("Invalid metrics server address: {}", bind_addr))?;
+        if !addr.ip().is_loopback() && !allow_remote {
+            return Err(anyhow!(
+                "Metrics server address {} is not loopback, enable remote access to allow it",
+                addr
+            ));
+        }
+        Ok(addr)
+    }
+
+    /// 按设置启动、重启或停止服务
+    ///
+    /// 新设置不合法时也先停掉正在运行的服务，避免继续按旧设置对外提供指标。
+    pub async fn apply(&self, enabled: bool, bind_addr: &str, allow_remote: bool) -> Result<()> {
+        if !enabled {
+            self.stop();
+            return Ok(());
+        }
+
+        let addr = match Self::validate_addr(bind_addr, allow_remote) {
+            Ok(addr) => addr,
+            Err(e) => {
+                self.stop();
+                return Err(e);
+            }
+        };
+
+        // 监听循环按启动时的 allow_remote 过滤客户端，变化时也要重启
+        if let Some((current, current_allow_remote, _)) = *self.running.lock().unwrap() {
+            if current == addr && current_allow_remote == allow_remote {
+                return Ok(());
+            }
+        }
+        self.stop();
+
+        let listener = TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("Failed to bind metrics server on {}", addr))?;
+        let token = CancellationToken::new();
+        *self.running.lock().unwrap() = Some((addr, allow_remote, token.clone()));
+        info!("Metrics WebSocket server listening on ws://{}", addr);
+
+        let frame_tx = self.frame_tx.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = token.cancelled() => break,
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, peer)) => {
+                            // 默认只允许本机连接
+                            if !allow_remote && !peer.ip().is_loopback() {
+                                warn!("Rejected metrics client {} (loopback only)", peer);
+                                continue;
+                            }
+                            tokio::spawn(Self::serve_client(
+                                stream,
+                                peer,
+                                frame_tx.subscribe(),
+                                token.clone(),
+                            ));
+                        }
+                        Err(e) => {
+                            warn!("Metrics server accept error: {}", e);
+                            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                        }
+                    }
+                }
+            }
+            info!("Metrics WebSocket server on {} stopped", addr);
+        });
+
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        if let Some((_, _, token)) = self.running.lock().unwrap().take() {
+            token.cancel();
+        }
+    }
+
+    async fn serve_client(
+        stream: TcpStream,
+        peer: SocketAddr,
+        mut frame_rx: watch::Receiver<String>,
+        token: CancellationToken,
+    ) {
+        let mut ws = match tokio_tungstenite::accept_async(stream).await {
+            Ok(ws) => ws,
+            Err(e) => {
+                debug!("WebSocket handshake with {} failed: {}", peer, e);
+                return;
+            }
+        };
+        debug!("Metrics client {} connected", peer);
+
+        // 连接后先推送当前快照
+        let current = frame_rx.borrow_and_update().clone();
+        if !current.is_empty() && ws.send(Message::text(current)).await.is_err() {
+            return;
+        }
+
+        loop {
+            tokio::select! {
+                _ = token.cancelled() => {
+                    let _ = ws.close(None).await;
+                    break;
+                }
+                changed = frame_rx.changed() => {
+                    if changed.is_err() {
+                        break;
+                    }
+                    let frame = frame_rx.borrow_and_update().clone();
+                    if ws.send(Message::text(frame)).await.is_err() {
+                        break;
+                    }
+                }
+                incoming = ws.next() => match incoming {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    // 只推送，不处理客户端消息
+                    Some(Ok(_)) => {}
+                }
+            }
+        }
+        debug!("Metrics client {} disconnected", peer);
+    }
+}
@@ -1,8 +1,38 @@
 mod actor;
+mod conn_limit;
+mod control_master;
+pub mod discovery;
+pub mod dns;
+pub mod events;
+mod failure_hook;
+pub mod key_path;
+pub mod known_hosts;
+pub mod link_quality;
+mod logs;
+pub mod maintenance;
 mod manager;
+mod metrics_history;
+pub mod metrics_ws;
 pub mod model;
+pub mod mtu;
+pub mod overhead;
+pub mod port_owner;
+pub mod preview;
+pub mod proxy;
+pub mod proxy_protocol;
 pub mod remote_cmd;
+pub mod remote_tail;
+pub mod screen_lock;
 mod server_manager;
+mod socks;
 pub mod ssh;
+pub mod ssh_command;
+pub mod system_ssh;
+pub mod throttle;
+pub mod trace;
 
 pub use server_manager::ServerManager;
+
+/// 子进程不创建控制台窗口，否则 GUI 进程每次运行 ssh、tasklist 等命令都会闪出窗口
+#[cfg(windows)]
+pub(crate) const CREATE_NO_WINDOW: u32 = 0x0800_0000;
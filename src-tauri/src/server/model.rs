@@ -1,23 +1,62 @@
 use crate::database::entity::tunnel_config::Model as TunnelModel;
-use anyhow::{anyhow, Result};
+use crate::database::models::PortMapping;
+use crate::server::conn_limit::DEFAULT_QUEUE_TIMEOUT;
+use crate::server::proxy::{self, Proxy};
+use crate::server::proxy_protocol::ProxyProtocolVersion;
+use crate::server::remote_cmd::RemoteShell;
+use crate::server::ssh::{Ssh, SshTasks};
+use anyhow::{anyhow, Context, Result};
+use std::net::{IpAddr, SocketAddr};
 use std::pin::Pin;
 use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
 use std::task::Poll;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
 
 #[derive(Clone, Debug)]
 pub enum TunnelAuth {
     Password(String),
-    Key(String),
+    /// 私钥路径和可选的口令
+    Key(String, Option<String>),
+    /// 使用 ssh-agent (SSH_AUTH_SOCK) 中的身份
+    Agent,
+}
+
+impl TunnelAuth {
+    /// 对应配置中的 auth_type
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TunnelAuth::Password(_) => "password",
+            TunnelAuth::Key(..) => "key",
+            TunnelAuth::Agent => "agent",
+        }
+    }
+
+    /// 不含密码和口令的说明，用于日志和事件
+    pub fn describe(&self) -> String {
+        match self {
+            TunnelAuth::Password(_) => "password".to_string(),
+            TunnelAuth::Key(path, _) => format!("key {}", path),
+            TunnelAuth::Agent => "ssh-agent".to_string(),
+        }
+    }
 }
 
 impl TryFrom<&TunnelModel> for TunnelAuth {
     type Error = anyhow::Error;
 
     fn try_from(value: &TunnelModel) -> Result<Self> {
-        let auth = match value.auth_type.as_str() {
+        Self::with_type(value, &value.auth_type)
+    }
+}
+
+impl TunnelAuth {
+    /// 按指定的认证方式取隧道保存的凭据
+    fn with_type(value: &TunnelModel, auth_type: &str) -> Result<Self> {
+        let auth = match auth_type {
             "password" => {
                 let password = value
                     .ssh_password
@@ -30,8 +69,10 @@ impl TryFrom<&TunnelModel> for TunnelAuth {
                     .ssh_key_path
                     .as_ref()
                     .ok_or_else(|| anyhow!("Key path not provided for key authentication"))?;
-                TunnelAuth::Key(key_path.clone())
+                let passphrase = value.ssh_key_passphrase.clone().filter(|p| !p.is_empty());
+                TunnelAuth::Key(key_path.clone(), passphrase)
             }
+            "agent" => TunnelAuth::Agent,
             other => return Err(anyhow!("Invalid auth type: {}", other)),
         };
 
@@ -84,6 +125,7 @@ impl TryFrom<&TunnelModel> for TunnelAuth {
 pub struct SshConfig {
     #[allow(dead_code)]
     pub connect_config: SshConnectConfig,
+    #[allow(dead_code)]
     pub forward_config: Option<SshForwardConfig>,
 }
 
@@ -103,6 +145,39 @@ pub struct SshConnectConfig {
 
     pub ssh_user: String,
     pub auth: TunnelAuth,
+    /// 配置的方式被拒绝后依次尝试的其它凭据，未开启 auth_fallback 时为空
+    pub fallback_auth: Vec<TunnelAuth>,
+    /// 经跳板机连接，出站连接的设置 (源地址、DSCP) 作用于跳板机，代理见 [`JumpHost::proxy`]
+    pub jump: Option<JumpHost>,
+    /// 仅使用配置的密钥认证，不尝试默认密钥
+    pub identities_only: bool,
+    /// 远程命令的执行方式
+    pub remote_shell: RemoteShell,
+    /// 出站 SSH 连接绑定的本地地址
+    pub source_addr: Option<IpAddr>,
+    /// 出站 SSH 连接的 DSCP 标记
+    pub dscp: Option<u8>,
+    /// 经由系统代理连接，隧道设置了 bypass_proxy 时为 None
+    pub proxy: Option<Proxy>,
+}
+
+/// 解析出站连接的本地地址，并确认它属于本机且可以绑定
+pub fn parse_source_addr(value: Option<&str>) -> Result<Option<IpAddr>> {
+    let value = match value.map(str::trim) {
+        Some(value) if !value.is_empty() => value,
+        _ => return Ok(None),
+    };
+    let ip: IpAddr = value
+        .parse()
+        .map_err(|_| anyhow!("Invalid source address '{}', expected an IP address", value))?;
+    std::net::UdpSocket::bind(SocketAddr::new(ip, 0)).map_err(|e| {
+        anyhow!(
+            "Source address {} is not assignable on this machine: {}",
+            ip,
+            e
+        )
+    })?;
+    Ok(Some(ip))
 }
 
 impl TryFrom<&TunnelModel> for SshConnectConfig {
@@ -110,6 +185,11 @@ impl TryFrom<&TunnelModel> for SshConnectConfig {
 
     fn try_from(db_config: &TunnelModel) -> Result<Self> {
         let auth = TunnelAuth::try_from(db_config)?;
+        let fallback_auth = if db_config.auth_fallback {
+            fallback_auth(db_config, &auth)
+        } else {
+            Vec::new()
+        };
 
         Ok(Self {
             ssh_host: db_config.ssh_host.clone(),
@@ -117,16 +197,289 @@ impl TryFrom<&TunnelModel> for SshConnectConfig {
 
             ssh_user: db_config.ssh_username.clone(),
             auth,
+            fallback_auth,
+            jump: JumpHost::from_model(db_config)?,
+            identities_only: db_config.identities_only,
+            remote_shell: RemoteShell::parse(db_config.remote_shell.as_deref())?,
+            source_addr: parse_source_addr(db_config.source_addr.as_deref())?,
+            dscp: db_config.dscp,
+            proxy: if db_config.bypass_proxy {
+                None
+            } else {
+                proxy::system_proxy(&db_config.ssh_host)
+            },
         })
     }
 }
 
+/// 跳板机 (`ssh -J`)，只支持一跳，跳板机本身不能再经其它跳板
+#[derive(Clone, Debug)]
+pub struct JumpHost {
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    pub auth: TunnelAuth,
+    /// 连接跳板机经由的系统代理，按跳板机的主机名判断 NO_PROXY，设置了 bypass_proxy 时为 None
+    pub proxy: Option<Proxy>,
+}
+
+impl JumpHost {
+    pub fn new(host: &str, port: u16, user: String, auth: TunnelAuth, bypass_proxy: bool) -> Self {
+        let host = unbracket_host(host).to_string();
+        let proxy = if bypass_proxy {
+            None
+        } else {
+            proxy::system_proxy(&host)
+        };
+        Self {
+            host,
+            port,
+            user,
+            auth,
+            proxy,
+        }
+    }
+
+    fn from_model(db_config: &TunnelModel) -> Result<Option<Self>> {
+        let Some(host) = db_config
+            .jump_host
+            .as_deref()
+            .map(str::trim)
+            .filter(|host| !host.is_empty())
+        else {
+            return Ok(None);
+        };
+        let auth_type = db_config
+            .jump_auth
+            .as_deref()
+            .filter(|auth| !auth.is_empty())
+            .unwrap_or(&db_config.auth_type);
+        Ok(Some(Self::new(
+            host,
+            db_config.jump_port.unwrap_or(22),
+            db_config
+                .jump_user
+                .clone()
+                .filter(|user| !user.trim().is_empty())
+                .unwrap_or_else(|| db_config.ssh_username.clone()),
+            TunnelAuth::with_type(db_config, auth_type).context("Invalid jump host auth")?,
+            db_config.bypass_proxy,
+        )))
+    }
+}
+
+/// 与配置方式不同的已保存凭据：密钥认证回退到密码，密码认证回退到私钥，
+/// 没有保存私钥时用第一个默认密钥 (IdentitiesOnly 关闭时其余默认密钥也会尝试)
+fn fallback_auth(db_config: &TunnelModel, auth: &TunnelAuth) -> Vec<TunnelAuth> {
+    let saved = |value: &Option<String>| value.clone().filter(|v| !v.is_empty());
+    let fallback = match auth {
+        TunnelAuth::Key(..) | TunnelAuth::Agent => {
+            saved(&db_config.ssh_password).map(TunnelAuth::Password)
+        }
+        TunnelAuth::Password(_) => match saved(&db_config.ssh_key_path) {
+            Some(key_path) => Some(TunnelAuth::Key(
+                key_path,
+                saved(&db_config.ssh_key_passphrase),
+            )),
+            None => Ssh::default_identities()
+                .into_iter()
+                .next()
+                .map(|path| TunnelAuth::Key(path.display().to_string(), None)),
+        },
+    };
+    fallback.into_iter().collect()
+}
+
+/// 转发方向
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ForwardDirection {
+    /// `ssh -L`：本地监听，经 SSH 主机连接目标
+    #[default]
+    Local,
+    /// `ssh -R`：SSH 主机在 `remote_host:remote_port` 监听，连接转回本地的 `local_host:local_port`
+    Remote,
+    /// `ssh -D`：本地作为 SOCKS5 代理监听，目标由客户端指定，remote_host/remote_port 不使用
+    Dynamic,
+}
+
+impl ForwardDirection {
+    /// 解析前端的 direction：未配置或 `local` 为本地转发，`remote` 为反向转发
+    pub fn parse(value: Option<&str>) -> Result<Self> {
+        match value.map(str::trim).filter(|v| !v.is_empty()) {
+            None | Some("local") => Ok(Self::Local),
+            Some("remote") => Ok(Self::Remote),
+            Some(other) => Err(anyhow!(
+                "Invalid forward direction '{}', expected local or remote",
+                other
+            )),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct SshForwardConfig {
+    pub direction: ForwardDirection,
     pub local_host: String,
     pub local_port: u16,
+    /// 本地端口被占用时改用后续空闲端口
+    pub auto_remap_port: bool,
     pub remote_host: String,
     pub remote_port: u16,
+    pub proxy_protocol: Option<ProxyProtocolVersion>,
+    /// 共用同一会话的额外端口段，转发到 `remote_host` 上对应的端口
+    pub port_map: Vec<PortMapping>,
+    /// 每个方向的读缓冲区大小
+    pub io_buffer_size: usize,
+    /// 连接空闲超过该时长后发送保活探测，None 表示关闭
+    pub keepalive_idle: Option<Duration>,
+    /// 同时转发的连接上限，None 表示不限制
+    pub max_connections: Option<u32>,
+    /// 达到上限后新连接排队等待的最长时间
+    pub queue_timeout: Duration,
+    /// 锁屏期间拒绝新连接
+    pub pause_when_locked: bool,
+    /// 转发连接本地 socket 的 DSCP 标记
+    pub dscp: Option<u8>,
+}
+
+// 转发读缓冲区：默认 64KB，限制在 4KB ~ 4MB
+pub const DEFAULT_IO_BUFFER_SIZE: usize = 64 * 1024;
+const MIN_IO_BUFFER_SIZE: usize = 4 * 1024;
+const MAX_IO_BUFFER_SIZE: usize = 4 * 1024 * 1024;
+
+pub fn keepalive_idle_from(db_config: &TunnelModel) -> Option<Duration> {
+    db_config
+        .keepalive_idle_secs
+        .filter(|secs| *secs > 0)
+        .map(|secs| Duration::from_secs(secs as u64))
+}
+
+pub fn queue_timeout_from(db_config: &TunnelModel) -> Duration {
+    db_config
+        .queue_timeout_secs
+        .filter(|secs| *secs > 0)
+        .map(|secs| Duration::from_secs(secs as u64))
+        .unwrap_or(DEFAULT_QUEUE_TIMEOUT)
+}
+
+pub fn io_buffer_size_from(db_config: &TunnelModel) -> usize {
+    db_config
+        .io_buffer_size
+        .map(|size| (size as usize).clamp(MIN_IO_BUFFER_SIZE, MAX_IO_BUFFER_SIZE))
+        .unwrap_or(DEFAULT_IO_BUFFER_SIZE)
+}
+
+impl SshForwardConfig {
+    /// 展开端口映射，得到每个额外端口各自的转发配置
+    pub fn mapped_forwards(&self) -> Vec<SshForwardConfig> {
+        self.port_map
+            .iter()
+            .flat_map(|mapping| {
+                (mapping.local_start..=mapping.local_end).map(move |local_port| {
+                    (
+                        local_port,
+                        mapping.remote_base + (local_port - mapping.local_start),
+                    )
+                })
+            })
+            .map(|(local_port, remote_port)| SshForwardConfig {
+                local_port,
+                remote_port,
+                auto_remap_port: false,
+                port_map: Vec::new(),
+                ..self.clone()
+            })
+            .collect()
+    }
+}
+
+// 单个隧道最多额外监听的端口数
+const MAX_MAPPED_PORTS: usize = 1024;
+
+/// 去掉 IPv6 字面量的方括号，`channel_open_direct_tcpip` 需要不带括号的地址
+pub fn unbracket_host(host: &str) -> &str {
+    let host = host.trim();
+    match host.strip_prefix('[').and_then(|h| h.strip_suffix(']')) {
+        Some(inner) if inner.parse::<std::net::Ipv6Addr>().is_ok() => inner,
+        _ => host,
+    }
+}
+
+/// `host:port`，IPv6 地址加上方括号
+pub fn format_host_port(host: &str, port: u16) -> String {
+    if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+/// 解析隧道保存的端口映射 (JSON) 并校验
+pub fn port_map_from(db_config: &TunnelModel) -> Result<Vec<PortMapping>> {
+    let port_map: Vec<PortMapping> = match db_config.port_map.as_deref() {
+        Some(json) => serde_json::from_str(json).context("Invalid port map")?,
+        None => Vec::new(),
+    };
+    validate_port_map(db_config.local_port, &port_map)?;
+    Ok(port_map)
+}
+
+/// 校验端口段合法且互不重叠，也不能与主转发端口冲突
+pub fn validate_port_map(local_port: Option<u16>, port_map: &[PortMapping]) -> Result<()> {
+    let mut total = 0usize;
+    for mapping in port_map {
+        if mapping.local_start == 0 || mapping.local_start > mapping.local_end {
+            return Err(anyhow!(
+                "Invalid port range {}-{}",
+                mapping.local_start,
+                mapping.local_end
+            ));
+        }
+        let span = (mapping.local_end - mapping.local_start) as u32;
+        if mapping.remote_base == 0 || mapping.remote_base as u32 + span > u16::MAX as u32 {
+            return Err(anyhow!(
+                "Remote ports for range {}-{} exceed 65535 (base {})",
+                mapping.local_start,
+                mapping.local_end,
+                mapping.remote_base
+            ));
+        }
+        if let Some(port) = local_port {
+            if (mapping.local_start..=mapping.local_end).contains(&port) {
+                return Err(anyhow!(
+                    "Port range {}-{} overlaps the tunnel's local port {}",
+                    mapping.local_start,
+                    mapping.local_end,
+                    port
+                ));
+            }
+        }
+        total += span as usize + 1;
+    }
+
+    if total > MAX_MAPPED_PORTS {
+        return Err(anyhow!(
+            "Port map covers {} ports, at most {} are allowed",
+            total,
+            MAX_MAPPED_PORTS
+        ));
+    }
+
+    let mut sorted: Vec<&PortMapping> = port_map.iter().collect();
+    sorted.sort_by_key(|mapping| mapping.local_start);
+    for pair in sorted.windows(2) {
+        if pair[1].local_start <= pair[0].local_end {
+            return Err(anyhow!(
+                "Port ranges {}-{} and {}-{} overlap",
+                pair[0].local_start,
+                pair[0].local_end,
+                pair[1].local_start,
+                pair[1].local_end
+            ));
+        }
+    }
+
+    Ok(())
 }
 
 impl TryFrom<&TunnelModel> for SshForwardConfig {
@@ -137,11 +490,34 @@ impl TryFrom<&TunnelModel> for SshForwardConfig {
             return Err(anyhow!("type error"));
         }
 
+        let (direction, remote_host, remote_port) = match db_config.forward_type.as_str() {
+            // SOCKS 模式忽略 target_host/target_port
+            "dynamic" => (ForwardDirection::Dynamic, String::new(), 0),
+            forward_type => (
+                if forward_type == "remote" {
+                    ForwardDirection::Remote
+                } else {
+                    ForwardDirection::Local
+                },
+                unbracket_host(db_config.target_host.as_deref().unwrap()).to_string(),
+                db_config.target_port.unwrap(),
+            ),
+        };
         Ok(SshForwardConfig {
+            direction,
             local_host: "127.0.0.1".to_string(),
             local_port: db_config.local_port.unwrap(),
-            remote_host: db_config.target_host.clone().unwrap(),
-            remote_port: db_config.target_port.unwrap(),
+            auto_remap_port: db_config.auto_remap_port,
+            remote_host,
+            remote_port,
+            proxy_protocol: ProxyProtocolVersion::parse(db_config.proxy_protocol.as_deref())?,
+            port_map: port_map_from(db_config)?,
+            io_buffer_size: io_buffer_size_from(db_config),
+            keepalive_idle: keepalive_idle_from(db_config),
+            max_connections: db_config.max_connections,
+            queue_timeout: queue_timeout_from(db_config),
+            pause_when_locked: db_config.pause_when_locked,
+            dscp: db_config.dscp,
         })
     }
 }
@@ -151,11 +527,34 @@ pub enum TunnelState {
     #[default]
     Stopped,
     Starting,
+    /// docker 模式下等待容器健康检查通过
+    WaitingForHealth,
+    /// 本地端口已监听，但还没有一次到目标的转发成功
+    Listening(Duration),
+    /// 已确认可以转发到目标 (Ready)
     Running(Duration),
+    /// 会话正常但转发目标不可达
+    Unstable(String),
     Stopping,
     Error(String),
 }
 
+impl TunnelState {
+    /// 状态名和错误信息，对外输出的 JSON 中使用
+    pub fn label(&self) -> (&'static str, Option<String>) {
+        match self {
+            TunnelState::Stopped => ("stopped", None),
+            TunnelState::Starting => ("starting", None),
+            TunnelState::WaitingForHealth => ("waiting_for_health", None),
+            TunnelState::Listening(_) => ("listening", None),
+            TunnelState::Running(_) => ("running", None),
+            TunnelState::Unstable(e) => ("unstable", Some(e.clone())),
+            TunnelState::Stopping => ("stopping", None),
+            TunnelState::Error(e) => ("error", Some(e.clone())),
+        }
+    }
+}
+
 impl From<&SSHStatus> for TunnelState {
     fn from(status: &SSHStatus) -> Self {
         match status {
@@ -195,12 +594,35 @@ impl Traffic {
         self.send_bytes = send_bytes;
         self.recv_bytes = recv_bytes;
     }
+
+    /// 相对上一次计数的增量，会话重建后计数归零时整段都算作增量
+    pub fn delta_since(&self, previous: &Traffic) -> (u128, u128) {
+        let delta = |now: u128, before: u128| if now >= before { now - before } else { now };
+        (
+            delta(self.send_bytes, previous.send_bytes),
+            delta(self.recv_bytes, previous.recv_bytes),
+        )
+    }
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct SSHEvent {
     pub ssh_status: SSHStatus,
     pub traffic: Traffic,
+    pub active_connections: u32,
+    pub queued_connections: u32,
+    /// 本次会话接受的连接数
+    pub connections: u64,
+    /// 本次会话中被拒绝或出错的连接数
+    pub connection_errors: u64,
+    /// 目标探测失败的原因，未开启探测或目标可达时为 None
+    pub target_error: Option<String>,
+    /// 本次会话中已有一次到目标的 direct-tcpip 通道成功打开
+    pub target_confirmed: bool,
+    /// 本次会话中监听回环地址却来自非回环地址的连接数
+    pub unexpected_sources: u64,
+    /// 最近一次此类连接的说明
+    pub last_unexpected_source: Option<String>,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -208,22 +630,107 @@ pub struct SSHEvent {
 pub struct TunnelMetric {
     pub tunnel_state: TunnelState,
     pub traffic: Traffic,
+    /// 健康检查最近一次测得的会话内 RTT，连接不稳定时保留上一次的值
+    pub last_latency: Option<Duration>,
+    /// 实际监听的本地地址，自动换端口后可能与配置不同
+    pub local_addr: Option<SocketAddr>,
+    /// 暂停统计时流量不计入历史，只累加到 `window_traffic`
+    pub stats_paused: bool,
+    /// 最近一次 pause_stats 之后的流量，恢复统计后保留到下次暂停
+    pub window_traffic: Traffic,
+    /// 正在转发的连接数
+    pub active_connections: u32,
+    /// 达到并发上限后排队等待的连接数
+    pub queued_connections: u32,
+    /// 本次会话累计的连接数和失败数，重连后归零，持久化的总数见 `DB::load_tunnel_counters`
+    pub connections: u64,
+    pub connection_errors: u64,
+    /// 最近一次有流量的时间，用于判断退出时是否有传输正在进行
+    pub last_traffic_at: Option<tokio::time::Instant>,
+    /// 本次启动的时间，用于计算运行时长，停止后清空
+    pub started_at: Option<tokio::time::Instant>,
+    /// 本次会话中监听回环地址却来自非回环地址的连接数，用于发现意外暴露的端口
+    pub unexpected_sources: u64,
+    /// 本次会话回退成功的认证方式，与配置相同时为 None
+    pub auth_fallback: Option<TunnelAuth>,
 }
 
 impl From<&SSHEvent> for TunnelMetric {
     fn from(event: &SSHEvent) -> Self {
+        let last_latency = match event.ssh_status {
+            SSHStatus::Healthy { latency } => Some(latency),
+            _ => None,
+        };
         Self {
             tunnel_state: TunnelState::from(&event.ssh_status),
             traffic: event.traffic.clone(),
+            last_latency,
+            local_addr: None,
+            stats_paused: false,
+            window_traffic: Traffic::default(),
+            active_connections: event.active_connections,
+            queued_connections: event.queued_connections,
+            connections: event.connections,
+            connection_errors: event.connection_errors,
+            last_traffic_at: None,
+            started_at: None,
+            unexpected_sources: event.unexpected_sources,
+            auth_fallback: None,
         }
     }
 }
 
+/// 推送给前端 `tunnel-log` 事件的单行日志
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct LogPayload {
+    pub id: String,
+    pub line: String,
+    pub level: String,
+    pub timestamp: u64,
+}
+
+/// 当前 Unix 时间戳 (毫秒)
+pub fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
 #[derive(Debug)]
 pub enum TunnelCommand {
     Start,
     Stop,
     Remove,
+    /// 同 Remove，停止前交出 Metrics 任务和会话任务的句柄，用于检查是否有遗留任务
+    RemoveWithReport(oneshot::Sender<ActorTasks>),
+    /// 替换不影响连接的配置 (名称、重连策略、失败钩子)，不重启隧道
+    UpdateConfig(Box<TunnelModel>),
+    /// 暂停历史统计并开始新的测量窗口
+    PauseStats,
+    /// 恢复历史统计，保留测量窗口的结果
+    ResumeStats,
+}
+
+/// actor 退出时交出的任务句柄，控制连接模式下没有会话任务
+#[derive(Debug, Default)]
+pub struct ActorTasks {
+    pub metrics_task: Option<JoinHandle<()>>,
+    pub ssh: Option<SshTasks>,
+}
+
+/// 移除隧道后各类任务是否在期限内退出，`*_left` 为期限到达时仍在运行的数量
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ShutdownReport {
+    pub id: String,
+    pub actor_exited: bool,
+    pub metrics_task_exited: bool,
+    pub monitors_left: usize,
+    pub accept_loops_left: usize,
+    pub connections_left: usize,
+    pub elapsed_ms: u64,
+    /// 全部任务都已退出
+    pub clean: bool,
 }
 
 pub struct TrafficCounter<T> {
@@ -287,3 +794,25 @@ impl<T: AsyncWrite + Unpin> AsyncWrite for TrafficCounter<T> {
         Pin::new(&mut self.inner).poll_shutdown(cx)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unbracket_ipv6_literals_only() {
+        assert_eq!(unbracket_host("[::1]"), "::1");
+        assert_eq!(unbracket_host(" [fd00::2] "), "fd00::2");
+        assert_eq!(unbracket_host("::1"), "::1");
+        assert_eq!(unbracket_host("db.internal"), "db.internal");
+        // 方括号里不是 IPv6 地址时原样保留
+        assert_eq!(unbracket_host("[db.internal]"), "[db.internal]");
+    }
+
+    #[test]
+    fn format_host_port_brackets_ipv6() {
+        assert_eq!(format_host_port("::1", 80), "[::1]:80");
+        assert_eq!(format_host_port("10.0.0.1", 22), "10.0.0.1:22");
+        assert_eq!(format_host_port("db.internal", 5432), "db.internal:5432");
+    }
+}
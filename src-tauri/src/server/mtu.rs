@@ -0,0 +1,177 @@
+use crate::server::discovery;
+use crate::server::model::SshConnectConfig;
+use crate::server::remote_cmd::RemoteCommand;
+use crate::server::ssh::Ssh;
+use anyhow::{anyhow, Result};
+use log::info;
+use serde::Serialize;
+use shell_escape::escape;
+use std::borrow::Cow;
+use tokio::time::{timeout, Duration};
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(30);
+// IPv4 + ICMP 头部
+const HEADER_BYTES: u16 = 28;
+const MIN_MTU: u16 = 576;
+const MAX_MTU: u16 = 1500;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PathMtu {
+    /// 被探测的主机，未指定时为 SSH 客户端地址
+    pub host: String,
+    pub mtu: u16,
+    pub probes: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PingResult {
+    Ok,
+    Fail,
+    Unsupported,
+}
+
+/// 禁止分片的单次 ping (`ping -M do`，需要 iputils)
+struct PingDfCmd {
+    host: String,
+    payload: u16,
+}
+
+impl RemoteCommand for PingDfCmd {
+    type Output = PingResult;
+
+    fn to_shell_string(&self) -> String {
+        format!(
+            "out=$(ping -M do -c 1 -W 1 -s {} {} 2>&1); \
+             if [ $? -eq 0 ]; then echo ok; \
+             elif echo \"$out\" | grep -qiE 'invalid|unrecognized|usage'; then echo unsupported; \
+             else echo fail; fi",
+            self.payload,
+            escape(Cow::from(&self.host))
+        )
+    }
+
+    fn requires_sudo(&self) -> bool {
+        false
+    }
+
+    fn needs_shell(&self) -> bool {
+        true
+    }
+
+    fn parse_output(&self, output: &str) -> Option<Self::Output> {
+        match output.trim() {
+            "ok" => Some(PingResult::Ok),
+            "fail" => Some(PingResult::Fail),
+            "unsupported" => Some(PingResult::Unsupported),
+            _ => None,
+        }
+    }
+}
+
+/// 读取 `$SSH_CLIENT` 中的客户端地址
+struct SshClientAddrCmd;
+
+impl RemoteCommand for SshClientAddrCmd {
+    type Output = String;
+
+    fn to_shell_string(&self) -> String {
+        "echo $SSH_CLIENT".to_string()
+    }
+
+    fn requires_sudo(&self) -> bool {
+        false
+    }
+
+    fn needs_shell(&self) -> bool {
+        true
+    }
+
+    fn parse_output(&self, output: &str) -> Option<Self::Output> {
+        output.split_whitespace().next().map(str::to_string)
+    }
+}
+
+/// 在 SSH 主机上用禁止分片的 ping 二分查找路径 MTU (IPv4)
+///
+/// 未指定 `target_host` 时探测回到本机的路径。整个过程最多 30 秒，
+/// 可通过 `discovery::cancel_all` 取消。
+pub async fn probe_path_mtu(
+    config: SshConnectConfig,
+    target_host: Option<String>,
+) -> Result<PathMtu> {
+    let guard = discovery::register();
+    let ssh = tokio::select! {
+        _ = guard.token.cancelled() => return Err(anyhow!("MTU probe cancelled")),
+        ssh = Ssh::init(config) => ssh?,
+    };
+
+    let result = tokio::select! {
+        _ = guard.token.cancelled() => Err(anyhow!("MTU probe cancelled")),
+        result = timeout(PROBE_TIMEOUT, search(&ssh, target_host)) => match result {
+            Ok(result) => result,
+            Err(_) => Err(anyhow!("MTU probe did not finish within {:?}", PROBE_TIMEOUT)),
+        },
+    };
+    ssh.disconnect().await;
+    result
+}
+
+async fn search(ssh: &Ssh, target_host: Option<String>) -> Result<PathMtu> {
+    let host = match target_host.filter(|h| !h.trim().is_empty()) {
+        Some(host) => host.trim().to_string(),
+        None => ssh
+            .exec_cmd(&SshClientAddrCmd, Duration::from_secs(5))
+            .await?
+            .ok_or_else(|| anyhow!("Cannot determine the client address, specify a target host"))?,
+    };
+
+    let mut probes = 0;
+    let mut probe = |mtu: u16| {
+        probes += 1;
+        let cmd = PingDfCmd {
+            host: host.clone(),
+            payload: mtu - HEADER_BYTES,
+        };
+        async move {
+            match ssh
+                .exec_cmd(&cmd, Duration::from_secs(5))
+                .await?
+                .unwrap_or(PingResult::Fail)
+            {
+                PingResult::Unsupported => Err(anyhow!(
+                    "The SSH host's ping does not support -M do (iputils ping is required)"
+                )),
+                result => Ok(result == PingResult::Ok),
+            }
+        }
+    };
+
+    if !probe(MIN_MTU).await? {
+        return Err(anyhow!(
+            "{} does not answer ping from the SSH host, cannot probe the MTU",
+            host
+        ));
+    }
+
+    let (mut low, mut high) = (MIN_MTU, MAX_MTU);
+    if probe(MAX_MTU).await? {
+        low = MAX_MTU;
+    } else {
+        // low 总是可通过，high 总是失败
+        while high - low > 1 {
+            let mid = low + (high - low) / 2;
+            if probe(mid).await? {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+    }
+
+    info!("Path MTU to {} is {} ({} probes)", host, low, probes);
+    Ok(PathMtu {
+        host,
+        mtu: low,
+        probes,
+    })
+}
@@ -0,0 +1,214 @@
+use crate::database::entity::tunnel_config::Model as TunnelModel;
+use crate::server::discovery;
+use crate::server::link_quality::{self, LinkQuality};
+use crate::server::model::{
+    format_host_port, io_buffer_size_from, unbracket_host, SshConnectConfig,
+};
+use crate::server::remote_cmd::RemoteCommand;
+use crate::server::ssh::Ssh;
+use anyhow::{anyhow, Result};
+use log::debug;
+use serde::Serialize;
+use std::net::IpAddr;
+use tokio::time::{sleep, timeout, Duration, Instant};
+
+/// 连接建立后测量部分的时长上限
+const MEASURE_TIMEOUT: Duration = Duration::from_secs(60);
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(200);
+pub const DEFAULT_SAMPLES: u32 = 10;
+const MAX_SAMPLES: u32 = 20;
+/// 吞吐测量从 SSH 主机读取的数据量
+const THROUGHPUT_BYTES: u64 = 8 * 1024 * 1024;
+const THROUGHPUT_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// 经隧道与直连的开销对比，延迟单位为毫秒
+#[derive(Debug, Clone, Serialize)]
+pub struct OverheadReport {
+    /// 隧道配置的转发目标
+    pub target: String,
+    /// 本机直连的 TCP 建连延迟，目标不可直连时为到 SSH 端口的延迟
+    pub direct: LinkQuality,
+    /// 直连实际测量的地址 (host:port)
+    pub direct_endpoint: String,
+    /// 目标不可直连，直连一侧测的是 SSH 端口
+    pub direct_fallback: bool,
+    /// 经 SSH 主机打开到目标的 direct-tcpip 通道的延迟
+    pub tunnel: ChannelLatency,
+    /// 隧道平均延迟减去直连平均延迟，任一侧没有成功样本或直连退回 SSH 端口时为 None
+    pub latency_overhead_ms: Option<f64>,
+    /// SSH 会话的下行吞吐 (字节/秒)，远程不能执行命令时为 None
+    pub tunnel_throughput: Option<f64>,
+    /// 测量吞吐时使用的缓冲区大小 (字节)，与转发连接的 io_buffer_size 相同
+    pub io_buffer_size: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelLatency {
+    pub samples: u32,
+    pub failures: u32,
+    pub min_ms: Option<f64>,
+    pub avg_ms: Option<f64>,
+    pub max_ms: Option<f64>,
+}
+
+/// 输出指定字节数的零 (`head -c`)
+struct ZeroStreamCmd {
+    bytes: u64,
+}
+
+impl RemoteCommand for ZeroStreamCmd {
+    type Output = ();
+
+    fn to_shell_string(&self) -> String {
+        format!("head -c {} /dev/zero", self.bytes)
+    }
+
+    fn requires_sudo(&self) -> bool {
+        false
+    }
+
+    fn parse_output(&self, _output: &str) -> Option<Self::Output> {
+        Some(())
+    }
+}
+
+/// 用隧道的连接配置对比经 SSH 主机和本机直连到目标的延迟，并测量会话吞吐
+///
+/// 只支持 standard 模式的本地转发，不需要隧道正在运行。建立连接后最多测量 60 秒，
+/// 可通过 `discovery::cancel_all` 取消。直连一侧没有可用的服务端，吞吐只测隧道，
+/// 按隧道的 io_buffer_size 读取，用于比较不同缓冲区大小的效果。
+pub async fn measure_overhead(tunnel: &TunnelModel, samples: u32) -> Result<OverheadReport> {
+    if tunnel.mode != "standard" || tunnel.forward_type == "remote" {
+        return Err(anyhow!(
+            "Overhead can only be measured for local forwards in standard mode"
+        ));
+    }
+    let target_host = tunnel
+        .target_host
+        .as_deref()
+        .map(unbracket_host)
+        .ok_or_else(|| anyhow!("Target host is not configured"))?
+        .to_string();
+    let target_port = tunnel
+        .target_port
+        .ok_or_else(|| anyhow!("Target port is not configured"))?;
+    let samples = samples.clamp(1, MAX_SAMPLES);
+    let io_buffer_size = io_buffer_size_from(tunnel);
+    let config = SshConnectConfig::try_from(tunnel)?;
+
+    let guard = discovery::register();
+    let ssh = tokio::select! {
+        _ = guard.token.cancelled() => return Err(anyhow!("Overhead measurement cancelled")),
+        ssh = Ssh::init(config) => ssh?,
+    };
+
+    let measure = async {
+        let (direct, direct_endpoint, direct_fallback) =
+            direct_latency(tunnel, &target_host, target_port, samples).await?;
+        let channel = channel_latency(&ssh, &target_host, target_port, samples).await;
+        let tunnel_throughput = throughput(&ssh, io_buffer_size).await;
+        // 到 SSH 端口的延迟与到目标的不可比
+        let latency_overhead_ms = match (channel.avg_ms, direct.avg_ms, direct_fallback) {
+            (Some(tunnel), Some(direct), false) => Some(tunnel - direct),
+            _ => None,
+        };
+        Ok(OverheadReport {
+            target: format!("{}:{}", target_host, target_port),
+            direct,
+            direct_endpoint,
+            direct_fallback,
+            tunnel: channel,
+            latency_overhead_ms,
+            tunnel_throughput,
+            io_buffer_size,
+        })
+    };
+    let result = tokio::select! {
+        _ = guard.token.cancelled() => Err(anyhow!("Overhead measurement cancelled")),
+        result = timeout(MEASURE_TIMEOUT, measure) => match result {
+            Ok(result) => result,
+            Err(_) => Err(anyhow!(
+                "Overhead measurement did not finish within {:?}",
+                MEASURE_TIMEOUT
+            )),
+        },
+    };
+    ssh.disconnect().await;
+    result
+}
+
+/// 目标在 SSH 主机本机时直连 SSH 主机的同一端口，连不上则退回 SSH 端口
+///
+/// 返回延迟、实际测量的地址和是否退回了 SSH 端口。
+async fn direct_latency(
+    tunnel: &TunnelModel,
+    target_host: &str,
+    target_port: u16,
+    samples: u32,
+) -> Result<(LinkQuality, String, bool)> {
+    let ssh_host = unbracket_host(&tunnel.ssh_host).to_string();
+    let loopback = target_host == "localhost"
+        || target_host
+            .parse::<IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback());
+    let host = if loopback {
+        ssh_host.clone()
+    } else {
+        target_host.to_string()
+    };
+
+    // 先探测一次，不可达时不必等完所有样本超时
+    let reachable = link_quality::measure_link_quality(host.clone(), target_port, 1)
+        .await
+        .is_ok_and(|probe| probe.avg_ms.is_some());
+    if reachable {
+        let endpoint = format_host_port(&host, target_port);
+        let quality = link_quality::measure_link_quality(host, target_port, samples).await?;
+        return Ok((quality, endpoint, false));
+    }
+    let endpoint = format_host_port(&ssh_host, tunnel.ssh_port);
+    debug!(
+        "Target is not reachable directly, measuring against {}",
+        endpoint
+    );
+    let quality = link_quality::measure_link_quality(ssh_host, tunnel.ssh_port, samples).await?;
+    Ok((quality, endpoint, true))
+}
+
+async fn channel_latency(ssh: &Ssh, host: &str, port: u16, samples: u32) -> ChannelLatency {
+    let mut latencies = Vec::with_capacity(samples as usize);
+    for i in 0..samples {
+        if i > 0 {
+            sleep(SAMPLE_INTERVAL).await;
+        }
+        let started = Instant::now();
+        match ssh.probe_target(host, port).await {
+            Ok(()) => latencies.push(started.elapsed().as_secs_f64() * 1000.0),
+            Err(e) => debug!("Overhead probe to {}:{} failed: {:#}", host, port, e),
+        }
+    }
+
+    let avg_ms =
+        (!latencies.is_empty()).then(|| latencies.iter().sum::<f64>() / latencies.len() as f64);
+    ChannelLatency {
+        samples,
+        failures: samples - latencies.len() as u32,
+        min_ms: latencies.iter().copied().reduce(f64::min),
+        avg_ms,
+        max_ms: latencies.iter().copied().reduce(f64::max),
+    }
+}
+
+async fn throughput(ssh: &Ssh, buffer_size: usize) -> Option<f64> {
+    let cmd = ZeroStreamCmd {
+        bytes: THROUGHPUT_BYTES,
+    };
+    match ssh.exec_count(&cmd, buffer_size, THROUGHPUT_TIMEOUT).await {
+        Ok((bytes, elapsed)) if bytes > 0 => Some(bytes as f64 / elapsed.as_secs_f64()),
+        Ok(_) => None,
+        Err(e) => {
+            debug!("Throughput measurement failed: {:#}", e);
+            None
+        }
+    }
+}
@@ -0,0 +1,134 @@
+#[cfg(windows)]
+use crate::server::CREATE_NO_WINDOW;
+use log::debug;
+use serde::Serialize;
+use std::fmt;
+use std::process::Stdio;
+use tokio::process::Command;
+use tokio::time::{timeout, Duration};
+
+/// 占用本地端口的进程
+#[derive(Debug, Clone, Serialize)]
+pub struct PortOwner {
+    pub pid: u32,
+    pub name: String,
+}
+
+impl fmt::Display for PortOwner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (pid {})", self.name, self.pid)
+    }
+}
+
+/// 尽力查找监听该端口的进程，没有权限或工具缺失时返回 None
+pub async fn who_has_port(port: u16) -> Option<PortOwner> {
+    let owner = if cfg!(windows) {
+        lookup_windows(port).await
+    } else {
+        match lookup_lsof(port).await {
+            Some(owner) => Some(owner),
+            None => lookup_ss(port).await,
+        }
+    };
+
+    if owner.is_none() {
+        debug!("Could not determine the owner of port {}", port);
+    }
+    owner
+}
+
+async fn run(program: &str, args: &[&str]) -> Option<String> {
+    let mut cmd = Command::new(program);
+    cmd.args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true);
+    #[cfg(windows)]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+
+    let output = match timeout(Duration::from_secs(3), cmd.output()).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => {
+            debug!("Failed to run {}: {}", program, e);
+            return None;
+        }
+        Err(_) => {
+            debug!("{} timed out", program);
+            return None;
+        }
+    };
+
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// `lsof -F pc` 输出形如 `p1234` / `cdocker` 的字段行
+async fn lookup_lsof(port: u16) -> Option<PortOwner> {
+    let filter = format!("-iTCP:{}", port);
+    let output = run("lsof", &["-nP", &filter, "-sTCP:LISTEN", "-Fpc"]).await?;
+
+    let mut pid = None;
+    for line in output.lines() {
+        if let Some(value) = line.strip_prefix('p') {
+            pid = value.parse().ok();
+        } else if let (Some(value), Some(pid)) = (line.strip_prefix('c'), pid) {
+            return Some(PortOwner {
+                pid,
+                name: value.to_string(),
+            });
+        }
+    }
+    None
+}
+
+/// Linux 上没有 lsof 时使用 ss，进程信息形如 `users:(("docker-proxy",pid=1234,fd=4))`
+async fn lookup_ss(port: u16) -> Option<PortOwner> {
+    let filter = format!("sport = :{}", port);
+    let output = run("ss", &["-ltnpH", &filter]).await?;
+
+    let users = output.lines().find_map(|line| {
+        let start = line.find("users:((\"")? + "users:((\"".len();
+        Some(&line[start..])
+    })?;
+    let (name, rest) = users.split_once('"')?;
+    let pid_start = rest.find("pid=")? + "pid=".len();
+    let pid = rest[pid_start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<String>()
+        .parse()
+        .ok()?;
+
+    Some(PortOwner {
+        pid,
+        name: name.to_string(),
+    })
+}
+
+/// Windows 上先用 netstat 找到 PID，再用 tasklist 查进程名
+async fn lookup_windows(port: u16) -> Option<PortOwner> {
+    let output = run("netstat", &["-ano", "-p", "TCP"]).await?;
+    let suffix = format!(":{}", port);
+
+    let pid: u32 = output.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            [_, local, _, state, pid] if local.ends_with(&suffix) && *state == "LISTENING" => {
+                pid.parse().ok()
+            }
+            _ => None,
+        }
+    })?;
+
+    let filter = format!("PID eq {}", pid);
+    let name = run("tasklist", &["/FI", &filter, "/FO", "CSV", "/NH"])
+        .await
+        .and_then(|output| {
+            let line = output.lines().next()?;
+            let name = line.split(',').next()?.trim_matches('"');
+            (!name.is_empty() && line.contains(&pid.to_string())).then(|| name.to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+
+    Some(PortOwner { pid, name })
+}
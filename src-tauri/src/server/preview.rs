@@ -0,0 +1,292 @@
+use crate::database::entity::tunnel_config::Model as TunnelModel;
+use crate::server::control_master::{self, ControlMaster};
+use crate::server::dns;
+use crate::server::model::{
+    format_host_port, port_map_from, unbracket_host, ForwardDirection, SshConnectConfig,
+    SshForwardConfig,
+};
+use crate::server::remote_cmd::{
+    parse_target, ContainerRuntime, DockerForwardVia, GetContainerAddrCmd, GetPublishedPortCmd,
+    ResolveTargetCmd,
+};
+use crate::server::ssh::Ssh;
+use crate::server::system_ssh::{self, SshBackend};
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use std::net::IpAddr;
+use std::time::Duration;
+
+/// 预览时建立 SSH 连接并解析目标的总时长上限
+const PREVIEW_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 转发目标的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetSource {
+    /// 配置中的 target_host/target_port
+    Configured,
+    /// target_resolver_command 的输出
+    Resolver,
+    ContainerIp,
+    /// 容器发布到 SSH 主机上的端口
+    PublishedPort,
+    /// SOCKS 模式由客户端指定
+    SocksClient,
+}
+
+/// 解析后的转发目标
+#[derive(Debug, Clone)]
+pub struct ResolvedTarget {
+    pub host: String,
+    pub port: u16,
+    pub source: TargetSource,
+}
+
+/// 启动前计算出的实际转发，不绑定本地端口
+#[derive(Debug, Serialize)]
+pub struct ForwardPreview {
+    pub id: String,
+    pub name: String,
+    /// "control_master" | "russh" | "system_ssh"
+    pub backend: &'static str,
+    /// 没有使用默认实现的原因，或 russh 忽略了的 `~/.ssh/config` 代理设置
+    pub backend_reason: Option<String>,
+    /// 实际连接的 SSH 主机，系统 ssh 会展开 `~/.ssh/config` 中的别名
+    pub ssh_host: String,
+    pub ssh_port: u16,
+    /// 本机连接的第一跳的地址，配置了跳板机时为跳板机的地址，经 `~/.ssh/config` 的代理连接时为空
+    pub ssh_addresses: Vec<IpAddr>,
+    /// "local" | "remote" | "dynamic"
+    pub direction: &'static str,
+    /// 本地监听地址，反向转发时为连接转回的本地地址
+    pub local_addr: String,
+    /// 转发目标，反向转发时为 SSH 主机上的监听地址，SOCKS 模式为 None
+    pub target: Option<String>,
+    pub target_source: TargetSource,
+    /// 端口映射展开后的每一条转发
+    pub port_map: Vec<String>,
+}
+
+/// 执行目标解析命令，返回输出中的 host:port
+pub async fn run_target_resolver(ssh: &Ssh, command: String) -> Result<(String, u16)> {
+    let output = ssh
+        .exec_cmd(&ResolveTargetCmd { command }, Duration::from_secs(30))
+        .await
+        .context("Target resolver command failed")?
+        .ok_or(anyhow!("Target resolver command printed nothing"))?;
+    parse_target(&output).context("Invalid output from the target resolver command")
+}
+
+/// docker 模式的转发目标：容器 IP 或容器发布到 SSH 主机上的端口，不等待健康检查
+pub async fn resolve_container_target(ssh: &Ssh, tunnel: &TunnelModel) -> Result<ResolvedTarget> {
+    let container_name = tunnel
+        .container_name
+        .clone()
+        .ok_or(anyhow!("Container name missing"))?;
+    let runtime = ContainerRuntime::parse(tunnel.container_runtime.as_deref())?;
+    let container_port = tunnel.container_port.unwrap_or(80);
+
+    match DockerForwardVia::parse(tunnel.docker_forward_via.as_deref())? {
+        DockerForwardVia::ContainerIp => {
+            let cmd = GetContainerAddrCmd {
+                container_name,
+                runtime,
+            };
+            let ip = ssh
+                .exec_cmd(&cmd, Duration::from_secs(10))
+                .await?
+                .ok_or(anyhow!("Container IP not found"))?;
+            Ok(ResolvedTarget {
+                host: ip,
+                port: container_port,
+                source: TargetSource::ContainerIp,
+            })
+        }
+        DockerForwardVia::PublishedPort => {
+            let cmd = GetPublishedPortCmd {
+                container_name: container_name.clone(),
+                container_port,
+                runtime,
+            };
+            let published = ssh
+                .exec_cmd(&cmd, Duration::from_secs(10))
+                .await?
+                .and_then(|ports| ports.into_iter().next())
+                .ok_or(anyhow!(
+                    "Port {}/tcp of container {} is not published on the host",
+                    container_port,
+                    container_name
+                ))?;
+            Ok(ResolvedTarget {
+                host: published.forward_host(),
+                port: published.host_port,
+                source: TargetSource::PublishedPort,
+            })
+        }
+    }
+}
+
+/// 按启动时的顺序计算实现、SSH 地址和转发目标，返回遇到的第一个错误
+///
+/// 只有 docker 模式和配置了解析命令时才会建立 SSH 连接，用完即断开。
+pub async fn preview(tunnel: &TunnelModel) -> Result<ForwardPreview> {
+    let local_port = tunnel
+        .local_port
+        .ok_or_else(|| anyhow!("Local port is not configured"))?;
+    let direction = match tunnel.forward_type.as_str() {
+        "remote" => ForwardDirection::Remote,
+        "dynamic" => ForwardDirection::Dynamic,
+        _ => ForwardDirection::Local,
+    };
+
+    let (backend, backend_reason) = select_backend(tunnel).await?;
+    if backend == "system_ssh" {
+        if let Some(feature) = system_ssh::unsupported_feature(tunnel) {
+            return Err(anyhow!("{} requires the russh backend", feature));
+        }
+    }
+    let ssh_host = match backend {
+        "system_ssh" => system_ssh::effective_config(tunnel)
+            .await
+            .and_then(|options| options.get("hostname").cloned())
+            .unwrap_or_else(|| tunnel.ssh_host.clone()),
+        _ => tunnel.ssh_host.clone(),
+    };
+    // 经跳板机时 SSH 主机由跳板机解析，这里只能解析跳板机
+    let jump_host = tunnel
+        .jump_host
+        .as_deref()
+        .map(str::trim)
+        .filter(|host| !host.is_empty());
+    // 系统 ssh 经 ~/.ssh/config 的 ProxyJump/ProxyCommand 连接时本机不解析 SSH 主机
+    let proxied = backend == "system_ssh"
+        && jump_host.is_none()
+        && system_ssh::proxy_hop(tunnel).await.is_some();
+    let ssh_addresses = if proxied {
+        Vec::new()
+    } else {
+        dns::resolve_all(jump_host.unwrap_or(&ssh_host)).await?
+    };
+
+    let target = if tunnel.mode == "socks" {
+        None
+    } else if tunnel.mode == "docker" || tunnel.target_resolver_command.is_some() {
+        Some(
+            tokio::time::timeout(PREVIEW_TIMEOUT, resolve_with_session(tunnel))
+                .await
+                .map_err(|_| {
+                    anyhow!("Resolving the target timed out after {:?}", PREVIEW_TIMEOUT)
+                })??,
+        )
+    } else {
+        Some(ResolvedTarget {
+            host: tunnel
+                .target_host
+                .as_deref()
+                .map(unbracket_host)
+                .ok_or_else(|| anyhow!("Target host is not configured"))?
+                .to_string(),
+            port: tunnel
+                .target_port
+                .ok_or_else(|| anyhow!("Target port is not configured"))?,
+            source: TargetSource::Configured,
+        })
+    };
+
+    let port_map = match &target {
+        Some(target) => port_map_from(tunnel)?
+            .iter()
+            .map(|mapping| {
+                format!(
+                    "127.0.0.1:{}-{} -> {}-{}",
+                    mapping.local_start,
+                    mapping.local_end,
+                    format_host_port(&target.host, mapping.remote_base),
+                    mapping.remote_base + (mapping.local_end - mapping.local_start)
+                )
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+
+    Ok(ForwardPreview {
+        id: tunnel.id.clone(),
+        name: tunnel.name.clone(),
+        backend,
+        backend_reason,
+        ssh_host,
+        ssh_port: tunnel.ssh_port,
+        ssh_addresses,
+        direction: match direction {
+            ForwardDirection::Local => "local",
+            ForwardDirection::Remote => "remote",
+            ForwardDirection::Dynamic => "dynamic",
+        },
+        local_addr: format_host_port("127.0.0.1", local_port),
+        target: target
+            .as_ref()
+            .map(|target| format_host_port(&target.host, target.port)),
+        target_source: target
+            .map(|target| target.source)
+            .unwrap_or(TargetSource::SocksClient),
+        port_map,
+    })
+}
+
+/// 与 actor 启动时的选择一致：可用的控制连接优先，其次按 backend 配置
+async fn select_backend(tunnel: &TunnelModel) -> Result<(&'static str, Option<String>)> {
+    if let Some(control_path) = tunnel
+        .control_path
+        .as_deref()
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+    {
+        let usable = tunnel.mode == "standard"
+            && SshForwardConfig::try_from(tunnel).is_ok_and(|forward_config| {
+                control_master::unsupported_feature(tunnel, &forward_config).is_none()
+            });
+        let control = ControlMaster::new(
+            control_path,
+            &tunnel.ssh_username,
+            &tunnel.ssh_host,
+            tunnel.ssh_port,
+        );
+        if usable && control.is_alive().await {
+            return Ok((
+                "control_master",
+                Some(format!("Control socket {} is alive", control_path)),
+            ));
+        }
+    }
+
+    match SshBackend::parse(tunnel.backend.as_deref())? {
+        // russh 忽略 ~/.ssh/config 中的代理，提示用户需要时改选系统 ssh
+        SshBackend::Auto | SshBackend::Russh => Ok((
+            "russh",
+            system_ssh::proxy_hop(tunnel).await.map(|option| {
+                format!(
+                    "~/.ssh/config sets '{}' for this host, which russh ignores; choose the system ssh backend to use it",
+                    option
+                )
+            }),
+        )),
+        SshBackend::SystemSsh => Ok(("system_ssh", Some("Configured".to_string()))),
+    }
+}
+
+async fn resolve_with_session(tunnel: &TunnelModel) -> Result<ResolvedTarget> {
+    let ssh = Ssh::init(SshConnectConfig::try_from(tunnel)?).await?;
+    let result =
+        match tunnel.target_resolver_command.clone() {
+            Some(command) if tunnel.mode != "docker" => run_target_resolver(&ssh, command)
+                .await
+                .map(|(host, port)| ResolvedTarget {
+                    host,
+                    port,
+                    source: TargetSource::Resolver,
+                }),
+            _ => resolve_container_target(&ssh, tunnel).await,
+        };
+    ssh.disconnect().await;
+    result
+}
@@ -0,0 +1,86 @@
+use anyhow::{anyhow, Result};
+use std::net::SocketAddr;
+
+const V2_SIGNATURE: [u8; 12] = [
+    0x0D, 0x0A, 0x0D, 0x0A, 0x00, 0x0D, 0x0A, 0x51, 0x55, 0x49, 0x54, 0x0A,
+];
+
+/// 转发连接时在数据前写入的 PROXY protocol 头版本
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProxyProtocolVersion {
+    V1,
+    V2,
+}
+
+impl ProxyProtocolVersion {
+    /// 解析配置值，未配置时返回 None (不注入)
+    pub fn parse(value: Option<&str>) -> Result<Option<Self>> {
+        match value.map(str::trim).filter(|v| !v.is_empty()) {
+            None => Ok(None),
+            Some("v1") => Ok(Some(Self::V1)),
+            Some("v2") => Ok(Some(Self::V2)),
+            Some(other) => Err(anyhow!("Unsupported PROXY protocol version: {}", other)),
+        }
+    }
+
+    /// 生成连接头，`src` 为本地接受的客户端地址，`dst` 为本地监听地址
+    pub fn header(&self, src: SocketAddr, dst: SocketAddr) -> Vec<u8> {
+        match self {
+            Self::V1 => Self::v1_header(src, dst),
+            Self::V2 => Self::v2_header(src, dst),
+        }
+    }
+
+    fn v1_header(src: SocketAddr, dst: SocketAddr) -> Vec<u8> {
+        let line = match (src, dst) {
+            (SocketAddr::V4(s), SocketAddr::V4(d)) => format!(
+                "PROXY TCP4 {} {} {} {}\r\n",
+                s.ip(),
+                d.ip(),
+                s.port(),
+                d.port()
+            ),
+            (SocketAddr::V6(s), SocketAddr::V6(d)) => format!(
+                "PROXY TCP6 {} {} {} {}\r\n",
+                s.ip(),
+                d.ip(),
+                s.port(),
+                d.port()
+            ),
+            _ => "PROXY UNKNOWN\r\n".to_string(),
+        };
+        line.into_bytes()
+    }
+
+    fn v2_header(src: SocketAddr, dst: SocketAddr) -> Vec<u8> {
+        let mut header = V2_SIGNATURE.to_vec();
+        // 版本 2 + PROXY 命令
+        header.push(0x21);
+
+        match (src, dst) {
+            (SocketAddr::V4(s), SocketAddr::V4(d)) => {
+                header.push(0x11); // TCP over IPv4
+                header.extend_from_slice(&12u16.to_be_bytes());
+                header.extend_from_slice(&s.ip().octets());
+                header.extend_from_slice(&d.ip().octets());
+                header.extend_from_slice(&s.port().to_be_bytes());
+                header.extend_from_slice(&d.port().to_be_bytes());
+            }
+            (SocketAddr::V6(s), SocketAddr::V6(d)) => {
+                header.push(0x21); // TCP over IPv6
+                header.extend_from_slice(&36u16.to_be_bytes());
+                header.extend_from_slice(&s.ip().octets());
+                header.extend_from_slice(&d.ip().octets());
+                header.extend_from_slice(&s.port().to_be_bytes());
+                header.extend_from_slice(&d.port().to_be_bytes());
+            }
+            _ => {
+                // 地址族不一致时使用 UNSPEC，接收方忽略地址信息
+                header.push(0x00);
+                header.extend_from_slice(&0u16.to_be_bytes());
+            }
+        }
+
+        header
+    }
+}
@@ -1,10 +1,14 @@
 use crate::commands::docker::DockerContainer;
+use crate::error::AppError;
+use crate::server::discovery;
 use crate::server::model::SshConnectConfig;
-use crate::server::ssh::Ssh;
-use anyhow::Result;
+use crate::server::ssh::{ExecOutput, Ssh};
+use anyhow::{anyhow, Context, Result};
 use log::info;
+use serde::Serialize;
 use shell_escape::escape;
 use std::borrow::Cow;
+use std::net::IpAddr;
 use std::time::Duration;
 
 pub trait RemoteCommand {
@@ -12,18 +16,154 @@ pub trait RemoteCommand {
 
     fn to_shell_string(&self) -> String;
 
-    fn build_shell_string(&self, use_sudo: bool) -> String {
+    /// 是否通过 `sudo -n` 执行
+    fn requires_sudo(&self) -> bool {
+        true
+    }
+
+    /// 是否依赖管道、命令替换等 shell 特性
+    fn needs_shell(&self) -> bool {
+        false
+    }
+
+    fn build_shell_string(&self, shell: &RemoteShell) -> Result<String> {
         let shell_string = self.to_shell_string();
-        if use_sudo {
+        let with_sudo = if self.requires_sudo() {
             format!("sudo -n {}", shell_string)
         } else {
-            shell_string
+            shell_string.clone()
+        };
+
+        match shell {
+            RemoteShell::Login => Ok(with_sudo),
+            RemoteShell::Wrap(prefix) => Ok(format!("{} {}", prefix, escape(Cow::from(with_sudo)))),
+            RemoteShell::Direct => {
+                if self.needs_shell() {
+                    return Err(anyhow!(
+                        "This command needs a POSIX shell, set remote_shell to 'sh -c' for this host"
+                    ));
+                }
+                Ok(shell_string)
+            }
         }
     }
 
     fn parse_output(&self, output: &str) -> Option<Self::Output>;
 }
 
+/// 远程命令的执行方式
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum RemoteShell {
+    /// 直接交给登录 shell，要求兼容 POSIX
+    #[default]
+    Login,
+    /// 用指定的 shell 包裹，如 `sh -c`
+    Wrap(String),
+    /// 不依赖 shell 特性，也不使用 sudo，适用于受限 shell
+    Direct,
+}
+
+impl RemoteShell {
+    /// 解析配置：未配置为登录 shell，`none` 为直接执行，其它值作为包裹命令
+    pub fn parse(value: Option<&str>) -> Result<Self> {
+        let value = match value.map(str::trim).filter(|v| !v.is_empty()) {
+            None | Some("default") => return Ok(Self::Login),
+            Some("none") => return Ok(Self::Direct),
+            Some(value) => value,
+        };
+
+        if value
+            .chars()
+            .any(|c| matches!(c, '\'' | '"' | ';' | '|' | '&' | '`' | '$' | '\n'))
+        {
+            return Err(anyhow!("Invalid remote shell: {}", value));
+        }
+        // `sh` 与 `sh -c` 等价
+        if value.ends_with(" -c") {
+            Ok(Self::Wrap(value.to_string()))
+        } else {
+            Ok(Self::Wrap(format!("{} -c", value)))
+        }
+    }
+}
+
+/// 根据失败输出判断是否受限 shell 或 sudo 问题，返回给用户的提示
+pub fn shell_failure_hint(exit_status: u32, stderr: &str) -> Option<&'static str> {
+    let stderr = stderr.to_lowercase();
+    if stderr.contains("restricted") {
+        Some("the remote account uses a restricted shell, set remote_shell to 'none' for this host")
+    } else if stderr.contains("sudo: a password is required")
+        || stderr.contains("sudo: a terminal is required")
+    {
+        Some("sudo needs a password on the remote host, allow passwordless sudo or set remote_shell to 'none' to run without sudo")
+    } else if stderr.contains("sudo: command not found") || stderr.contains("sudo: not found") {
+        Some("sudo is not installed on the remote host, set remote_shell to 'none' to run without it")
+    } else if exit_status == 127
+        || stderr.contains("unknown command")
+        || stderr.contains("invalid command")
+        || stderr.contains("syntax error")
+    {
+        Some("the remote shell could not run the command, try setting remote_shell to 'sh -c' or 'none'")
+    } else {
+        None
+    }
+}
+
+/// 远程主机上的容器运行时
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ContainerRuntime {
+    #[default]
+    Docker,
+    Podman,
+    /// 优先 docker，不存在时使用 podman
+    Auto,
+}
+
+impl ContainerRuntime {
+    /// 解析配置中的运行时名称，未配置时默认为 docker
+    pub fn parse(value: Option<&str>) -> Result<Self> {
+        match value.map(str::trim).filter(|v| !v.is_empty()) {
+            None | Some("docker") => Ok(Self::Docker),
+            Some("podman") => Ok(Self::Podman),
+            Some("auto") => Ok(Self::Auto),
+            Some(other) => Err(anyhow!("Unsupported container runtime: {}", other)),
+        }
+    }
+
+    /// 命令行中调用运行时的方式
+    fn binary(&self) -> &'static str {
+        match self {
+            Self::Docker => "docker",
+            Self::Podman => "podman",
+            Self::Auto => "$(command -v docker || command -v podman || echo docker)",
+        }
+    }
+}
+
+/// docker 模式下转发的目标
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DockerForwardVia {
+    /// 容器在 bridge 网络中的 IP，要求 SSH 主机能访问容器网络
+    #[default]
+    ContainerIp,
+    /// 容器发布在宿主机上的端口
+    PublishedPort,
+}
+
+impl DockerForwardVia {
+    pub fn parse(value: Option<&str>) -> Result<Self> {
+        match value.map(str::trim).filter(|v| !v.is_empty()) {
+            None | Some("container_ip") => Ok(Self::ContainerIp),
+            Some("published_port") => Ok(Self::PublishedPort),
+            Some(other) => Err(anyhow!(
+                "Invalid docker forward mode '{}', expected container_ip or published_port",
+                other
+            )),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct ContainerInfo {
     pub id: String,
@@ -47,18 +187,22 @@ impl From<&ContainerInfo> for DockerContainer {
 
 pub struct GetContainerInfoCmd {
     pub keyword: Option<String>,
+    pub runtime: ContainerRuntime,
 }
 
 impl RemoteCommand for GetContainerInfoCmd {
     type Output = Vec<ContainerInfo>;
 
     fn to_shell_string(&self) -> String {
-        if let Some(keyword) = &self.keyword {
-            let keyword = Cow::from(keyword);
-            format!("docker ps --format '{{{{.ID}}}}|{{{{.Image}}}}|{{{{.Names}}}}|{{{{.Ports}}}}|{{{{.Status}}}}' | grep {}", escape(keyword))
-        } else {
-            "docker ps --format '{{.ID}}|{{.Image}}|{{.Names}}|{{.Ports}}|{{.Status}}'".to_string()
-        }
+        // 关键字在本地过滤，命令不需要管道
+        format!(
+            "{} ps --format '{{{{.ID}}}}|{{{{.Image}}}}|{{{{.Names}}}}|{{{{.Ports}}}}|{{{{.Status}}}}'",
+            self.runtime.binary()
+        )
+    }
+
+    fn needs_shell(&self) -> bool {
+        self.runtime == ContainerRuntime::Auto
     }
 
     fn parse_output(&self, output: &str) -> Option<Self::Output> {
@@ -69,6 +213,11 @@ impl RemoteCommand for GetContainerInfoCmd {
                 info!("Empty line: {}", line);
                 continue;
             }
+            if let Some(keyword) = &self.keyword {
+                if !line.contains(keyword.as_str()) {
+                    continue;
+                }
+            }
 
             let parts = line.split('|').collect::<Vec<&str>>();
             if parts.len() < 5 {
@@ -97,9 +246,113 @@ impl RemoteCommand for GetContainerInfoCmd {
     }
 }
 
+/// `docker ps` 中的一个端口，未发布到宿主机时 ip 和 host_port 为 None
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ContainerPort {
+    pub ip: Option<String>,
+    pub host_port: Option<u16>,
+    pub container_port: u16,
+    /// "tcp" | "udp" | "sctp"
+    pub protocol: String,
+}
+
+/// 单个端口范围展开的上限，避免超大范围撑爆下拉列表
+const MAX_PORT_RANGE: u32 = 1024;
+
+/// 解析 docker ps 的 Ports 列，如 `0.0.0.0:8000-8001->80-81/tcp, [::]:53->53/udp, 443/tcp`
+///
+/// 范围按位展开；IPv4 和 IPv6 各发布一次的同一端口只保留第一条。
+pub fn parse_container_ports(ports: &[String]) -> Vec<ContainerPort> {
+    let mut parsed: Vec<ContainerPort> = Vec::new();
+    for entry in ports.iter().flat_map(|p| p.split(',')) {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            continue;
+        }
+        let (mapping, protocol) = entry.rsplit_once('/').unwrap_or((entry, "tcp"));
+        let (published, container) = match mapping.split_once("->") {
+            Some((published, container)) => (Some(published), container),
+            None => (None, mapping),
+        };
+        let Some(container_ports) = parse_port_range(container) else {
+            info!("Skipping unrecognized container port: {}", entry);
+            continue;
+        };
+
+        let (ip, host_ports) = match published {
+            None => (None, None),
+            Some(published) => {
+                // `:::8080` 是 docker 对 IPv6 任意地址的写法
+                let Some((ip, host_ports)) = published.rsplit_once(':') else {
+                    info!("Skipping unrecognized container port: {}", entry);
+                    continue;
+                };
+                let ip = ip.trim_start_matches('[').trim_end_matches(']');
+                match parse_port_range(host_ports) {
+                    Some(host_ports) if host_ports.len() == container_ports.len() => {
+                        (Some(ip.to_string()), Some(host_ports))
+                    }
+                    _ => {
+                        info!("Skipping unrecognized container port: {}", entry);
+                        continue;
+                    }
+                }
+            }
+        };
+
+        for (i, container_port) in container_ports.iter().enumerate() {
+            let port = ContainerPort {
+                ip: ip.clone(),
+                host_port: host_ports.as_ref().map(|ports| ports[i]),
+                container_port: *container_port,
+                protocol: protocol.to_ascii_lowercase(),
+            };
+            let duplicate = parsed.iter().any(|p| {
+                p.host_port == port.host_port
+                    && p.container_port == port.container_port
+                    && p.protocol == port.protocol
+            });
+            if !duplicate {
+                parsed.push(port);
+            }
+        }
+    }
+    parsed
+}
+
+/// `80` 或 `8000-8002`
+fn parse_port_range(value: &str) -> Option<Vec<u16>> {
+    let (start, end) = match value.split_once('-') {
+        Some((start, end)) => (start.trim().parse::<u16>().ok()?, end.trim().parse().ok()?),
+        None => {
+            let port = value.trim().parse().ok()?;
+            (port, port)
+        }
+    };
+    if end < start || (end - start) as u32 >= MAX_PORT_RANGE {
+        return None;
+    }
+    Some((start..=end).collect())
+}
+
+/// 按 id (可为前缀) 或名称查找容器，返回解析后的端口
+pub async fn get_container_ports(
+    ssh_connect_config: &SshConnectConfig,
+    container: &str,
+    runtime: ContainerRuntime,
+) -> Result<Vec<ContainerPort>> {
+    let containers = get_container_infos(ssh_connect_config, None, runtime).await?;
+    let info = containers
+        .iter()
+        .find(|c| c.name == container || (!container.is_empty() && c.id.starts_with(container)))
+        .ok_or_else(|| AppError::not_found(format!("Container {} is not running", container)))?;
+    Ok(parse_container_ports(&info.ports))
+}
+
 #[allow(dead_code)]
 pub struct GetContainerAddrCmd {
     pub container_name: String,
+    pub runtime: ContainerRuntime,
 }
 
 impl RemoteCommand for GetContainerAddrCmd {
@@ -107,35 +360,642 @@ impl RemoteCommand for GetContainerAddrCmd {
 
     fn to_shell_string(&self) -> String {
         let container_name = Cow::from(&self.container_name);
+        // docker 与 podman 的 inspect 模板一致，每个网络输出 IPv4 和全局 IPv6 地址
         format!(
-            "docker inspect -f '{{{{range .NetworkSettings.Networks}}}}{{{{.IPAddress}}}}{{{{end}}}}' {}",
+            "{} inspect -f '{{{{range .NetworkSettings.Networks}}}}{{{{.IPAddress}}}} {{{{.GlobalIPv6Address}}}} {{{{end}}}}' {}",
+            self.runtime.binary(),
             escape(container_name)
         )
     }
 
+    fn needs_shell(&self) -> bool {
+        self.runtime == ContainerRuntime::Auto
+    }
+
+    /// 优先使用 IPv4 地址，只有 IPv6 网络的容器使用 IPv6 地址
+    fn parse_output(&self, output: &str) -> Option<Self::Output> {
+        let addrs: Vec<IpAddr> = output
+            .split_whitespace()
+            .filter_map(|addr| addr.parse().ok())
+            .collect();
+        addrs
+            .iter()
+            .find(|addr| addr.is_ipv4())
+            .or_else(|| addrs.first())
+            .map(|addr| addr.to_string())
+    }
+}
+
+/// 容器端口在宿主机上的发布地址，`docker port <name> <port>/tcp`
+pub struct GetPublishedPortCmd {
+    pub container_name: String,
+    pub container_port: u16,
+    pub runtime: ContainerRuntime,
+}
+
+impl RemoteCommand for GetPublishedPortCmd {
+    type Output = Vec<PublishedPort>;
+
+    fn to_shell_string(&self) -> String {
+        let container_name = Cow::from(&self.container_name);
+        // 端口未发布时 docker port 以非零状态退出，视为空列表
+        format!(
+            "{} port {} {}/tcp 2>/dev/null || true",
+            self.runtime.binary(),
+            escape(container_name),
+            self.container_port
+        )
+    }
+
+    fn needs_shell(&self) -> bool {
+        true
+    }
+
     fn parse_output(&self, output: &str) -> Option<Self::Output> {
-        let ip = output.trim().to_string();
+        Some(
+            output
+                .lines()
+                .filter_map(|line| split_listen_addr(line.trim()))
+                .map(|(host_ip, host_port)| PublishedPort { host_ip, host_port })
+                .collect(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PublishedPort {
+    pub host_ip: String,
+    pub host_port: u16,
+}
 
-        if ip.is_empty() {
-            return None;
+impl PublishedPort {
+    /// 从 SSH 主机上访问该端口的地址，监听所有地址时使用回环地址
+    pub fn forward_host(&self) -> String {
+        match self.host_ip.as_str() {
+            "" | "0.0.0.0" | "::" => "127.0.0.1".to_string(),
+            ip => ip.to_string(),
         }
-        Some(ip)
+    }
+}
+
+/// 容器端口是否发布到宿主机，以及建议的转发方式
+#[derive(Debug, Serialize)]
+pub struct ContainerPortStatus {
+    pub container_port: u16,
+    pub published: Vec<PublishedPort>,
+    pub recommended: DockerForwardVia,
+}
+
+pub async fn check_container_port(
+    ssh_connect_config: &SshConnectConfig,
+    container_name: String,
+    container_port: u16,
+    runtime: ContainerRuntime,
+) -> Result<ContainerPortStatus> {
+    let command = GetPublishedPortCmd {
+        container_name,
+        container_port,
+        runtime,
+    };
+    let published = run_discovery(ssh_connect_config, &command, Duration::from_secs(10))
+        .await
+        .context("Failed to inspect the container port")?
+        .unwrap_or_default();
+    let recommended = if published.is_empty() {
+        DockerForwardVia::ContainerIp
+    } else {
+        DockerForwardVia::PublishedPort
+    };
+    Ok(ContainerPortStatus {
+        container_port,
+        published,
+        recommended,
+    })
+}
+
+/// 远程主机上正在监听的 TCP 端口
+#[derive(Debug, Clone, Serialize)]
+pub struct ListeningPort {
+    pub address: String,
+    pub port: u16,
+    /// 权限不足时 ss/netstat 不显示进程信息
+    pub process: Option<String>,
+    pub pid: Option<u32>,
+}
+
+/// 优先使用 ss，不存在时退回 netstat
+pub struct GetListeningPortsCmd;
+
+impl RemoteCommand for GetListeningPortsCmd {
+    type Output = Vec<ListeningPort>;
+
+    fn to_shell_string(&self) -> String {
+        "if command -v ss >/dev/null 2>&1; then ss -tlnp; else netstat -tlnp; fi".to_string()
+    }
+
+    // 不用 sudo，只显示当前用户有权限看到的进程
+    fn requires_sudo(&self) -> bool {
+        false
+    }
+
+    fn needs_shell(&self) -> bool {
+        true
+    }
+
+    fn parse_output(&self, output: &str) -> Option<Self::Output> {
+        let mut ports: Vec<ListeningPort> = output
+            .lines()
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                match fields.as_slice() {
+                    // ss: State Recv-Q Send-Q Local Peer [Process]
+                    ["LISTEN", _, _, local, _, rest @ ..] => {
+                        let (address, port) = split_listen_addr(local)?;
+                        let (process, pid) = rest
+                            .first()
+                            .and_then(|users| parse_ss_process(users))
+                            .unzip();
+                        Some(ListeningPort {
+                            address,
+                            port,
+                            process,
+                            pid,
+                        })
+                    }
+                    // netstat: Proto Recv-Q Send-Q Local Foreign State [PID/Program]
+                    [proto, _, _, local, _, "LISTEN", rest @ ..] if proto.starts_with("tcp") => {
+                        let (address, port) = split_listen_addr(local)?;
+                        let (pid, process) = rest
+                            .first()
+                            .and_then(|owner| owner.split_once('/'))
+                            .map(|(pid, name)| (pid.parse().ok(), Some(name.to_string())))
+                            .unwrap_or((None, None));
+                        Some(ListeningPort {
+                            address,
+                            port,
+                            process,
+                            pid,
+                        })
+                    }
+                    _ => None,
+                }
+            })
+            .collect();
+
+        ports.sort_by(|a, b| a.port.cmp(&b.port).then_with(|| a.address.cmp(&b.address)));
+        ports.dedup_by(|a, b| a.port == b.port && a.address == b.address);
+        Some(ports)
+    }
+}
+
+/// `0.0.0.0:22`、`[::]:22`、`*:80`、`127.0.0.53%lo:53`
+fn split_listen_addr(local: &str) -> Option<(String, u16)> {
+    let (host, port) = local.rsplit_once(':')?;
+    let port = port.parse().ok()?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let host = host.split('%').next().unwrap_or(host);
+    Some((host.to_string(), port))
+}
+
+/// `users:(("sshd",pid=812,fd=3))`
+fn parse_ss_process(users: &str) -> Option<(String, u32)> {
+    let start = users.find("((\"")? + "((\"".len();
+    let (name, rest) = users[start..].split_once('"')?;
+    let pid_start = rest.find("pid=")? + "pid=".len();
+    let pid = rest[pid_start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<String>()
+        .parse()
+        .ok()?;
+    Some((name.to_string(), pid))
+}
+
+/// 从 SSH 主机检查目标是否可达
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetReachability {
+    pub reachable: bool,
+    /// 使用的检测方式：`nc` 或 `bash`
+    pub method: String,
+}
+
+/// 优先 `nc -z`，没有 nc 时使用 bash 的 `/dev/tcp`
+pub struct GetTargetReachableCmd {
+    pub host: String,
+    pub port: u16,
+}
+
+impl RemoteCommand for GetTargetReachableCmd {
+    type Output = TargetReachability;
+
+    fn to_shell_string(&self) -> String {
+        let host = escape(Cow::from(&self.host));
+        let port = self.port;
+        format!(
+            "if command -v nc >/dev/null 2>&1; then \
+               if nc -z -w 5 {host} {port} >/dev/null 2>&1; then echo open nc; else echo closed nc; fi; \
+             elif command -v bash >/dev/null 2>&1; then \
+               T=; command -v timeout >/dev/null 2>&1 && T='timeout 5'; \
+               if $T bash -c 'exec 3<>/dev/tcp/\"$1\"/\"$2\"' check {host} {port} 2>/dev/null; \
+               then echo open bash; else echo closed bash; fi; \
+             else echo unsupported; fi"
+        )
+    }
+
+    fn requires_sudo(&self) -> bool {
+        false
+    }
+
+    fn needs_shell(&self) -> bool {
+        true
+    }
+
+    fn parse_output(&self, output: &str) -> Option<Self::Output> {
+        let line = output
+            .lines()
+            .map(str::trim)
+            .rfind(|line| !line.is_empty())?;
+        let (state, method) = line.split_once(' ')?;
+        let reachable = match state {
+            "open" => true,
+            "closed" => false,
+            _ => return None,
+        };
+        Some(TargetReachability {
+            reachable,
+            method: method.to_string(),
+        })
+    }
+}
+
+/// 在缓存的会话上执行一条发现命令，可被 `discovery::cancel_all` 中途取消
+///
+/// 会话在短时间内被后续发现命令复用，执行失败时丢弃。
+async fn run_discovery<C: RemoteCommand>(
+    ssh_connect_config: &SshConnectConfig,
+    command: &C,
+    timeout: Duration,
+) -> Result<Option<C::Output>> {
+    let guard = discovery::register();
+
+    let ssh_instance = tokio::select! {
+        _ = guard.token.cancelled() => return Err(anyhow!("Discovery cancelled")),
+        ssh = discovery::warm_session(ssh_connect_config) => ssh?,
+    };
+    let result = tokio::select! {
+        _ = guard.token.cancelled() => Err(anyhow!("Discovery cancelled")),
+        result = ssh_instance.exec_cmd(command, timeout) => result,
+    };
+    if result.is_err() {
+        discovery::invalidate_session(ssh_connect_config).await;
+    }
+    result
+}
+
+pub async fn check_target_reachable(
+    ssh_connect_config: &SshConnectConfig,
+    host: String,
+    port: u16,
+) -> Result<TargetReachability> {
+    let command = GetTargetReachableCmd { host, port };
+    let result = run_discovery(ssh_connect_config, &command, Duration::from_secs(15))
+        .await
+        .context("Failed to check the target, nc or bash must be available on the SSH host")?;
+    result.ok_or_else(|| anyhow!("Reachability check returned no result"))
+}
+
+/// 诊断时允许执行的只读命令，整行精确匹配
+pub struct DiagnosticCmd {
+    pub command: String,
+}
+
+impl RemoteCommand for DiagnosticCmd {
+    type Output = ();
+
+    fn to_shell_string(&self) -> String {
+        self.command.clone()
+    }
+
+    fn requires_sudo(&self) -> bool {
+        false
+    }
+
+    fn parse_output(&self, _output: &str) -> Option<Self::Output> {
+        Some(())
+    }
+}
+
+/// 多个空白视为一个，用于和白名单比较
+fn normalize_command(command: &str) -> String {
+    command.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// 在 SSH 主机上执行白名单中的一条命令，返回 stdout、stderr 和退出码
+///
+/// 命令必须与白名单中的某一行完全一致 (忽略多余空白)，不接受参数替换或拼接。
+pub async fn run_diagnostic_command(
+    config: SshConnectConfig,
+    command: &str,
+    allow_list: &[String],
+) -> Result<ExecOutput> {
+    let command = normalize_command(command);
+    if command.is_empty() {
+        return Err(AppError::validation("Command is empty").into());
+    }
+    if !allow_list
+        .iter()
+        .any(|allowed| normalize_command(allowed) == command)
+    {
+        return Err(AppError::validation(format!(
+            "Command is not in the diagnostic allow-list: {}",
+            command
+        ))
+        .into());
+    }
+
+    info!(
+        "Running diagnostic command on {}:{}: {}",
+        config.ssh_host, config.ssh_port, command
+    );
+    let ssh = Ssh::init(config).await?;
+    let result = ssh
+        .exec_raw(&DiagnosticCmd { command }, Duration::from_secs(30))
+        .await;
+    ssh.disconnect().await;
+    result
+}
+
+/// 用户配置的目标解析命令，输出最后一行为 `host:port`
+pub struct ResolveTargetCmd {
+    pub command: String,
+}
+
+impl RemoteCommand for ResolveTargetCmd {
+    type Output = String;
+
+    fn to_shell_string(&self) -> String {
+        self.command.clone()
+    }
+
+    fn requires_sudo(&self) -> bool {
+        false
+    }
+
+    fn needs_shell(&self) -> bool {
+        true
+    }
+
+    fn parse_output(&self, output: &str) -> Option<Self::Output> {
+        output
+            .lines()
+            .map(str::trim)
+            .rfind(|line| !line.is_empty())
+            .map(str::to_string)
+    }
+}
+
+/// 解析 `host:port`，IPv6 需用方括号包裹
+pub fn parse_target(value: &str) -> Result<(String, u16)> {
+    let invalid = || anyhow!("Expected host:port, got '{}'", value);
+    let (host, port) = match value.strip_prefix('[') {
+        Some(rest) => {
+            let (host, port) = rest.split_once("]:").ok_or_else(invalid)?;
+            host.parse::<std::net::Ipv6Addr>().map_err(|_| invalid())?;
+            (host, port)
+        }
+        None => {
+            let (host, port) = value.rsplit_once(':').ok_or_else(invalid)?;
+            let valid_host = !host.is_empty()
+                && host
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'));
+            if !valid_host {
+                return Err(invalid());
+            }
+            (host, port)
+        }
+    };
+    let port = match port.parse::<u16>() {
+        Ok(port) if port > 0 => port,
+        _ => return Err(invalid()),
+    };
+    Ok((host.to_string(), port))
+}
+
+/// 超过该偏差 (秒) 时提示用户，SSH 证书和 TLS 可能因此失败
+pub const TIME_SKEW_WARN_SECS: i64 = 30;
+
+/// 远程时钟相对本地的偏差
+#[derive(Debug, Serialize)]
+pub struct TimeSkew {
+    /// 远程时间减去本地时间，正数表示远程时钟较快
+    pub offset_secs: i64,
+    /// 执行命令的往返耗时，偏差的误差约为其一半
+    pub round_trip_ms: u64,
+    pub exceeds_threshold: bool,
+}
+
+/// 远程 Unix 时间戳 (秒)
+pub struct RemoteDateCmd;
+
+impl RemoteCommand for RemoteDateCmd {
+    type Output = i64;
+
+    fn to_shell_string(&self) -> String {
+        "date +%s".to_string()
+    }
+
+    fn requires_sudo(&self) -> bool {
+        false
+    }
+
+    fn parse_output(&self, output: &str) -> Option<Self::Output> {
+        output.trim().parse().ok()
+    }
+}
+
+/// 比较 SSH 主机与本地的时钟，以命令往返的中点作为本地参考时间
+pub async fn check_time_skew(ssh_connect_config: &SshConnectConfig) -> Result<TimeSkew> {
+    // 先建立会话，避免连接耗时计入往返时间
+    discovery::warm_session(ssh_connect_config).await?;
+
+    let started = std::time::SystemTime::now();
+    let remote = run_discovery(ssh_connect_config, &RemoteDateCmd, Duration::from_secs(10))
+        .await?
+        .ok_or_else(|| anyhow!("Unexpected output from date on the SSH host"))?;
+    let round_trip = started.elapsed().unwrap_or_default();
+
+    let local_midpoint = started + round_trip / 2;
+    let local = local_midpoint
+        .duration_since(std::time::UNIX_EPOCH)
+        .context("Local clock is before the Unix epoch")?
+        .as_secs_f64();
+    let offset_secs = (remote as f64 - local).round() as i64;
+
+    Ok(TimeSkew {
+        offset_secs,
+        round_trip_ms: round_trip.as_millis() as u64,
+        exceeds_threshold: offset_secs.abs() > TIME_SKEW_WARN_SECS,
+    })
+}
+
+/// 规范化 MAC 地址为 `aa:bb:cc:dd:ee:ff`，支持 `:` 或 `-` 分隔
+pub fn normalize_mac(mac: &str) -> Result<String> {
+    let parts: Vec<&str> = mac.trim().split([':', '-']).collect();
+    let valid = parts.len() == 6
+        && parts
+            .iter()
+            .all(|p| p.len() == 2 && p.chars().all(|c| c.is_ascii_hexdigit()));
+    if !valid {
+        return Err(anyhow!("Invalid MAC address: {}", mac));
+    }
+    Ok(parts.join(":").to_lowercase())
+}
+
+/// 从 SSH 主机发送 WoL 魔术包，依次尝试 wakeonlan、etherwake 和 python3
+pub struct WakeOnLanCmd {
+    pub mac: String,
+}
+
+// 广播 6 个 0xff 加 16 次 MAC 到 UDP 9 端口
+const WOL_PYTHON: &str = "import socket,sys;\
+m=bytes.fromhex(sys.argv[1].replace(':',''));\
+s=socket.socket(socket.AF_INET,socket.SOCK_DGRAM);\
+s.setsockopt(socket.SOL_SOCKET,socket.SO_BROADCAST,1);\
+s.sendto(b'\\xff'*6+m*16,('255.255.255.255',9))";
+
+impl RemoteCommand for WakeOnLanCmd {
+    /// 使用的工具，None 表示远程没有可用的工具
+    type Output = Option<String>;
+
+    fn to_shell_string(&self) -> String {
+        let mac = escape(Cow::from(&self.mac));
+        format!(
+            "if command -v wakeonlan >/dev/null 2>&1; then wakeonlan {mac} >/dev/null && echo sent wakeonlan; \
+             elif command -v etherwake >/dev/null 2>&1; then \
+               (etherwake {mac} 2>/dev/null || sudo -n etherwake {mac}) && echo sent etherwake; \
+             elif command -v python3 >/dev/null 2>&1; then python3 -c {script} {mac} && echo sent python3; \
+             else echo unsupported; fi",
+            script = escape(Cow::from(WOL_PYTHON))
+        )
+    }
+
+    // etherwake 需要 root 时单独尝试 sudo
+    fn requires_sudo(&self) -> bool {
+        false
+    }
+
+    fn needs_shell(&self) -> bool {
+        true
+    }
+
+    fn parse_output(&self, output: &str) -> Option<Self::Output> {
+        let line = output
+            .lines()
+            .map(str::trim)
+            .rfind(|line| !line.is_empty())?;
+        if line == "unsupported" {
+            return Some(None);
+        }
+        line.strip_prefix("sent ")
+            .map(|tool| Some(tool.to_string()))
+    }
+}
+
+pub async fn get_listening_ports(
+    ssh_connect_config: &SshConnectConfig,
+) -> Result<Vec<ListeningPort>> {
+    let result = run_discovery(
+        ssh_connect_config,
+        &GetListeningPortsCmd,
+        Duration::from_secs(10),
+    )
+    .await
+    .context("Failed to list listening ports, ss or netstat must be available")?;
+    Ok(result.unwrap_or_default())
+}
+
+/// 容器健康检查状态：`healthy` / `starting` / `unhealthy`，未配置健康检查时为 `none`
+pub struct GetContainerHealthCmd {
+    pub container_name: String,
+    pub runtime: ContainerRuntime,
+}
+
+impl RemoteCommand for GetContainerHealthCmd {
+    type Output = String;
+
+    fn to_shell_string(&self) -> String {
+        let container_name = Cow::from(&self.container_name);
+        format!(
+            "{} inspect -f '{{{{if .State.Health}}}}{{{{.State.Health.Status}}}}{{{{else}}}}none{{{{end}}}}' {}",
+            self.runtime.binary(),
+            escape(container_name)
+        )
+    }
+
+    fn needs_shell(&self) -> bool {
+        self.runtime == ContainerRuntime::Auto
+    }
+
+    fn parse_output(&self, output: &str) -> Option<Self::Output> {
+        let status = output.trim();
+        (!status.is_empty()).then(|| status.to_string())
     }
 }
 
 pub async fn get_container_infos(
     ssh_connect_config: &SshConnectConfig,
     keyword: Option<String>,
+    runtime: ContainerRuntime,
 ) -> Result<Vec<ContainerInfo>> {
-    let ssh_instance = Ssh::init(ssh_connect_config.clone()).await?;
     let command = GetContainerInfoCmd {
         keyword: keyword.clone(),
+        runtime,
     };
-    let result = ssh_instance
-        .exec_cmd(&command, Duration::from_secs(10))
-        .await?;
+    let result = run_discovery(ssh_connect_config, &command, Duration::from_secs(10)).await?;
     match result {
         None => Ok(vec![]),
         Some(container_infos) => Ok(container_infos),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn container_addr(output: &str) -> Option<String> {
+        GetContainerAddrCmd {
+            container_name: "web".to_string(),
+            runtime: ContainerRuntime::Docker,
+        }
+        .parse_output(output)
+    }
+
+    #[test]
+    fn container_addr_prefers_ipv4() {
+        assert_eq!(
+            container_addr("172.17.0.2 fd00::2 \n").as_deref(),
+            Some("172.17.0.2")
+        );
+        // 第一个网络只有 IPv6，第二个网络有 IPv4
+        assert_eq!(
+            container_addr(" fd00::2 10.0.0.3  \n").as_deref(),
+            Some("10.0.0.3")
+        );
+    }
+
+    #[test]
+    fn container_addr_falls_back_to_ipv6() {
+        assert_eq!(container_addr(" fd00::5 \n").as_deref(), Some("fd00::5"));
+        assert_eq!(
+            container_addr(" 2001:db8::1 ").as_deref(),
+            Some("2001:db8::1")
+        );
+    }
+
+    #[test]
+    fn container_addr_without_address() {
+        assert_eq!(container_addr("  \n"), None);
+        assert_eq!(container_addr("<no value> <no value> "), None);
+    }
+}
@@ -0,0 +1,194 @@
+use crate::error::AppError;
+use crate::server::model::SshConnectConfig;
+use crate::server::remote_cmd::RemoteCommand;
+use crate::server::ssh::Ssh;
+use anyhow::Result;
+use log::info;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use shell_escape::escape;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tokio::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// 开始跟踪时最多回看的行数
+pub const MAX_INITIAL_LINES: u32 = 10_000;
+/// 每秒最多转发的行数，超出的行丢弃并计数
+const MAX_LINES_PER_SEC: u32 = 200;
+
+/// 进行中的跟踪，按 id 取消
+static TAILS: Lazy<Mutex<HashMap<u64, CancellationToken>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// 推送给前端的跟踪事件
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TailEvent {
+    Line {
+        line: String,
+    },
+    /// tail 的 stderr，例如文件被截断或轮转后重新打开的提示
+    Notice {
+        message: String,
+    },
+    /// 超过速率限制而丢弃的行数
+    Dropped {
+        count: u64,
+    },
+    /// 跟踪结束，主动停止时 error 为 None
+    Ended {
+        error: Option<String>,
+    },
+}
+
+/// `tail -F` 按文件名跟踪，文件轮转或截断后会重新打开
+struct TailCmd {
+    path: String,
+    lines: u32,
+}
+
+impl RemoteCommand for TailCmd {
+    type Output = ();
+
+    fn to_shell_string(&self) -> String {
+        format!(
+            "tail -n {} -F -- {}",
+            self.lines,
+            escape(Cow::from(&self.path))
+        )
+    }
+
+    fn requires_sudo(&self) -> bool {
+        false
+    }
+
+    fn parse_output(&self, _output: &str) -> Option<Self::Output> {
+        Some(())
+    }
+}
+
+/// 固定一秒窗口的行数限制
+struct RateLimit {
+    window_start: Instant,
+    count: u32,
+    dropped: u64,
+}
+
+impl RateLimit {
+    fn new() -> Self {
+        Self {
+            window_start: Instant::now(),
+            count: 0,
+            dropped: 0,
+        }
+    }
+
+    /// 返回这一行能否发送，进入新窗口时先报告上个窗口丢弃的行数
+    fn admit(&mut self, on_event: &mut impl FnMut(TailEvent)) -> bool {
+        if self.window_start.elapsed() >= Duration::from_secs(1) {
+            self.report_dropped(on_event);
+            self.window_start = Instant::now();
+            self.count = 0;
+        }
+        if self.count >= MAX_LINES_PER_SEC {
+            self.dropped += 1;
+            return false;
+        }
+        self.count += 1;
+        true
+    }
+
+    fn report_dropped(&mut self, on_event: &mut impl FnMut(TailEvent)) {
+        if self.dropped > 0 {
+            on_event(TailEvent::Dropped {
+                count: std::mem::take(&mut self.dropped),
+            });
+        }
+    }
+}
+
+/// 在后台跟踪远程文件，事件连同跟踪 id 通过 on_event 推送，最后一个事件总是 Ended
+///
+/// 返回的 id 用于 `stop`。每个跟踪使用独立的 SSH 会话，结束时断开。
+pub fn start(
+    config: SshConnectConfig,
+    path: String,
+    lines: u32,
+    mut on_event: impl FnMut(u64, TailEvent) + Send + 'static,
+) -> Result<u64> {
+    let path = path.trim().to_string();
+    if path.is_empty() || path.contains(['\n', '\0']) {
+        return Err(AppError::validation("Invalid remote file path").into());
+    }
+    if lines > MAX_INITIAL_LINES {
+        return Err(
+            AppError::validation(format!("lines must be at most {}", MAX_INITIAL_LINES)).into(),
+        );
+    }
+
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let token = CancellationToken::new();
+    TAILS.lock().unwrap().insert(id, token.clone());
+    info!(
+        "Tailing {} on {}:{} (tail #{})",
+        path, config.ssh_host, config.ssh_port, id
+    );
+
+    tokio::spawn(async move {
+        let mut emit = |event: TailEvent| on_event(id, event);
+        let result = follow(config, TailCmd { path, lines }, token, &mut emit).await;
+        TAILS.lock().unwrap().remove(&id);
+        info!("Tail #{} ended", id);
+        emit(TailEvent::Ended {
+            error: result.err().map(|e| format!("{:#}", e)),
+        });
+    });
+    Ok(id)
+}
+
+async fn follow(
+    config: SshConnectConfig,
+    command: TailCmd,
+    token: CancellationToken,
+    on_event: &mut impl FnMut(TailEvent),
+) -> Result<()> {
+    let ssh = tokio::select! {
+        ssh = Ssh::init(config) => ssh?,
+        _ = token.cancelled() => return Ok(()),
+    };
+    let mut limit = RateLimit::new();
+    let result = ssh
+        .exec_stream(&command, token.clone(), |is_stderr, line| {
+            if is_stderr {
+                on_event(TailEvent::Notice { message: line });
+            } else if limit.admit(on_event) {
+                on_event(TailEvent::Line { line });
+            }
+        })
+        .await;
+    limit.report_dropped(on_event);
+    ssh.disconnect().await;
+
+    match result? {
+        // tail -F 只会在出错或被取消时退出
+        Some(code) if code != 0 && !token.is_cancelled() => {
+            Err(anyhow::anyhow!("tail exited with status {}", code))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// 停止一个跟踪，不存在或已结束时返回 false
+pub fn stop(id: u64) -> bool {
+    match TAILS.lock().unwrap().remove(&id) {
+        Some(token) => {
+            token.cancel();
+            true
+        }
+        None => false,
+    }
+}
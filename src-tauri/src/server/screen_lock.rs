@@ -0,0 +1,85 @@
+#[cfg(windows)]
+use crate::server::CREATE_NO_WINDOW;
+use log::info;
+use once_cell::sync::Lazy;
+use tokio::sync::watch;
+use tokio::time::Duration;
+
+/// 锁屏状态的轮询间隔
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 当前是否锁屏，开启了 pause_when_locked 的转发订阅该通道
+static LOCKED: Lazy<watch::Sender<bool>> = Lazy::new(|| watch::channel(false).0);
+
+pub fn subscribe() -> watch::Receiver<bool> {
+    LOCKED.subscribe()
+}
+
+/// 后台轮询系统的锁屏状态，没有订阅者时不执行检测命令
+///
+/// 无法判断锁屏状态的平台始终视为未锁屏。
+pub async fn watch_screen_lock() {
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        if LOCKED.receiver_count() == 0 {
+            continue;
+        }
+        let locked = tokio::task::spawn_blocking(detect)
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or(false);
+        if LOCKED.send_if_modified(|current| std::mem::replace(current, locked) != locked) {
+            info!(
+                "Screen {}, {} listener(s) affected",
+                if locked { "locked" } else { "unlocked" },
+                LOCKED.receiver_count()
+            );
+        }
+    }
+}
+
+/// macOS 读 IORegistry 的 CGSSessionScreenIsLocked，Linux 读 logind 的 LockedHint，
+/// Windows 以锁屏界面 LogonUI.exe 是否在运行为准
+fn detect() -> Option<bool> {
+    if cfg!(target_os = "macos") {
+        let output = command_output("ioreg", &["-n", "Root", "-d1"])?;
+        Some(
+            output
+                .lines()
+                .any(|line| line.contains("\"CGSSessionScreenIsLocked\"=Yes")),
+        )
+    } else if cfg!(windows) {
+        let output = command_output("tasklist", &["/FI", "IMAGENAME eq LogonUI.exe", "/NH"])?;
+        Some(output.to_ascii_lowercase().contains("logonui.exe"))
+    } else if cfg!(target_os = "linux") {
+        let session = std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "auto".to_string());
+        let output = command_output(
+            "loginctl",
+            &["show-session", &session, "-p", "LockedHint", "--value"],
+        )?;
+        match output.trim() {
+            "yes" => Some(true),
+            "no" => Some(false),
+            _ => None,
+        }
+    } else {
+        None
+    }
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let mut cmd = std::process::Command::new(program);
+    cmd.args(args);
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+    let output = cmd.output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
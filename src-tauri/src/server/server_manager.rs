@@ -1,22 +1,64 @@
 use crate::server::manager::TunnelManager;
 
 use crate::database::entity::tunnel_config::Model as TunnelModel;
-use crate::server::model::TunnelMetric;
+use crate::database::models::TunnelCounters;
+use crate::database::DB;
+use crate::server::logs::{self, TunnelLogBuffer, LOG_BATCH_INTERVAL, LOG_BATCH_MAX_LINES};
+use crate::server::metrics_history::{MetricsSampler, SAMPLE_INTERVAL};
+use crate::server::metrics_ws::MetricsWsServer;
+use crate::server::model::{LogPayload, ShutdownReport, TunnelMetric, TunnelState};
+use crate::settings::{AppSettings, SettingsManager};
 use crate::TrayStatusPayload;
 use anyhow::Result;
-use std::sync::Arc;
+use log::{debug, info, warn};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tauri::{AppHandle, Emitter};
+use tokio::sync::mpsc;
+
+// 调试关闭时等待任务退出的期限
+const SHUTDOWN_DEADLINE: Duration = Duration::from_secs(5);
+
+/// 托盘状态没有变化时不推送，隧道很多时避免每次刷新都重设菜单文字
+#[derive(Default)]
+struct TrayStatusTracker {
+    last: Option<TrayStatusPayload>,
+}
+
+impl TrayStatusTracker {
+    /// 与上次推送的状态不同时返回新的状态
+    fn changed(&mut self, all_status: &HashMap<String, TunnelMetric>) -> Option<TrayStatusPayload> {
+        let payload = TrayStatusPayload::from_tunnel_metric_map(all_status);
+        if self.last.as_ref() == Some(&payload) {
+            return None;
+        }
+        self.last = Some(payload.clone());
+        Some(payload)
+    }
+}
 
 #[derive(Clone)]
 pub struct ServerManager {
     tunnel_manager: Arc<TunnelManager>,
+    log_buffer: TunnelLogBuffer,
+    // 由 monitor_tunnels_status 取走，用于转发日志到前端
+    log_rx: Arc<Mutex<Option<mpsc::UnboundedReceiver<LogPayload>>>>,
+    metrics_ws: MetricsWsServer,
+    // 采样任务与 get_tunnel_counters 共用，用于计算尚未落盘的增量
+    sampler: Arc<Mutex<MetricsSampler>>,
 }
 
 impl ServerManager {
-    pub fn new() -> Self {
+    pub fn new(settings: Arc<SettingsManager>) -> Self {
+        let (log_tx, log_rx) = mpsc::unbounded_channel();
         Self {
-            tunnel_manager: Arc::new(TunnelManager::new()),
+            tunnel_manager: Arc::new(TunnelManager::new(log_tx, settings)),
+            log_buffer: TunnelLogBuffer::default(),
+            log_rx: Arc::new(Mutex::new(Some(log_rx))),
+            metrics_ws: MetricsWsServer::new(),
+            sampler: Arc::new(Mutex::new(MetricsSampler::default())),
         }
     }
 
@@ -41,27 +83,300 @@ impl ServerManager {
         state.unwrap_or(TunnelMetric::default())
     }
 
+    /// 所有已加载隧道的当前指标
+    pub async fn get_all_tunnel_metrics(&self) -> HashMap<String, TunnelMetric> {
+        self.tunnel_manager.get_all_tunnel_health_state().await
+    }
+
+    /// 运行中隧道实际绑定的本地地址，按隧道 id 排序
+    pub async fn get_active_bindings(&self) -> Vec<(String, SocketAddr)> {
+        let mut bindings: Vec<(String, SocketAddr)> = self
+            .tunnel_manager
+            .get_all_tunnel_health_state()
+            .await
+            .into_iter()
+            .filter_map(|(id, metric)| metric.local_addr.map(|addr| (id, addr)))
+            .collect();
+        bindings.sort();
+        bindings
+    }
+
+    /// 处于错误状态的隧道 id
+    pub async fn failed_tunnels(&self) -> Vec<String> {
+        let mut failed: Vec<String> = self
+            .tunnel_manager
+            .get_all_tunnel_health_state()
+            .await
+            .into_iter()
+            .filter(|(_, metric)| matches!(metric.tunnel_state, TunnelState::Error(_)))
+            .map(|(id, _)| id)
+            .collect();
+        failed.sort();
+        failed
+    }
+
+    /// 立即重新计算并推送托盘状态，不等下一次定时刷新
+    pub async fn emit_tray_status(&self, app_handle: &AppHandle) {
+        let all_status = self.tunnel_manager.get_all_tunnel_health_state().await;
+        let payload = TrayStatusPayload::from_tunnel_metric_map(&all_status);
+        let _ = app_handle.emit("update-tray-status", &payload);
+    }
+
+    /// 有活动连接且 `window` 内有流量的隧道
+    pub async fn busy_tunnels(&self, window: Duration) -> Vec<(String, u32)> {
+        let mut busy: Vec<(String, u32)> = self
+            .tunnel_manager
+            .get_all_tunnel_health_state()
+            .await
+            .into_iter()
+            .filter(|(_, metric)| {
+                metric.active_connections > 0
+                    && metric
+                        .last_traffic_at
+                        .is_some_and(|at| at.elapsed() <= window)
+            })
+            .map(|(id, metric)| (id, metric.active_connections))
+            .collect();
+        busy.sort();
+        busy
+    }
+
+    /// 持久化的连接计数加上当前会话中尚未落盘的部分
+    pub async fn get_tunnel_counters(&self, id: &String) -> Result<TunnelCounters> {
+        let mut counters = DB::load_tunnel_counters(id).await?;
+        if let Some(metric) = self.tunnel_manager.get_tunnel_metric(id).await {
+            let (connections, errors) = self.sampler.lock().unwrap().pending_counters(id, &metric);
+            counters.connections += connections;
+            counters.connection_errors += errors;
+        }
+        Ok(counters)
+    }
+
+    pub async fn update_tunnel_config(&self, tunnel_model: &TunnelModel) -> Result<()> {
+        self.tunnel_manager.update_tunnel_config(tunnel_model).await
+    }
+
+    pub async fn pause_stats(&self, id: &String) -> Result<()> {
+        self.tunnel_manager.pause_stats(id).await
+    }
+
+    pub async fn resume_stats(&self, id: &String) -> Result<()> {
+        self.tunnel_manager.resume_stats(id).await
+    }
+
+    /// 把尚未落盘的连接计数写入数据库，隧道移除前调用
+    async fn flush_counters(&self, id: &String) {
+        let Some(metric) = self.tunnel_manager.get_tunnel_metric(id).await else {
+            return;
+        };
+        let (connections, connection_errors) = self
+            .sampler
+            .lock()
+            .unwrap()
+            .take_pending_counters(id, &metric);
+        if connections == 0 && connection_errors == 0 {
+            return;
+        }
+        let delta = TunnelCounters {
+            tunnel_id: id.clone(),
+            connections,
+            connection_errors,
+        };
+        if let Err(e) = DB::add_tunnel_counters(&[delta]).await {
+            warn!("Failed to record connection counters of {}: {:#}", id, e);
+        }
+    }
+
     pub async fn remove_tunnel(&self, id: &String) -> Result<()> {
+        self.flush_counters(id).await;
         let manager = self.tunnel_manager.clone();
         manager.remove_tunnel(id).await
     }
 
+    /// 调试用：移除隧道并报告 actor 派生的任务是否全部退出
+    pub async fn shutdown_tunnel_debug(&self, id: &String) -> Result<ShutdownReport> {
+        self.flush_counters(id).await;
+        let report = self
+            .tunnel_manager
+            .shutdown_tunnel_debug(id, SHUTDOWN_DEADLINE)
+            .await?;
+        if report.clean {
+            info!("Tunnel {} shut down cleanly in {}ms", id, report.elapsed_ms);
+        } else {
+            warn!(
+                "Tunnel {} left tasks running after shutdown: {:?}",
+                id, report
+            );
+        }
+        Ok(report)
+    }
+
+    pub fn get_recent_logs(&self, id: Option<&str>, limit: usize) -> Vec<LogPayload> {
+        self.log_buffer.recent(id, limit)
+    }
+
+    pub fn clear_logs(&self, id: &str) {
+        self.log_buffer.clear(id);
+    }
+
+    /// 按设置启停本地 WebSocket 指标服务
+    pub async fn apply_metrics_server(&self, settings: &AppSettings) -> Result<()> {
+        self.metrics_ws
+            .apply(
+                settings.metrics_ws_enabled,
+                &settings.metrics_ws_addr,
+                settings.metrics_ws_allow_remote,
+            )
+            .await
+    }
+
     pub async fn monitor_tunnels_status(&self, app_handle: &AppHandle) -> Result<()> {
         let mut interval = tokio::time::interval(Duration::from_secs(5));
         let manager = self.tunnel_manager.clone();
         let app_handle = app_handle.clone();
 
+        // 日志先写入环形缓冲区，再按批推送给前端，避免日志密集时阻塞 webview
+        if let Some(log_rx) = self.log_rx.lock().unwrap().take() {
+            let log_buffer = self.log_buffer.clone();
+            let app_handle = app_handle.clone();
+            tokio::spawn(logs::batch_logs(
+                log_rx,
+                LOG_BATCH_INTERVAL,
+                LOG_BATCH_MAX_LINES,
+                move |batch| {
+                    for payload in &batch {
+                        log_buffer.push(payload.clone());
+                    }
+                    let _ = app_handle.emit("tunnel-log", &batch);
+                },
+            ));
+        }
+
+        // 每分钟记录一次流量增量和延迟，供导出历史使用
+        let sampler_manager = self.tunnel_manager.clone();
+        let sampler = self.sampler.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+            loop {
+                interval.tick().await;
+                let all_status = sampler_manager.get_all_tunnel_health_state().await;
+                let (samples, counters) = {
+                    let mut sampler = sampler.lock().unwrap();
+                    (
+                        sampler.sample(&all_status),
+                        sampler.counter_deltas(&all_status),
+                    )
+                };
+                if let Err(e) =
+                    DB::insert_metric_samples(&samples, MetricsSampler::retain_after()).await
+                {
+                    warn!("Failed to record metrics history: {:#}", e);
+                }
+                if let Err(e) = DB::add_tunnel_counters(&counters).await {
+                    warn!("Failed to record connection counters: {:#}", e);
+                }
+            }
+        });
+
+        let metrics_ws = self.metrics_ws.clone();
         tokio::spawn(async move {
+            let mut tray_status = TrayStatusTracker::default();
             loop {
                 interval.tick().await;
                 let all_status = manager.get_all_tunnel_health_state().await;
-                println!("all_status: {:?}", all_status);
-                let payload = TrayStatusPayload::from_tunnel_metric_map(&all_status);
-                println!("payload: {:?}", &payload);
-                let _ = app_handle.emit("update-tray-status", &payload);
+                metrics_ws.publish(&all_status);
+                if let Some(payload) = tray_status.changed(&all_status) {
+                    debug!("Tray status changed: {:?}", payload);
+                    let _ = app_handle.emit("update-tray-status", &payload);
+                }
             }
         });
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    const TUNNEL_COUNT: usize = 200;
+
+    fn tunnel_model(index: usize) -> TunnelModel {
+        serde_json::from_value(serde_json::json!({
+            "id": format!("tunnel-{}", index),
+            "name": format!("Tunnel {}", index),
+            "mode": "standard",
+            "ssh_host": "127.0.0.1",
+            "ssh_port": 22,
+            "ssh_username": "test",
+            "auth_type": "password",
+            "identities_only": false,
+            "bypass_proxy": false,
+            "auth_fallback": false,
+            "forward_type": "direct",
+            "auto_remap_port": false,
+            "fail_fast": false,
+            "pause_when_locked": false,
+            "wait_for_healthy": false,
+            "was_running": false,
+            "local_port": 20000 + index,
+            "target_host": "127.0.0.1",
+            "target_port": 80,
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn many_tunnels_aggregate_quickly_and_emit_only_on_change() {
+        let settings = Arc::new(SettingsManager::new().await);
+        let manager = ServerManager::new(settings);
+        for index in 0..TUNNEL_COUNT {
+            manager
+                .tunnel_manager
+                .add_tunnel(&tunnel_model(index))
+                .await;
+        }
+        manager
+            .metrics_ws
+            .apply(true, "127.0.0.1:0", false)
+            .await
+            .unwrap();
+        let mut tray_status = TrayStatusTracker::default();
+
+        // 一次完整的刷新：汇总、推送指标、计算托盘状态
+        let started = Instant::now();
+        let all_status = manager.tunnel_manager.get_all_tunnel_health_state().await;
+        assert!(manager.metrics_ws.publish(&all_status));
+        let payload = tray_status.changed(&all_status).unwrap();
+        let elapsed = started.elapsed();
+        assert_eq!(all_status.len(), TUNNEL_COUNT);
+        assert_eq!(payload.unavailable_count, TUNNEL_COUNT);
+        assert!(
+            elapsed < Duration::from_millis(200),
+            "aggregation pass took {:?}",
+            elapsed
+        );
+
+        // 没有变化时都不推送
+        let mut all_status = manager.tunnel_manager.get_all_tunnel_health_state().await;
+        assert!(!manager.metrics_ws.publish(&all_status));
+        assert!(tray_status.changed(&all_status).is_none());
+
+        // 单个隧道出错时两者都推送
+        all_status.get_mut("tunnel-7").unwrap().tunnel_state =
+            TunnelState::Error("connection refused".to_string());
+        assert!(manager.metrics_ws.publish(&all_status));
+        let payload = tray_status.changed(&all_status).unwrap();
+        assert_eq!(payload.error_count, 1);
+        assert_eq!(payload.unavailable_count, TUNNEL_COUNT - 1);
+
+        // 只有流量变化时指标推送，托盘不变
+        all_status.get_mut("tunnel-8").unwrap().traffic.recv_bytes += 1024;
+        assert!(manager.metrics_ws.publish(&all_status));
+        assert!(tray_status.changed(&all_status).is_none());
+
+        manager.metrics_ws.stop();
+    }
+}
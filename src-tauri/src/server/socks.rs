@@ -0,0 +1,185 @@
+use anyhow::{anyhow, Result};
+use std::net::{Ipv4Addr, Ipv6Addr};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Duration};
+
+/// 客户端完成握手的最长时间
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 回复码 (RFC 1928)
+const REPLY_SUCCEEDED: u8 = 0x00;
+const REPLY_GENERAL_FAILURE: u8 = 0x01;
+const REPLY_CONNECTION_REFUSED: u8 = 0x05;
+const REPLY_COMMAND_NOT_SUPPORTED: u8 = 0x07;
+const REPLY_ADDRESS_NOT_SUPPORTED: u8 = 0x08;
+
+/// 完成 SOCKS5 握手，返回客户端请求的目标，之后需用 `reply` 告知连接结果
+///
+/// 只支持无认证的 CONNECT，本地监听只接受本机连接。域名原样交给 SSH 主机解析。
+pub async fn accept(stream: &mut TcpStream) -> Result<(String, u16)> {
+    timeout(HANDSHAKE_TIMEOUT, read_request(stream))
+        .await
+        .map_err(|_| anyhow!("SOCKS handshake timed out after {:?}", HANDSHAKE_TIMEOUT))?
+}
+
+/// 通道打开后回复客户端，失败时回复 connection refused
+pub async fn reply(stream: &mut TcpStream, success: bool) -> Result<()> {
+    let code = if success {
+        REPLY_SUCCEEDED
+    } else {
+        REPLY_CONNECTION_REFUSED
+    };
+    send_reply(stream, code).await
+}
+
+async fn read_request(stream: &mut TcpStream) -> Result<(String, u16)> {
+    let mut greeting = [0u8; 2];
+    stream.read_exact(&mut greeting).await?;
+    if greeting[0] != 0x05 {
+        return Err(anyhow!("Unsupported SOCKS version {}", greeting[0]));
+    }
+    let mut methods = vec![0u8; greeting[1] as usize];
+    stream.read_exact(&mut methods).await?;
+    // 0x00 无认证，0xFF 没有可用的方法
+    if !methods.contains(&0x00) {
+        stream.write_all(&[0x05, 0xFF]).await?;
+        return Err(anyhow!("SOCKS client offered no supported auth method"));
+    }
+    stream.write_all(&[0x05, 0x00]).await?;
+
+    let mut header = [0u8; 4];
+    stream.read_exact(&mut header).await?;
+    if header[1] != 0x01 {
+        send_reply(stream, REPLY_COMMAND_NOT_SUPPORTED).await?;
+        return Err(anyhow!(
+            "Unsupported SOCKS command {}, only CONNECT is supported",
+            header[1]
+        ));
+    }
+    let host = match header[3] {
+        0x01 => {
+            let mut octets = [0u8; 4];
+            stream.read_exact(&mut octets).await?;
+            Ipv4Addr::from(octets).to_string()
+        }
+        0x04 => {
+            let mut octets = [0u8; 16];
+            stream.read_exact(&mut octets).await?;
+            Ipv6Addr::from(octets).to_string()
+        }
+        0x03 => {
+            let mut len = [0u8; 1];
+            stream.read_exact(&mut len).await?;
+            let mut name = vec![0u8; len[0] as usize];
+            stream.read_exact(&mut name).await?;
+            match String::from_utf8(name) {
+                Ok(name) if !name.is_empty() => name,
+                _ => {
+                    send_reply(stream, REPLY_GENERAL_FAILURE).await?;
+                    return Err(anyhow!("Invalid SOCKS domain name"));
+                }
+            }
+        }
+        other => {
+            send_reply(stream, REPLY_ADDRESS_NOT_SUPPORTED).await?;
+            return Err(anyhow!("Unsupported SOCKS address type {}", other));
+        }
+    };
+    let mut port = [0u8; 2];
+    stream.read_exact(&mut port).await?;
+    Ok((host, u16::from_be_bytes(port)))
+}
+
+/// 绑定地址填 0.0.0.0:0，客户端不需要它
+async fn send_reply(stream: &mut TcpStream, code: u8) -> Result<()> {
+    stream
+        .write_all(&[0x05, code, 0x00, 0x01, 0, 0, 0, 0, 0, 0])
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// 客户端发送 `request` 后读取服务端的全部回复，服务端处理完即断开
+    async fn handshake(request: &[u8]) -> (Result<(String, u16)>, Vec<u8>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            read_request(&mut stream).await
+        });
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client.write_all(request).await.unwrap();
+        let mut replies = Vec::new();
+        client.read_to_end(&mut replies).await.unwrap();
+        (server.await.unwrap(), replies)
+    }
+
+    fn connect_request(address: &[u8], port: u16) -> Vec<u8> {
+        let mut request = vec![0x05, 0x01, 0x00, 0x05, 0x01, 0x00];
+        request.extend_from_slice(address);
+        request.extend_from_slice(&port.to_be_bytes());
+        request
+    }
+
+    #[tokio::test]
+    async fn reads_ipv4_target() {
+        let (result, replies) = handshake(&connect_request(&[0x01, 10, 0, 0, 5], 8080)).await;
+        assert_eq!(result.unwrap(), ("10.0.0.5".to_string(), 8080));
+        // 只回复了方法选择，连接结果由 reply 发送
+        assert_eq!(replies, [0x05, 0x00]);
+    }
+
+    #[tokio::test]
+    async fn reads_ipv6_target() {
+        let mut address = vec![0x04];
+        address.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+        let (result, _) = handshake(&connect_request(&address, 443)).await;
+        assert_eq!(result.unwrap(), ("::1".to_string(), 443));
+    }
+
+    #[tokio::test]
+    async fn reads_domain_target() {
+        let mut address = vec![0x03, 11];
+        address.extend_from_slice(b"example.com");
+        let (result, _) = handshake(&connect_request(&address, 80)).await;
+        assert_eq!(result.unwrap(), ("example.com".to_string(), 80));
+    }
+
+    #[tokio::test]
+    async fn rejects_clients_without_no_auth_method() {
+        // 只提供用户名密码认证 (0x02)
+        let (result, replies) = handshake(&[0x05, 0x01, 0x02]).await;
+        assert!(result.is_err());
+        assert_eq!(replies, [0x05, 0xFF]);
+    }
+
+    #[tokio::test]
+    async fn rejects_commands_other_than_connect() {
+        // BIND (0x02)，服务端读完请求头即回复
+        let (result, replies) = handshake(&[0x05, 0x01, 0x00, 0x05, 0x02, 0x00, 0x01]).await;
+        assert!(result.is_err());
+        assert_eq!(
+            replies,
+            [
+                0x05,
+                0x00,
+                0x05,
+                REPLY_COMMAND_NOT_SUPPORTED,
+                0x00,
+                0x01,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0
+            ]
+        );
+    }
+}
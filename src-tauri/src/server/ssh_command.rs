@@ -0,0 +1,165 @@
+use crate::database::entity::tunnel_config::Model as TunnelModel;
+use crate::server::model::{format_host_port, port_map_from, unbracket_host};
+use crate::server::remote_cmd::{ContainerRuntime, GetContainerAddrCmd, RemoteCommand};
+use crate::server::system_ssh;
+use anyhow::{anyhow, Result};
+use shell_escape::escape;
+use std::borrow::Cow;
+
+/// 将隧道配置渲染为等价的 OpenSSH 命令行，便于在终端复现或分享
+///
+/// 密码永远不会出现在输出中，密码认证只以注释形式提示。
+/// 无法用 `ssh` 参数表达的部分 (如 docker 容器地址) 同样以注释说明。
+pub fn to_ssh_command(tunnel: &TunnelModel) -> Result<String> {
+    let mut notes = Vec::new();
+    let mut args: Vec<String> = vec!["ssh".into(), "-N".into()];
+
+    let local_port = tunnel
+        .local_port
+        .ok_or_else(|| anyhow!("Local port is not configured"))?;
+
+    match tunnel.mode.as_str() {
+        "standard" => {
+            let target_host = tunnel
+                .target_host
+                .as_deref()
+                .map(unbracket_host)
+                .ok_or_else(|| anyhow!("Target host is not configured"))?;
+            let target_port = tunnel
+                .target_port
+                .ok_or_else(|| anyhow!("Target port is not configured"))?;
+            if tunnel.forward_type == "remote" {
+                // 反向转发：SSH 主机监听 target，转回本地端口
+                args.push("-R".into());
+                args.push(quote(&format!(
+                    "{}:127.0.0.1:{}",
+                    format_host_port(target_host, target_port),
+                    local_port
+                )));
+            } else {
+                args.push("-L".into());
+                args.push(quote(&format!(
+                    "127.0.0.1:{}:{}",
+                    local_port,
+                    format_host_port(target_host, target_port)
+                )));
+            }
+            for mapping in port_map_from(tunnel)? {
+                for offset in 0..=(mapping.local_end - mapping.local_start) {
+                    args.push("-L".into());
+                    args.push(quote(&format!(
+                        "127.0.0.1:{}:{}",
+                        mapping.local_start + offset,
+                        format_host_port(target_host, mapping.remote_base + offset)
+                    )));
+                }
+            }
+        }
+        "socks" => {
+            args.push("-D".into());
+            args.push(format!("127.0.0.1:{}", local_port));
+        }
+        "docker" => {
+            let container_name = tunnel
+                .container_name
+                .as_deref()
+                .ok_or_else(|| anyhow!("Container name is not configured"))?;
+            let runtime = ContainerRuntime::parse(tunnel.container_runtime.as_deref())?;
+            let lookup = GetContainerAddrCmd {
+                container_name: container_name.to_string(),
+                runtime,
+            };
+            args.push("-L".into());
+            args.push(format!(
+                "127.0.0.1:{}:<container-ip>:{}",
+                local_port,
+                tunnel.container_port.unwrap_or(80)
+            ));
+            notes.push(format!(
+                "# Replace <container-ip> with the output of: {}",
+                lookup.to_shell_string()
+            ));
+        }
+        other => return Err(anyhow!("Unsupported tunnel mode: {}", other)),
+    }
+    if let Some(command) = tunnel.target_resolver_command.as_deref() {
+        notes.push(format!(
+            "# The target is resolved at connect time, replace it with the output of: {}",
+            command
+        ));
+    }
+    if let Some(mac) = tunnel.wol_mac.as_deref() {
+        notes.push(format!(
+            "# Wake the target first on the SSH host: wakeonlan {}",
+            mac
+        ));
+    }
+
+    if let Some(source_addr) = tunnel.source_addr.as_deref() {
+        args.push("-b".into());
+        args.push(quote(source_addr));
+    }
+
+    if tunnel.ssh_port != 22 {
+        args.push("-p".into());
+        args.push(tunnel.ssh_port.to_string());
+    }
+
+    if let Some(jump) = system_ssh::jump_spec(tunnel) {
+        args.push("-J".into());
+        args.push(quote(&jump));
+    }
+
+    match tunnel.auth_type.as_str() {
+        "key" => {
+            if let Some(key_path) = tunnel.ssh_key_path.as_deref() {
+                args.push("-i".into());
+                args.push(quote(key_path));
+            }
+            if tunnel.identities_only {
+                args.push("-o".into());
+                args.push("IdentitiesOnly=yes".into());
+            }
+        }
+        "password" => {
+            notes.push("# Uses password authentication, ssh will prompt for it".into());
+        }
+        "agent" => {
+            notes.push("# Uses the identities loaded in ssh-agent".into());
+        }
+        other => return Err(anyhow!("Invalid auth type: {}", other)),
+    }
+
+    if let Some(control_path) = tunnel
+        .control_path
+        .as_deref()
+        .filter(|path| !path.trim().is_empty())
+    {
+        args.push("-S".into());
+        args.push(quote(control_path.trim()));
+    }
+
+    for option in system_ssh::extra_options_from(tunnel)? {
+        args.push("-o".into());
+        args.push(quote(&option));
+    }
+
+    if tunnel.proxy_protocol.is_some() {
+        notes.push(
+            "# The PROXY protocol header is added by ciconia and has no ssh equivalent".into(),
+        );
+    }
+
+    args.push(quote(&format!(
+        "{}@{}",
+        tunnel.ssh_username, tunnel.ssh_host
+    )));
+
+    let mut lines = notes;
+    lines.push(args.join(" "));
+    Ok(lines.join("\n"))
+}
+
+fn quote(value: &str) -> String {
+    escape(Cow::Borrowed(value)).into_owned()
+}
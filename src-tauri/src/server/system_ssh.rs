@@ -0,0 +1,359 @@
+use crate::database::entity::tunnel_config::Model as TunnelModel;
+use crate::server::model::{format_host_port, unbracket_host, ForwardDirection, SshForwardConfig};
+#[cfg(windows)]
+use crate::server::CREATE_NO_WINDOW;
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::process::Stdio;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::process::{Child, Command};
+use tokio::time::{sleep, timeout, Duration, Instant};
+
+/// 等待 ssh 子进程开始监听本地端口的最长时间
+const LISTEN_TIMEOUT: Duration = Duration::from_secs(20);
+const LISTEN_POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// 确认转发时等待 ssh 关闭连接或返回数据的时间
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 隧道使用的 SSH 实现
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SshBackend {
+    /// 默认，与 Russh 相同；系统 ssh 只在显式选择时使用
+    #[default]
+    Auto,
+    Russh,
+    /// 以子进程运行系统的 `ssh -N -L`，不支持的功能见 [`unsupported_feature`]
+    SystemSsh,
+}
+
+impl SshBackend {
+    pub fn parse(value: Option<&str>) -> Result<Self> {
+        match value.map(str::trim).filter(|v| !v.is_empty()) {
+            None | Some("auto") => Ok(Self::Auto),
+            Some("russh") => Ok(Self::Russh),
+            Some("system_ssh") => Ok(Self::SystemSsh),
+            Some(other) => Err(anyhow!(
+                "Invalid SSH backend '{}', expected auto, russh or system_ssh",
+                other
+            )),
+        }
+    }
+}
+
+/// 系统 ssh 对该主机的有效配置 (`ssh -G`)，键为小写的选项名，ssh 不可用时为 None
+///
+/// 同一选项出现多次 (如 identityfile) 时保留第一个值。
+pub async fn effective_config(tunnel: &TunnelModel) -> Option<HashMap<String, String>> {
+    let mut cmd = Command::new("ssh");
+    cmd.arg("-G")
+        .arg("-p")
+        .arg(tunnel.ssh_port.to_string())
+        .arg(destination(tunnel))
+        .stdin(Stdio::null());
+    #[cfg(windows)]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+    let output = match timeout(Duration::from_secs(5), cmd.output()).await {
+        Ok(Ok(output)) if output.status.success() => output,
+        _ => return None,
+    };
+    let mut options = HashMap::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        if let Some((key, value)) = line.split_once(' ') {
+            options
+                .entry(key.to_string())
+                .or_insert_with(|| value.to_string());
+        }
+    }
+    Some(options)
+}
+
+/// 系统 ssh 配置中该主机经过的代理 (ProxyJump 或 ProxyCommand)，没有时为 None
+///
+/// russh 不读取 `~/.ssh/config`，这些主机只有选择系统 ssh 时才会经过代理连接。
+pub async fn proxy_hop(tunnel: &TunnelModel) -> Option<String> {
+    let options = effective_config(tunnel).await?;
+    ["proxyjump", "proxycommand"].iter().find_map(|key| {
+        let value = options.get(*key).filter(|value| *value != "none")?;
+        Some(format!("{} {}", key, value))
+    })
+}
+
+/// 系统 ssh 不支持的功能，启动和预览时都用它检查
+pub fn unsupported_feature(tunnel: &TunnelModel) -> Option<&'static str> {
+    if tunnel.auth_type == "password" {
+        Some("Password authentication")
+    } else if tunnel.forward_type == "container" {
+        Some("Docker mode")
+    } else if tunnel.forward_type == "remote" {
+        Some("Remote forwarding")
+    } else if tunnel.target_resolver_command.is_some() {
+        Some("Target resolver command")
+    } else if tunnel.wol_mac.is_some() {
+        Some("Wake-on-LAN")
+    } else if tunnel.proxy_protocol.is_some() {
+        Some("PROXY protocol")
+    } else if tunnel.port_map.is_some() {
+        Some("Port mapping")
+    } else {
+        None
+    }
+}
+
+/// 启动 `ssh -N -L ...` (SOCKS 模式为 `-D`) 并等待本地端口可连接，子进程在返回的 Child 被丢弃时结束
+///
+/// 只支持密钥和 ssh-agent 认证 (BatchMode)，不能在运行中提示输入密码。
+pub async fn spawn(tunnel: &TunnelModel, forward_config: &SshForwardConfig) -> Result<Child> {
+    if let Some(feature) = unsupported_feature(tunnel) {
+        return Err(anyhow!("{} requires the russh backend", feature));
+    }
+
+    let mut cmd = Command::new("ssh");
+    cmd.args([
+        "-N",
+        "-o",
+        "BatchMode=yes",
+        "-o",
+        "ExitOnForwardFailure=yes",
+    ])
+    .args(["-o", "ServerAliveInterval=30"]);
+    let (flag, spec) = forward_arg(forward_config);
+    cmd.arg(flag).arg(spec);
+    cmd.arg("-p").arg(tunnel.ssh_port.to_string());
+    if let Some(jump) = jump_spec(tunnel) {
+        cmd.arg("-J").arg(jump);
+    }
+    if let Some(key_path) = tunnel
+        .ssh_key_path
+        .as_deref()
+        .filter(|_| tunnel.auth_type == "key")
+    {
+        cmd.arg("-i").arg(key_path);
+    }
+    // IdentitiesOnly 会让 ssh 忽略 agent 中不在 IdentityFile 里的密钥
+    if tunnel.identities_only && tunnel.auth_type == "key" {
+        cmd.args(["-o", "IdentitiesOnly=yes"]);
+    }
+    if let Some(source_addr) = tunnel.source_addr.as_deref() {
+        cmd.arg("-b").arg(source_addr);
+    }
+    // ssh 对同一选项取第一次出现的值，用户选项放在最后，不会覆盖上面的内置选项
+    for option in extra_options_from(tunnel)? {
+        cmd.arg("-o").arg(option);
+    }
+    cmd.arg(destination(tunnel))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    #[cfg(windows)]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+    debug!("Spawning system ssh: {:?}", cmd.as_std());
+
+    let local_addr: SocketAddr =
+        format_host_port(&forward_config.local_host, forward_config.local_port)
+            .parse()
+            .with_context(|| format!("Invalid local address {}", forward_config.local_host))?;
+    let mut child = cmd
+        .spawn()
+        .context("Failed to run ssh, is OpenSSH installed?")?;
+    let started = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            let mut stderr = String::new();
+            if let Some(mut pipe) = child.stderr.take() {
+                let _ = pipe.read_to_string(&mut stderr).await;
+            }
+            return Err(anyhow!("ssh exited with {}: {}", status, stderr.trim()));
+        }
+        if TcpStream::connect(local_addr).await.is_ok() {
+            return Ok(child);
+        }
+        if started.elapsed() >= LISTEN_TIMEOUT {
+            return Err(anyhow!(
+                "ssh did not start listening on {} within {:?}",
+                local_addr,
+                LISTEN_TIMEOUT
+            ));
+        }
+        sleep(LISTEN_POLL_INTERVAL).await;
+    }
+}
+
+/// 通过本地端口确认 ssh 能把连接转发出去
+///
+/// 目标拒绝连接时 ssh 会立即关闭本地连接，连接保持打开或收到数据视为成功；
+/// SOCKS 模式只确认 ssh 完成了握手。
+pub async fn confirm_forward(forward_config: &SshForwardConfig) -> bool {
+    let Ok(local_addr) = format_host_port(&forward_config.local_host, forward_config.local_port)
+        .parse::<SocketAddr>()
+    else {
+        return false;
+    };
+    let Ok(Ok(mut stream)) = timeout(CONFIRM_TIMEOUT, TcpStream::connect(local_addr)).await else {
+        return false;
+    };
+    if forward_config.direction == ForwardDirection::Dynamic {
+        // 只提供"无需认证"一种方式，期望回复 [5, 0]
+        let mut reply = [0u8; 2];
+        return stream.write_all(&[5, 1, 0]).await.is_ok()
+            && matches!(
+                timeout(CONFIRM_TIMEOUT, stream.read_exact(&mut reply)).await,
+                Ok(Ok(_))
+            )
+            && reply == [5, 0];
+    }
+    let mut buf = [0u8; 1];
+    match timeout(CONFIRM_TIMEOUT, stream.read(&mut buf)).await {
+        Err(_) => true,
+        Ok(Ok(n)) => n > 0,
+        Ok(Err(_)) => false,
+    }
+}
+
+/// `-L` 或 SOCKS 模式的 `-D` 及其参数，IPv6 地址带方括号，如 `[::1]:8080:[fd00::2]:80`
+fn forward_arg(forward_config: &SshForwardConfig) -> (&'static str, String) {
+    let bind = format_host_port(&forward_config.local_host, forward_config.local_port);
+    if forward_config.direction == ForwardDirection::Dynamic {
+        ("-D", bind)
+    } else {
+        (
+            "-L",
+            format!(
+                "{}:{}",
+                bind,
+                format_host_port(&forward_config.remote_host, forward_config.remote_port)
+            ),
+        )
+    }
+}
+
+/// `-J` 的参数 `user@host[:port]`，未配置跳板机时为 None
+pub fn jump_spec(tunnel: &TunnelModel) -> Option<String> {
+    let host = tunnel
+        .jump_host
+        .as_deref()
+        .map(str::trim)
+        .filter(|host| !host.is_empty())?;
+    let user = tunnel
+        .jump_user
+        .as_deref()
+        .map(str::trim)
+        .filter(|user| !user.is_empty())
+        .unwrap_or(&tunnel.ssh_username);
+    let host = unbracket_host(host);
+    Some(match tunnel.jump_port {
+        Some(port) if port != 22 => format!("{}@{}", user, format_host_port(host, port)),
+        _ => format!("{}@{}", user, host),
+    })
+}
+
+/// 解析隧道保存的额外 ssh 选项 (JSON) 并校验
+pub fn extra_options_from(tunnel: &TunnelModel) -> Result<Vec<String>> {
+    let options: Vec<String> = match tunnel.extra_ssh_options.as_deref() {
+        Some(json) => serde_json::from_str(json).context("Invalid extra ssh options")?,
+        None => Vec::new(),
+    };
+    for option in &options {
+        validate_extra_option(option)?;
+    }
+    Ok(options)
+}
+
+/// 额外选项必须是 `Key=Value`，键只能是字母，避免被当作其他命令行参数
+pub fn validate_extra_option(option: &str) -> Result<()> {
+    let (key, value) = option
+        .split_once('=')
+        .ok_or_else(|| anyhow!("SSH option '{}' must look like Key=Value", option))?;
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(anyhow!(
+            "SSH option '{}' must start with an option name made of letters",
+            option
+        ));
+    }
+    if value.trim().is_empty() || value.contains(['\n', '\r', '\0']) {
+        return Err(anyhow!("SSH option '{}' needs a single-line value", option));
+    }
+    Ok(())
+}
+
+fn destination(tunnel: &TunnelModel) -> String {
+    format!("{}@{}", tunnel.ssh_username, tunnel.ssh_host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forward_config(direction: ForwardDirection, local_host: &str) -> SshForwardConfig {
+        SshForwardConfig {
+            direction,
+            local_host: local_host.to_string(),
+            local_port: 8080,
+            auto_remap_port: false,
+            remote_host: "fd00::2".to_string(),
+            remote_port: 80,
+            proxy_protocol: None,
+            port_map: Vec::new(),
+            io_buffer_size: 4096,
+            keepalive_idle: None,
+            max_connections: None,
+            queue_timeout: Duration::from_secs(1),
+            pause_when_locked: false,
+            dscp: None,
+        }
+    }
+
+    #[test]
+    fn forward_arg_brackets_ipv6() {
+        assert_eq!(
+            forward_arg(&forward_config(ForwardDirection::Local, "::1")),
+            ("-L", "[::1]:8080:[fd00::2]:80".to_string())
+        );
+        assert_eq!(
+            forward_arg(&forward_config(ForwardDirection::Local, "127.0.0.1")),
+            ("-L", "127.0.0.1:8080:[fd00::2]:80".to_string())
+        );
+        assert_eq!(
+            forward_arg(&forward_config(ForwardDirection::Dynamic, "::1")),
+            ("-D", "[::1]:8080".to_string())
+        );
+    }
+
+    #[test]
+    fn extra_options_must_be_key_value() {
+        assert!(validate_extra_option("AddressFamily=inet").is_ok());
+        assert!(validate_extra_option("IPQoS=lowdelay throughput").is_ok());
+        // 不能借选项注入其他参数
+        assert!(validate_extra_option("-oProxyCommand=sh").is_err());
+        assert!(validate_extra_option("AddressFamily").is_err());
+        assert!(validate_extra_option("AddressFamily=").is_err());
+        assert!(validate_extra_option("Address Family=inet").is_err());
+        assert!(validate_extra_option("IPQoS=af21\nProxyCommand=sh").is_err());
+    }
+
+    #[tokio::test]
+    async fn confirm_forward_needs_the_connection_kept_open() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut config = forward_config(ForwardDirection::Local, "127.0.0.1");
+        config.local_port = listener.local_addr().unwrap().port();
+
+        // 目标拒绝时 ssh 会立即关闭本地连接
+        let closing = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            drop(stream);
+            listener
+        });
+        assert!(!confirm_forward(&config).await);
+
+        let listener = closing.await.unwrap();
+        let _held = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            sleep(Duration::from_secs(5)).await;
+            drop(stream);
+        });
+        assert!(confirm_forward(&config).await);
+    }
+}
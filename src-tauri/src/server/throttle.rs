@@ -0,0 +1,121 @@
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use tokio::time::{Duration, Instant};
+
+/// 统计握手阶段断开的时间窗口
+const DROP_WINDOW: Duration = Duration::from_secs(10 * 60);
+/// 确认限流后保持该判断的时间
+const THROTTLED_FOR: Duration = Duration::from_secs(30 * 60);
+/// 窗口内达到该次数即视为限流，不必等到重试成功
+const DROPS_FOR_THROTTLING: usize = 2;
+
+#[derive(Default)]
+struct HostState {
+    drops: Vec<Instant>,
+    throttled_at: Option<Instant>,
+}
+
+/// 按 (主机, 端口) 记录认证前被断开的连接
+static HOSTS: Lazy<Mutex<HashMap<(String, u16), HostState>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 认证之前被服务器关闭的连接，sshd 的 MaxStartups 在负载高时会随机丢弃这类连接
+#[derive(Debug)]
+pub struct PreAuthDisconnect {
+    pub host: String,
+    pub port: u16,
+    /// 同一主机近期多次出现，或断开后重试成功
+    pub throttling: bool,
+}
+
+impl fmt::Display for PreAuthDisconnect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.throttling {
+            write!(
+                f,
+                "{}:{} is throttling new connections (sshd MaxStartups) and dropped this one before authentication. \
+                 Retrying later or reusing a session through control_path avoids it",
+                self.host, self.port
+            )
+        } else {
+            write!(
+                f,
+                "{}:{} closed the connection before authentication",
+                self.host, self.port
+            )
+        }
+    }
+}
+
+impl std::error::Error for PreAuthDisconnect {}
+
+/// 错误链中是否有认证前断开
+pub fn is_pre_auth_disconnect(err: &anyhow::Error) -> bool {
+    err.chain()
+        .any(|cause| cause.downcast_ref::<PreAuthDisconnect>().is_some())
+}
+
+/// 记录一次认证前断开，返回带限流判断的错误
+pub fn record_drop(host: &str, port: u16) -> PreAuthDisconnect {
+    let now = Instant::now();
+    let mut hosts = HOSTS.lock().unwrap();
+    let state = hosts.entry((host.to_string(), port)).or_default();
+    state
+        .drops
+        .retain(|at| now.duration_since(*at) < DROP_WINDOW);
+    state.drops.push(now);
+    if state.drops.len() >= DROPS_FOR_THROTTLING && !is_throttled(state, now) {
+        warn!(
+            "{}:{} dropped {} connection attempts before authentication, the server appears to be throttling",
+            host,
+            port,
+            state.drops.len()
+        );
+        state.throttled_at = Some(now);
+    }
+    PreAuthDisconnect {
+        host: host.to_string(),
+        port,
+        throttling: is_throttled(state, now),
+    }
+}
+
+/// 认证成功，若之前刚被断开过则确认是限流 (重试即成功)
+pub fn record_success(host: &str, port: u16) {
+    let now = Instant::now();
+    let mut hosts = HOSTS.lock().unwrap();
+    let Some(state) = hosts.get_mut(&(host.to_string(), port)) else {
+        return;
+    };
+    state
+        .drops
+        .retain(|at| now.duration_since(*at) < DROP_WINDOW);
+    if !state.drops.is_empty() {
+        info!(
+            "Connected to {}:{} after {} dropped attempt(s), the server is throttling new connections",
+            host,
+            port,
+            state.drops.len()
+        );
+        state.throttled_at = Some(now);
+        state.drops.clear();
+    } else if !is_throttled(state, now) {
+        hosts.remove(&(host.to_string(), port));
+    }
+}
+
+/// 主机当前是否被判断为限流
+pub fn is_throttling(host: &str, port: u16) -> bool {
+    HOSTS
+        .lock()
+        .unwrap()
+        .get(&(host.to_string(), port))
+        .is_some_and(|state| is_throttled(state, Instant::now()))
+}
+
+fn is_throttled(state: &HostState, now: Instant) -> bool {
+    matches!(state.throttled_at, Some(at) if now.duration_since(at) < THROTTLED_FOR)
+}
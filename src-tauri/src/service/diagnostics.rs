@@ -0,0 +1,198 @@
+use crate::database::entity::tunnel_config::Model as TunnelModel;
+use crate::database::models::{AppSettings, TunnelConfig, TunnelEvent};
+use crate::error::AppError;
+use crate::server::dns::DnsStats;
+use crate::server::model::{LogPayload, SshConnectConfig, TunnelMetric, TunnelState};
+use crate::server::remote_cmd::DiagnosticCmd;
+use crate::server::ssh::{ExecOutput, Ssh};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// 替换敏感字段的占位符
+pub const REDACTED: &str = "<redacted>";
+
+/// 可能泄露凭据的隧道字段：密码、私钥路径与口令，以及可能带有令牌的命令
+///
+/// 诊断包和配置比较都按这份列表脱敏。
+pub const SECRET_FIELDS: [&str; 6] = [
+    "ssh_password",
+    "ssh_key_path",
+    "ssh_key_passphrase",
+    "on_failure_command",
+    "check_command",
+    "target_resolver_command",
+];
+
+/// 一次性检查命令默认和最长的总超时 (连接加执行)
+pub const DEFAULT_CHECK_TIMEOUT_SECS: u64 = 30;
+pub const MAX_CHECK_TIMEOUT_SECS: u64 = 300;
+
+/// 单个隧道的 SSH 连接测试结果
+#[derive(Debug, Serialize)]
+pub struct ConnectionTest {
+    pub id: String,
+    pub name: String,
+    pub ok: bool,
+    /// 建立连接并完成认证的耗时
+    pub elapsed_ms: u64,
+    pub error: Option<String>,
+}
+
+/// 提交问题时附带的诊断包，所有可能泄露凭据的字段都已脱敏
+#[derive(Debug, Serialize)]
+pub struct DiagnosticBundle {
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub created_at: String,
+    pub settings: AppSettings,
+    pub tunnels: Vec<TunnelConfig>,
+    pub events: Vec<TunnelEvent>,
+    /// 按隧道 id 分组的最近日志
+    pub logs: HashMap<String, Vec<LogPayload>>,
+    pub connection_tests: Vec<ConnectionTest>,
+    pub dns: DnsStats,
+}
+
+/// 某一时刻所有隧道的指标，用于排查前后对比
+#[derive(Debug, Serialize)]
+pub struct MetricsSnapshot {
+    pub app_version: String,
+    pub created_at: String,
+    pub tunnels: Vec<TunnelSnapshot>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TunnelSnapshot {
+    pub id: String,
+    pub name: String,
+    pub state: &'static str,
+    pub error: Option<String>,
+    pub latency_ms: Option<u128>,
+    pub send_bytes: u128,
+    pub recv_bytes: u128,
+    pub active_connections: u32,
+    pub queued_connections: u32,
+    pub connections: u64,
+    pub connection_errors: u64,
+    pub local_addr: Option<String>,
+    /// 本次启动以来的秒数，未运行时为 None
+    pub uptime_secs: Option<u64>,
+}
+
+impl TunnelSnapshot {
+    pub fn new(tunnel: &TunnelConfig, metric: &TunnelMetric) -> Self {
+        let (state, error) = metric.tunnel_state.label();
+        let running = !matches!(
+            metric.tunnel_state,
+            TunnelState::Stopped | TunnelState::Error(_)
+        );
+        Self {
+            id: tunnel.id.clone(),
+            name: tunnel.name.clone(),
+            state,
+            error,
+            latency_ms: metric.last_latency.map(|d| d.as_millis()),
+            send_bytes: metric.traffic.send_bytes,
+            recv_bytes: metric.traffic.recv_bytes,
+            active_connections: metric.active_connections,
+            queued_connections: metric.queued_connections,
+            connections: metric.connections,
+            connection_errors: metric.connection_errors,
+            local_addr: metric.local_addr.map(|addr| addr.to_string()),
+            uptime_secs: metric
+                .started_at
+                .filter(|_| running)
+                .map(|at| at.elapsed().as_secs()),
+        }
+    }
+}
+
+/// 建立一次 SSH 连接并立即断开，不做端口转发
+pub async fn test_connection(tunnel: &TunnelModel, timeout: Duration) -> ConnectionTest {
+    let started = Instant::now();
+    let result = async {
+        let config = SshConnectConfig::try_from(tunnel)?;
+        let ssh = tokio::time::timeout(timeout, Ssh::init(config))
+            .await
+            .map_err(|_| anyhow::anyhow!("Connection timed out after {:?}", timeout))??;
+        ssh.disconnect().await;
+        anyhow::Ok(())
+    }
+    .await;
+
+    ConnectionTest {
+        id: tunnel.id.clone(),
+        name: tunnel.name.clone(),
+        ok: result.is_ok(),
+        elapsed_ms: started.elapsed().as_millis() as u64,
+        error: result.err().map(|e| format!("{:#}", e)),
+    }
+}
+
+/// 连接、执行隧道的 check_command 并断开，timeout 覆盖连接和执行的总时间
+///
+/// 会话只在本次调用中使用，无论成功、失败还是超时都会断开。
+pub async fn run_check_command(
+    tunnel: &TunnelModel,
+    timeout: Duration,
+) -> anyhow::Result<ExecOutput> {
+    let command = tunnel
+        .check_command
+        .as_deref()
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .ok_or_else(|| AppError::validation("This tunnel has no check command"))?;
+    let config = SshConnectConfig::try_from(tunnel)?;
+
+    let deadline = Instant::now() + timeout;
+    let ssh = tokio::time::timeout(timeout, Ssh::init(config))
+        .await
+        .map_err(|_| anyhow::anyhow!("Connection timed out after {:?}", timeout))??;
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    let command = DiagnosticCmd {
+        command: command.to_string(),
+    };
+    let result = ssh.exec_raw(&command, remaining).await;
+    ssh.disconnect().await;
+    result
+}
+
+/// [`SECRET_FIELDS`] 中的非空字符串替换为占位符，其余原样返回
+pub fn mask_secret(field: &str, value: &serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::String(s) if SECRET_FIELDS.contains(&field) && !s.is_empty() => {
+            serde_json::Value::String(REDACTED.to_string())
+        }
+        value => value.clone(),
+    }
+}
+
+/// 按 [`SECRET_FIELDS`] 脱敏隧道配置
+pub fn redact_tunnel(tunnel: TunnelConfig) -> serde_json::Result<TunnelConfig> {
+    let mut value = serde_json::to_value(tunnel)?;
+    if let Some(fields) = value.as_object_mut() {
+        for (field, value) in fields.iter_mut() {
+            *value = mask_secret(field, value);
+        }
+    }
+    serde_json::from_value(value)
+}
+
+pub fn redact_settings(mut settings: AppSettings) -> AppSettings {
+    if settings.default_ssh_key.is_some() {
+        settings.default_ssh_key = Some(REDACTED.to_string());
+    }
+    settings
+}
+
+/// 日志中出现的已知密码等敏感值替换为占位符
+pub fn scrub_line(line: &str, secrets: &[String]) -> String {
+    secrets
+        .iter()
+        .filter(|secret| !secret.is_empty())
+        .fold(line.to_string(), |line, secret| {
+            line.replace(secret.as_str(), REDACTED)
+        })
+}
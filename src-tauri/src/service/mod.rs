@@ -1 +1,5 @@
+pub mod diagnostics;
+pub mod schedule;
 pub mod tunnel;
+pub mod uri;
+pub mod usage;
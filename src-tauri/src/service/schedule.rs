@@ -0,0 +1,186 @@
+use crate::database::entity::tunnel_config::Model as TunnelModel;
+use crate::database::models::TunnelSchedule;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, FixedOffset, Local, NaiveTime, Utc, Weekday};
+use std::time::Duration;
+
+/// 检查时间段的周期
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+impl TunnelSchedule {
+    pub fn validate(&self) -> Result<()> {
+        parse_days(&self.days)?;
+        let start = parse_time(&self.start)?;
+        let end = parse_time(&self.end)?;
+        if start == end {
+            return Err(anyhow!("Schedule start and end must differ"));
+        }
+        parse_timezone(self.timezone.as_deref())?;
+        Ok(())
+    }
+
+    /// `now` 是否落在允许的时间段内；结束早于开始时表示跨过午夜，星期按开始的那天算
+    pub fn is_open_at(&self, now: DateTime<Utc>) -> Result<bool> {
+        let days = parse_days(&self.days)?;
+        let start = parse_time(&self.start)?;
+        let end = parse_time(&self.end)?;
+        let local = match parse_timezone(self.timezone.as_deref())? {
+            Some(offset) => now.with_timezone(&offset).naive_local(),
+            None => now.with_timezone(&Local).naive_local(),
+        };
+
+        let allowed = |day: Weekday| days.is_empty() || days.contains(&day);
+        let time = local.time();
+        let today = local.weekday();
+        Ok(if start < end {
+            allowed(today) && time >= start && time < end
+        } else {
+            (allowed(today) && time >= start) || (allowed(today.pred()) && time < end)
+        })
+    }
+
+    /// 用于错误提示，如 `mon,tue 09:00-18:00 (local)`
+    pub fn describe(&self) -> String {
+        let days = if self.days.is_empty() {
+            "every day".to_string()
+        } else {
+            self.days.join(",")
+        };
+        format!(
+            "{} {}-{} ({})",
+            days,
+            self.start,
+            self.end,
+            self.timezone.as_deref().unwrap_or("local")
+        )
+    }
+}
+
+/// 读取隧道保存的时间段，解析失败视为未设置
+pub fn schedule_of(tunnel: &TunnelModel) -> Option<TunnelSchedule> {
+    serde_json::from_str(tunnel.schedule.as_deref()?).ok()
+}
+
+fn parse_days(days: &[String]) -> Result<Vec<Weekday>> {
+    days.iter()
+        .map(|day| {
+            day.trim()
+                .parse::<Weekday>()
+                .map_err(|_| anyhow!("Invalid schedule day '{}', expected mon..sun", day))
+        })
+        .collect()
+}
+
+fn parse_time(value: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M")
+        .map_err(|_| anyhow!("Invalid schedule time '{}', expected HH:MM", value))
+}
+
+/// None 或 "local" 使用系统时区，其它支持 "UTC" 和 "+08:00" 形式的固定偏移
+///
+/// 不支持 IANA 时区名：固定偏移不随夏令时变化，需要跟随夏令时的时间段应使用 local。
+fn parse_timezone(value: Option<&str>) -> Result<Option<FixedOffset>> {
+    let value = match value.map(str::trim) {
+        None | Some("") => return Ok(None),
+        Some(value) if value.eq_ignore_ascii_case("local") => return Ok(None),
+        Some(value) => value,
+    };
+    if value.eq_ignore_ascii_case("utc") || value == "Z" {
+        return Ok(FixedOffset::east_opt(0));
+    }
+
+    let invalid = || {
+        anyhow!(
+            "Invalid timezone '{}', use local, UTC or a fixed offset like +08:00 (time zone names are not supported)",
+            value
+        )
+    };
+    let (sign, rest) = match value.as_bytes().first() {
+        Some(b'+') => (1, &value[1..]),
+        Some(b'-') => (-1, &value[1..]),
+        _ => return Err(invalid()),
+    };
+    let (hours, minutes) = rest.split_once(':').ok_or_else(invalid)?;
+    let hours: i32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: i32 = minutes.parse().map_err(|_| invalid())?;
+    if hours > 14 || minutes > 59 {
+        return Err(invalid());
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+        .map(Some)
+        .ok_or_else(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn window(days: &[&str], start: &str, end: &str, timezone: &str) -> TunnelSchedule {
+        TunnelSchedule {
+            days: days.iter().map(|day| day.to_string()).collect(),
+            start: start.to_string(),
+            end: end.to_string(),
+            timezone: Some(timezone.to_string()),
+        }
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        // 2024-01-01 是星期一
+        Utc.with_ymd_and_hms(2024, 1, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn window_crossing_midnight_belongs_to_start_day() {
+        let schedule = window(&["mon"], "22:00", "06:00", "UTC");
+        assert!(!schedule.is_open_at(at(1, 21, 59)).unwrap());
+        assert!(schedule.is_open_at(at(1, 22, 0)).unwrap());
+        // 星期二凌晨属于星期一开始的时间段
+        assert!(schedule.is_open_at(at(2, 5, 59)).unwrap());
+        assert!(!schedule.is_open_at(at(2, 6, 0)).unwrap());
+        // 星期一凌晨属于星期日的时间段，星期日不允许
+        assert!(!schedule.is_open_at(at(1, 3, 0)).unwrap());
+        assert!(!schedule.is_open_at(at(2, 22, 30)).unwrap());
+    }
+
+    #[test]
+    fn window_uses_configured_offset() {
+        let schedule = window(&["tue"], "09:00", "18:00", "+08:00");
+        // 01:00Z 是 +08:00 的星期二 09:00
+        assert!(schedule.is_open_at(at(2, 1, 0)).unwrap());
+        assert!(schedule.is_open_at(at(2, 9, 59)).unwrap());
+        assert!(!schedule.is_open_at(at(2, 10, 0)).unwrap());
+        // 星期一 17:00Z 已是 +08:00 的星期二 01:00，早于开始时间
+        assert!(!schedule.is_open_at(at(1, 17, 0)).unwrap());
+        assert!(!schedule.is_open_at(at(1, 1, 0)).unwrap());
+
+        let every_day = window(&[], "23:30", "00:30", "-05:30");
+        // 05:00Z 是 -05:30 的前一天 23:30
+        assert!(every_day.is_open_at(at(3, 5, 0)).unwrap());
+        assert!(every_day.is_open_at(at(3, 5, 59)).unwrap());
+        assert!(!every_day.is_open_at(at(3, 6, 0)).unwrap());
+    }
+
+    #[test]
+    fn timezone_accepts_only_fixed_offsets() {
+        assert_eq!(parse_timezone(None).unwrap(), None);
+        assert_eq!(parse_timezone(Some(" ")).unwrap(), None);
+        assert_eq!(parse_timezone(Some("Local")).unwrap(), None);
+        assert_eq!(
+            parse_timezone(Some("utc")).unwrap(),
+            FixedOffset::east_opt(0)
+        );
+        assert_eq!(parse_timezone(Some("Z")).unwrap(), FixedOffset::east_opt(0));
+        assert_eq!(
+            parse_timezone(Some("+08:00")).unwrap(),
+            FixedOffset::east_opt(8 * 3600)
+        );
+        assert_eq!(
+            parse_timezone(Some("-05:30")).unwrap(),
+            FixedOffset::west_opt(5 * 3600 + 30 * 60)
+        );
+        for invalid in ["Europe/Berlin", "+8", "+15:00", "+08:60", "08:00"] {
+            assert!(parse_timezone(Some(invalid)).is_err(), "{}", invalid);
+        }
+    }
+}
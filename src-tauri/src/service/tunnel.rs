@@ -1,20 +1,245 @@
-use crate::database::models::TunnelConfig;
+use crate::database::models::{TunnelConfig, TunnelCounters};
 use crate::database::DB;
-use crate::server::model::TunnelMetric;
+use crate::error::AppError;
+use crate::server::dns;
+use crate::server::key_path::{self, validate_key_path};
+use crate::server::model::{
+    parse_source_addr, validate_port_map, ForwardDirection, LogPayload, ShutdownReport,
+    SshConnectConfig, TunnelAuth, TunnelMetric, TunnelState,
+};
+use crate::server::overhead::{self, OverheadReport};
+use crate::server::preview::{self, ForwardPreview};
+use crate::server::remote_cmd::{normalize_mac, DockerForwardVia, RemoteShell};
+use crate::server::ssh::{ExecOutput, Ssh, MAX_DSCP};
+use crate::server::ssh_command;
+use crate::server::system_ssh::{self, SshBackend};
 use crate::server::ServerManager;
-use anyhow::Result;
+use crate::service::diagnostics::{
+    self, ConnectionTest, DiagnosticBundle, MetricsSnapshot, TunnelSnapshot,
+};
+use crate::service::schedule::{self, schedule_of};
+use crate::service::uri;
+use crate::settings::SettingsManager;
+use anyhow::{Context, Result};
 use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
 use tauri::AppHandle;
 
+/// 私钥路径迁移结果，`to` 为空且无错误表示路径已是规范形式
+#[derive(Debug, Serialize)]
+pub struct KeyPathMigration {
+    pub id: String,
+    pub name: String,
+    pub from: String,
+    pub to: Option<String>,
+    pub error: Option<String>,
+}
+
+/// 权限过宽的私钥文件
+#[derive(Debug, Serialize)]
+pub struct KeyPermIssue {
+    pub path: String,
+    /// 八进制权限位，如 "0644"
+    pub mode: String,
+    /// 使用该私钥的隧道
+    pub tunnel_ids: Vec<String>,
+    /// 是否为设置中的默认私钥
+    pub default_key: bool,
+}
+
+/// 退出前仍在传输数据的隧道
+#[derive(Debug, Clone, Serialize)]
+pub struct BusyTunnel {
+    pub id: String,
+    pub name: String,
+    pub active_connections: u32,
+}
+
+/// 最近这段时间内有流量的连接才算正在传输
+const BUSY_TRAFFIC_WINDOW: Duration = Duration::from_secs(10);
+
+/// 批量修改的字段，为 None 的字段保持不变
+#[derive(Debug, Default, Deserialize)]
+pub struct TunnelPatch {
+    pub ssh_username: Option<String>,
+    pub ssh_port: Option<u16>,
+    /// 设置后认证方式切换为密钥并清除已保存的密码
+    pub ssh_key_path: Option<String>,
+}
+
+/// 单个隧道的批量修改结果
+#[derive(Debug, Serialize)]
+pub struct BatchUpdateResult {
+    pub id: String,
+    pub ok: bool,
+    pub error: Option<String>,
+}
+
+/// 活动记录中的一条，已带上隧道名称和描述
+#[derive(Debug, Serialize)]
+pub struct ActivityEntry {
+    pub id: i64,
+    pub tunnel_id: String,
+    /// 隧道已删除时为 None
+    pub tunnel_name: Option<String>,
+    pub kind: String,
+    pub description: String,
+    pub timestamp: u64, // unix millis
+}
+
+/// 两个隧道配置中值不同的字段，敏感字段以占位符显示
+#[derive(Debug, Serialize)]
+pub struct FieldDiff {
+    pub field: String,
+    pub a: serde_json::Value,
+    pub b: serde_json::Value,
+}
+
+/// 判断重复时比较的字段：连接与转发参数，忽略 id、名称和仅影响行为的选项
+#[derive(PartialEq, Eq, Hash)]
+struct TunnelIdentity {
+    mode: String,
+    remote: bool,
+    ssh_host: String,
+    ssh_port: u16,
+    ssh_username: String,
+    auth_type: String,
+    ssh_key_path: Option<String>,
+    local_port: Option<u16>,
+    target: Option<(String, u16)>,
+    container: Option<(String, u16, String)>,
+    port_map: Vec<(u16, u16, u16)>,
+}
+
+impl From<&TunnelConfig> for TunnelIdentity {
+    fn from(tunnel: &TunnelConfig) -> Self {
+        let is_docker = tunnel.mode == "docker";
+        // 主机名大小写不敏感
+        let target = match (&tunnel.target_host, tunnel.target_port) {
+            (Some(host), Some(port)) if tunnel.mode == "standard" => {
+                Some((host.to_lowercase(), port))
+            }
+            _ => None,
+        };
+        let container = match &tunnel.container_name {
+            Some(name) if is_docker => Some((
+                name.clone(),
+                tunnel.container_port.unwrap_or(80),
+                tunnel
+                    .container_runtime
+                    .clone()
+                    .unwrap_or_else(|| "docker".to_string()),
+            )),
+            _ => None,
+        };
+        let mut port_map: Vec<(u16, u16, u16)> = tunnel
+            .port_map
+            .iter()
+            .map(|m| (m.local_start, m.local_end, m.remote_base))
+            .collect();
+        port_map.sort_unstable();
+
+        Self {
+            mode: tunnel.mode.clone(),
+            remote: tunnel.direction.as_deref() == Some("remote"),
+            ssh_host: tunnel.ssh_host.trim().to_lowercase(),
+            ssh_port: tunnel.ssh_port,
+            ssh_username: tunnel.ssh_username.clone(),
+            auth_type: tunnel.auth_type.clone(),
+            ssh_key_path: tunnel.ssh_key_path.clone(),
+            local_port: tunnel.local_port,
+            target,
+            container,
+            port_map,
+        }
+    }
+}
+
+/// 反向转发只支持 standard 模式下的单端口转发，由 SSH 主机监听 target_host:target_port
+fn validate_remote_forward(tunnel: &TunnelConfig, backend: SshBackend) -> Result<()> {
+    let unsupported = if tunnel.mode != "standard" {
+        Some("Docker mode")
+    } else if !tunnel.port_map.is_empty() {
+        Some("Port mapping")
+    } else if tunnel.proxy_protocol.is_some() {
+        Some("PROXY protocol")
+    } else if tunnel.target_resolver_command.is_some() {
+        Some("Target resolver command")
+    } else if tunnel
+        .wol_mac
+        .as_deref()
+        .is_some_and(|mac| !mac.trim().is_empty())
+    {
+        Some("Wake-on-LAN")
+    } else if backend == SshBackend::SystemSsh {
+        Some("The system ssh backend")
+    } else {
+        None
+    };
+    if let Some(feature) = unsupported {
+        return Err(AppError::validation(format!(
+            "{} is not supported for remote (-R) forwarding",
+            feature
+        ))
+        .into());
+    }
+    if tunnel.local_port.is_none() || tunnel.target_host.is_none() || tunnel.target_port.is_none() {
+        return Err(AppError::validation(
+            "Remote forwarding needs the local port to connect to and the remote address to listen on",
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// SOCKS 模式在 local_port 上监听，目标由客户端指定，不能配置与固定目标相关的选项
+fn validate_socks(tunnel: &TunnelConfig) -> Result<()> {
+    let unsupported = if tunnel.direction.as_deref() == Some("remote") {
+        Some("Remote (-R) forwarding")
+    } else if !tunnel.port_map.is_empty() {
+        Some("Port mapping")
+    } else if tunnel.proxy_protocol.is_some() {
+        Some("PROXY protocol")
+    } else if tunnel
+        .wol_mac
+        .as_deref()
+        .is_some_and(|mac| !mac.trim().is_empty())
+    {
+        Some("Wake-on-LAN")
+    } else {
+        None
+    };
+    if let Some(feature) = unsupported {
+        return Err(AppError::validation(format!(
+            "{} is not supported for SOCKS tunnels",
+            feature
+        ))
+        .into());
+    }
+    if tunnel.local_port.is_none() {
+        return Err(AppError::validation("SOCKS tunnels need a local port to listen on").into());
+    }
+    Ok(())
+}
+
 #[derive(Clone)]
 pub struct TunnelService {
     server_manager: ServerManager,
+    settings: Arc<SettingsManager>,
 }
 
 impl TunnelService {
-    pub fn new() -> Self {
-        let server_manager = ServerManager::new();
-        Self { server_manager }
+    pub fn new(settings: Arc<SettingsManager>) -> Self {
+        let server_manager = ServerManager::new(settings.clone());
+        Self {
+            server_manager,
+            settings,
+        }
     }
 
     pub async fn get_tunnels(&self) -> Result<Vec<TunnelConfig>> {
@@ -25,14 +250,425 @@ impl TunnelService {
         Ok(result)
     }
 
-    pub async fn save_tunnel(&self, tunnel: TunnelConfig) -> Result<()> {
+    pub async fn save_tunnel(&self, mut tunnel: TunnelConfig) -> Result<()> {
         debug!("Saving tunnel {} to database", tunnel.id);
+        // 配置校验失败统一标记为 validation 错误
+        let invalid = |e: anyhow::Error| AppError::validation(format!("{:#}", e));
+        validate_port_map(tunnel.local_port, &tunnel.port_map).map_err(invalid)?;
+        RemoteShell::parse(tunnel.remote_shell.as_deref()).map_err(invalid)?;
+        DockerForwardVia::parse(tunnel.docker_forward_via.as_deref()).map_err(invalid)?;
+        let backend = SshBackend::parse(tunnel.backend.as_deref()).map_err(invalid)?;
+        if ForwardDirection::parse(tunnel.direction.as_deref()).map_err(invalid)?
+            == ForwardDirection::Remote
+        {
+            validate_remote_forward(&tunnel, backend)?;
+        }
+        if tunnel.mode == "socks" {
+            validate_socks(&tunnel)?;
+        }
+        tunnel.extra_ssh_options = tunnel
+            .extra_ssh_options
+            .iter()
+            .map(|option| option.trim())
+            .filter(|option| !option.is_empty())
+            .map(str::to_string)
+            .collect();
+        if !tunnel.extra_ssh_options.is_empty() {
+            // russh 不解析 ssh_config 选项，只有系统 ssh 能使用这些参数
+            if backend != SshBackend::SystemSsh {
+                return Err(AppError::validation(
+                    "Extra SSH options are only supported by the system ssh backend",
+                )
+                .into());
+            }
+            for option in &tunnel.extra_ssh_options {
+                system_ssh::validate_extra_option(option).map_err(invalid)?;
+            }
+        }
+        match tunnel.target_unreachable_action.as_deref() {
+            None | Some("mark") | Some("reconnect") => {}
+            Some(other) => {
+                return Err(AppError::validation(format!(
+                    "Invalid target unreachable action '{}', expected mark or reconnect",
+                    other
+                ))
+                .into())
+            }
+        }
+        tunnel.jump_host = tunnel
+            .jump_host
+            .as_deref()
+            .map(str::trim)
+            .filter(|host| !host.is_empty())
+            .map(str::to_string);
+        match tunnel.jump_auth.as_deref() {
+            None | Some("password") | Some("key") | Some("agent") => {}
+            Some(other) => {
+                return Err(AppError::validation(format!(
+                    "Invalid jump host auth '{}', expected password, key or agent",
+                    other
+                ))
+                .into())
+            }
+        }
+        if tunnel.jump_port == Some(0) {
+            return Err(AppError::validation("Jump host port must not be 0").into());
+        }
+        if tunnel.dscp.is_some_and(|dscp| dscp > MAX_DSCP) {
+            return Err(
+                AppError::validation(format!("DSCP must be between 0 and {}", MAX_DSCP)).into(),
+            );
+        }
+        if tunnel
+            .target_check_interval_secs
+            .is_some_and(|secs| secs > 0 && secs < 5)
+        {
+            return Err(
+                AppError::validation("Target check interval must be at least 5 seconds").into(),
+            );
+        }
+        tunnel.source_addr = parse_source_addr(tunnel.source_addr.as_deref())
+            .map_err(invalid)?
+            .map(|ip| ip.to_string());
+        tunnel.target_resolver_command = tunnel
+            .target_resolver_command
+            .as_deref()
+            .map(str::trim)
+            .filter(|command| !command.is_empty())
+            .map(str::to_string);
+        if tunnel.target_resolver_command.is_some() && tunnel.mode != "standard" {
+            return Err(AppError::validation(
+                "Target resolver command is only supported in standard mode",
+            )
+            .into());
+        }
+        if let Some(schedule) = &tunnel.schedule {
+            schedule.validate().map_err(invalid)?;
+        }
+        tunnel.wol_mac = match tunnel.wol_mac.as_deref().map(str::trim) {
+            Some(mac) if !mac.is_empty() => Some(normalize_mac(mac).map_err(invalid)?),
+            _ => None,
+        };
+        if tunnel.auth_type == "key" {
+            if let Some(key_path) = tunnel.ssh_key_path.as_deref() {
+                let key_path = validate_key_path(key_path).map_err(invalid)?;
+                tunnel.ssh_key_path = Some(key_path.display().to_string());
+            }
+        }
         DB::save_tunnel(&tunnel).await?;
         info!("Tunnel {} saved successfully", tunnel.id);
 
         Ok(())
     }
 
+    /// 从 ssh:// URI 生成隧道配置，仅用于前端确认，不写入数据库
+    pub fn create_tunnel_from_uri(&self, uri: &str) -> Result<TunnelConfig> {
+        debug!("Parsing tunnel URI");
+        let tunnel = uri::tunnel_from_uri(uri)?;
+        validate_port_map(tunnel.local_port, &tunnel.port_map)?;
+        Ok(tunnel)
+    }
+
+    /// 将已保存的私钥路径统一迁移为规范化的绝对路径
+    pub async fn migrate_key_paths(&self) -> Result<Vec<KeyPathMigration>> {
+        let mut results = Vec::new();
+        for mut tunnel in DB::load_tunnels().await? {
+            let Some(original) = tunnel.ssh_key_path.clone() else {
+                continue;
+            };
+
+            let mut migration = KeyPathMigration {
+                id: tunnel.id.clone(),
+                name: tunnel.name.clone(),
+                from: original.clone(),
+                to: None,
+                error: None,
+            };
+            match validate_key_path(&original) {
+                Ok(normalized) => {
+                    let normalized = normalized.display().to_string();
+                    if normalized != original {
+                        tunnel.ssh_key_path = Some(normalized.clone());
+                        DB::save_tunnel(&tunnel).await?;
+                        info!("Migrated key path of tunnel {}", tunnel.id);
+                        migration.to = Some(normalized);
+                    }
+                }
+                Err(e) => {
+                    warn!("Key path of tunnel {} is invalid: {:#}", tunnel.id, e);
+                    migration.error = Some(format!("{:#}", e));
+                }
+            }
+            results.push(migration);
+        }
+
+        Ok(results)
+    }
+
+    /// 检查隧道和默认设置中的私钥，报告组或其他用户可读写的文件
+    ///
+    /// 无法解析的路径由 `migrate_key_paths` 报告，这里跳过。
+    pub async fn audit_key_permissions(&self) -> Result<Vec<KeyPermIssue>> {
+        // 规范化后的路径 -> (使用它的隧道, 是否为默认私钥)
+        let mut keys: BTreeMap<std::path::PathBuf, (Vec<String>, bool)> = BTreeMap::new();
+        for tunnel in DB::load_tunnels().await? {
+            if tunnel.auth_type != "key" {
+                continue;
+            }
+            let Some(path) = tunnel.ssh_key_path.as_deref() else {
+                continue;
+            };
+            if let Ok(path) = key_path::normalize_key_path(path) {
+                keys.entry(path).or_default().0.push(tunnel.id);
+            }
+        }
+        if let Some(path) = self.settings.get_settings().default_ssh_key {
+            if let Ok(path) = key_path::normalize_key_path(&path) {
+                keys.entry(path).or_default().1 = true;
+            }
+        }
+
+        let mut issues = Vec::new();
+        for (path, (tunnel_ids, default_key)) in keys {
+            match key_path::loose_permissions(&path) {
+                Ok(Some(mode)) => {
+                    warn!(
+                        "Key file {} has loose permissions {:04o}",
+                        path.display(),
+                        mode
+                    );
+                    issues.push(KeyPermIssue {
+                        path: path.display().to_string(),
+                        mode: format!("{:04o}", mode),
+                        tunnel_ids,
+                        default_key,
+                    });
+                }
+                Ok(None) => {}
+                Err(e) => warn!("Failed to check key permissions: {:#}", e),
+            }
+        }
+        Ok(issues)
+    }
+
+    /// 换用新的私钥：先用新密钥测试登录，成功后才写入配置，失败时保留原密钥
+    pub async fn rotate_tunnel_key(
+        &self,
+        id: String,
+        new_key_path: String,
+        passphrase: Option<String>,
+    ) -> Result<()> {
+        let key_path = validate_key_path(&new_key_path)
+            .map_err(|e| AppError::validation(format!("{:#}", e)))?
+            .display()
+            .to_string();
+
+        let mut candidate = DB::get_tunnel_by_id(&id)
+            .await?
+            .ok_or_else(|| AppError::not_found("Tunnel not found"))?;
+        candidate.auth_type = "key".to_string();
+        candidate.ssh_key_path = Some(key_path.clone());
+        candidate.ssh_password = None;
+        candidate.ssh_key_passphrase = passphrase.filter(|p| !p.is_empty());
+
+        // 测试登录
+        let connect_config = SshConnectConfig::try_from(&candidate)?;
+        let timeout = Duration::from_secs(self.settings.get_settings().connection_timeout as u64);
+        let ssh = tokio::time::timeout(timeout, Ssh::init(connect_config))
+            .await
+            .map_err(|_| anyhow::anyhow!("Connection test timed out after {:?}", timeout))?
+            .context("Connection test with the new key failed")?;
+        ssh.disconnect().await;
+
+        let mut tunnel = DB::load_tunnels()
+            .await?
+            .into_iter()
+            .find(|tunnel| tunnel.id == id)
+            .ok_or_else(|| AppError::not_found("Tunnel not found"))?;
+        tunnel.auth_type = "key".to_string();
+        tunnel.ssh_key_path = Some(key_path);
+        tunnel.ssh_password = None;
+        tunnel.ssh_key_passphrase = candidate.ssh_key_passphrase;
+        DB::save_tunnel(&tunnel).await?;
+        info!("Rotated the SSH key of tunnel {}", id);
+
+        // 运行中的隧道在下次重连时使用新密钥
+        if let Some(model) = DB::get_tunnel_by_id(&id).await? {
+            let _ = self.server_manager.update_tunnel_config(&model).await;
+        }
+        Ok(())
+    }
+
+    /// 把本次会话回退成功的认证方式保存为隧道的认证方式
+    pub async fn apply_auth_fallback(&self, id: String) -> Result<()> {
+        let used = self
+            .server_manager
+            .get_tunnel_metric(&id)
+            .await
+            .auth_fallback
+            .ok_or_else(|| {
+                AppError::validation("The tunnel has not fallen back to another auth method")
+            })?;
+
+        let mut tunnel = DB::load_tunnels()
+            .await?
+            .into_iter()
+            .find(|tunnel| tunnel.id == id)
+            .ok_or_else(|| AppError::not_found("Tunnel not found"))?;
+        tunnel.auth_type = used.kind().to_string();
+        if let TunnelAuth::Key(key_path, _) = &used {
+            // 回退到默认密钥时改用该密钥，原口令不再适用
+            if tunnel.ssh_key_path.as_deref() != Some(key_path.as_str()) {
+                tunnel.ssh_key_path = Some(key_path.clone());
+                tunnel.ssh_key_passphrase = None;
+            }
+        }
+        DB::save_tunnel(&tunnel).await?;
+        info!("Tunnel {} now uses {} auth", id, used.kind());
+
+        if let Some(model) = DB::get_tunnel_by_id(&id).await? {
+            let _ = self.server_manager.update_tunnel_config(&model).await;
+        }
+        Ok(())
+    }
+
+    /// 把同一组修改应用到多个隧道，在一个事务中保存，返回每个隧道的结果
+    pub async fn batch_update(
+        &self,
+        ids: Vec<String>,
+        patch: TunnelPatch,
+    ) -> Result<Vec<BatchUpdateResult>> {
+        let username = patch.ssh_username.as_deref().map(str::trim);
+        if username.is_none() && patch.ssh_port.is_none() && patch.ssh_key_path.is_none() {
+            return Err(AppError::validation("Nothing to update").into());
+        }
+        if username == Some("") {
+            return Err(AppError::validation("SSH username cannot be empty").into());
+        }
+        if patch.ssh_port == Some(0) {
+            return Err(AppError::validation("SSH port must be between 1 and 65535").into());
+        }
+        let key_path = patch
+            .ssh_key_path
+            .as_deref()
+            .map(|path| {
+                validate_key_path(path)
+                    .map(|path| path.display().to_string())
+                    .map_err(|e| AppError::validation(format!("{:#}", e)))
+            })
+            .transpose()?;
+
+        let mut tunnels: HashMap<String, TunnelConfig> = DB::load_tunnels()
+            .await?
+            .into_iter()
+            .map(|tunnel| (tunnel.id.clone(), tunnel))
+            .collect();
+
+        let mut results = Vec::with_capacity(ids.len());
+        let mut updated = Vec::new();
+        for id in ids {
+            let Some(mut tunnel) = tunnels.remove(&id) else {
+                results.push(BatchUpdateResult {
+                    id,
+                    ok: false,
+                    error: Some("Tunnel not found".to_string()),
+                });
+                continue;
+            };
+            if let Some(username) = username {
+                tunnel.ssh_username = username.to_string();
+            }
+            if let Some(port) = patch.ssh_port {
+                tunnel.ssh_port = port;
+            }
+            if let Some(key_path) = &key_path {
+                tunnel.auth_type = "key".to_string();
+                tunnel.ssh_key_path = Some(key_path.clone());
+                tunnel.ssh_password = None;
+                tunnel.ssh_key_passphrase = None;
+            }
+            results.push(BatchUpdateResult {
+                id,
+                ok: true,
+                error: None,
+            });
+            updated.push(tunnel);
+        }
+
+        DB::save_tunnels(&updated).await?;
+        info!("Batch updated {} tunnels", updated.len());
+
+        // 运行中的隧道在下次重连时使用新配置
+        for tunnel in &updated {
+            if let Some(model) = DB::get_tunnel_by_id(&tunnel.id).await? {
+                let _ = self.server_manager.update_tunnel_config(&model).await;
+            }
+        }
+        Ok(results)
+    }
+
+    /// 按连接与转发参数分组，返回包含两个及以上隧道 id 的重复组
+    pub async fn find_duplicate_tunnels(&self) -> Result<Vec<Vec<String>>> {
+        let tunnels = DB::load_tunnels().await?;
+
+        let mut groups: HashMap<TunnelIdentity, Vec<String>> = HashMap::new();
+        for tunnel in &tunnels {
+            groups
+                .entry(TunnelIdentity::from(tunnel))
+                .or_default()
+                .push(tunnel.id.clone());
+        }
+
+        let mut duplicates: Vec<Vec<String>> =
+            groups.into_values().filter(|ids| ids.len() > 1).collect();
+        // 保持与列表一致的顺序，便于前端展示
+        let order: HashMap<&str, usize> = tunnels
+            .iter()
+            .enumerate()
+            .map(|(index, tunnel)| (tunnel.id.as_str(), index))
+            .collect();
+        for ids in &mut duplicates {
+            ids.sort_by_key(|id| order[id.as_str()]);
+        }
+        duplicates.sort_by_key(|ids| order[ids[0].as_str()]);
+
+        Ok(duplicates)
+    }
+
+    /// 逐字段比较两个隧道的配置，忽略 id
+    pub async fn diff_tunnels(&self, id_a: String, id_b: String) -> Result<Vec<FieldDiff>> {
+        let tunnels = DB::load_tunnels().await?;
+        let find = |id: &str| {
+            tunnels
+                .iter()
+                .find(|tunnel| tunnel.id == id)
+                .ok_or_else(|| AppError::not_found(format!("Tunnel {} not found", id)))
+        };
+        let a = serde_json::to_value(find(&id_a)?)?;
+        let b = serde_json::to_value(find(&id_b)?)?;
+        let (Some(a), Some(b)) = (a.as_object(), b.as_object()) else {
+            return Ok(Vec::new());
+        };
+
+        let null = serde_json::Value::Null;
+        let diffs = a
+            .keys()
+            .chain(b.keys().filter(|key| !a.contains_key(*key)))
+            .filter(|field| field.as_str() != "id")
+            .filter_map(|field| {
+                let value_a = a.get(field).unwrap_or(&null);
+                let value_b = b.get(field).unwrap_or(&null);
+                // 比较时按原值，返回时脱敏
+                (value_a != value_b).then(|| FieldDiff {
+                    field: field.clone(),
+                    a: diagnostics::mask_secret(field, value_a),
+                    b: diagnostics::mask_secret(field, value_b),
+                })
+            })
+            .collect();
+        Ok(diffs)
+    }
+
     pub async fn delete_tunnel(&self, id: String) -> Result<()> {
         debug!("Deleting tunnel {}", id);
 
@@ -44,6 +680,17 @@ impl TunnelService {
             debug!("Tunnel {} removed from manager", id);
         }
 
+        self.server_manager.clear_logs(&id);
+        if let Err(e) = DB::delete_metric_history(&id).await {
+            warn!("Failed to delete metrics history of tunnel {}: {:#}", id, e);
+        }
+        if let Err(e) = DB::delete_tunnel_counters(&id).await {
+            warn!(
+                "Failed to delete connection counters of tunnel {}: {:#}",
+                id, e
+            );
+        }
+
         // Delete from database regardless of removal result
         DB::delete_tunnel(&id).await?;
         info!("Tunnel {} deleted from database", id);
@@ -51,41 +698,552 @@ impl TunnelService {
         Ok(())
     }
 
+    /// 调试用：移除隧道的 actor 并报告是否有任务遗留，之后可重新启动
+    pub async fn shutdown_tunnel_debug(&self, id: String) -> Result<ShutdownReport> {
+        debug!("Shutting down tunnel {} for leak check", id);
+        let report = self.server_manager.shutdown_tunnel_debug(&id).await?;
+        self.remember_running(&id, false).await;
+        Ok(report)
+    }
+
     pub async fn start_tunnel(&self, id: String) -> Result<()> {
         debug!("Starting tunnel {}", id);
         let tunnels = DB::get_tunnel_by_id(&id).await?;
         debug!("Loaded tunnel for starting tunnel {}", id);
 
         if tunnels.is_none() {
-            let error_msg = "Tunnel not found".to_string();
             error!("Tunnel {} not found when attempting to start", id);
-            return Err(anyhow::anyhow!(error_msg));
+            return Err(AppError::not_found("Tunnel not found").into());
         }
 
         let tunnel = tunnels.unwrap();
         let result = self.server_manager.start_tunnel(&tunnel).await;
         match &result {
-            Ok(()) => info!("Tunnel {} started successfully", id),
+            Ok(()) => {
+                info!("Tunnel {} started successfully", id);
+                self.remember_running(&id, true).await;
+            }
             Err(e) => error!("Failed to start tunnel {}: {}", id, e),
         }
 
         result
     }
 
+    /// 手动启动：不在时间段内时拒绝，除非指定忽略时间段
+    pub async fn start_tunnel_manually(&self, id: String, override_schedule: bool) -> Result<()> {
+        if !override_schedule {
+            let tunnel = DB::get_tunnel_by_id(&id)
+                .await?
+                .ok_or_else(|| AppError::not_found("Tunnel not found"))?;
+            if let Some(schedule) = schedule_of(&tunnel) {
+                if !schedule.is_open_at(chrono::Utc::now())? {
+                    return Err(AppError::validation(format!(
+                        "Tunnel is outside its schedule ({}), start it with the schedule override to run it anyway",
+                        schedule.describe()
+                    ))
+                    .into());
+                }
+            }
+        }
+        self.start_tunnel(id).await
+    }
+
+    /// 按时间段启停隧道，只在进入或离开时间段时动作，不干预期间的手动操作
+    pub async fn run_schedules(&self) {
+        let mut last_open: HashMap<String, bool> = HashMap::new();
+        loop {
+            // 首次检查也延后，避免与启动时恢复隧道同时启动
+            tokio::time::sleep(schedule::CHECK_INTERVAL).await;
+            let tunnels = match DB::load_tunnels().await {
+                Ok(tunnels) => tunnels,
+                Err(e) => {
+                    warn!("Failed to load tunnels for schedules: {:#}", e);
+                    continue;
+                }
+            };
+
+            let now = chrono::Utc::now();
+            for tunnel in &tunnels {
+                let Some(schedule) = &tunnel.schedule else {
+                    continue;
+                };
+                let open = match schedule.is_open_at(now) {
+                    Ok(open) => open,
+                    Err(e) => {
+                        warn!("Invalid schedule of tunnel {}: {:#}", tunnel.id, e);
+                        continue;
+                    }
+                };
+                if last_open.insert(tunnel.id.clone(), open) == Some(open) {
+                    continue;
+                }
+
+                let running = self.is_running(&tunnel.id).await;
+                let result = if open && !running {
+                    info!("Schedule window of tunnel {} opened, starting", tunnel.id);
+                    self.start_tunnel(tunnel.id.clone()).await
+                } else if !open && running {
+                    info!("Schedule window of tunnel {} closed, stopping", tunnel.id);
+                    self.stop_tunnel(tunnel.id.clone()).await
+                } else {
+                    Ok(())
+                };
+                if let Err(e) = result {
+                    warn!(
+                        "Scheduled start/stop of tunnel {} failed: {:#}",
+                        tunnel.id, e
+                    );
+                }
+            }
+            last_open.retain(|id, _| tunnels.iter().any(|tunnel| &tunnel.id == id));
+        }
+    }
+
+    async fn is_running(&self, id: &String) -> bool {
+        !matches!(
+            self.server_manager.get_tunnel_metric(id).await.tunnel_state,
+            TunnelState::Stopped | TunnelState::Stopping | TunnelState::Error(_)
+        )
+    }
+
+    /// 保存正在运行的隧道的配置，只有连接相关的字段变化时才重启
+    ///
+    /// 名称、自动重连和失败钩子直接应用到运行中的隧道。返回是否发生了重启。
+    pub async fn update_running_tunnel(&self, tunnel: TunnelConfig) -> Result<bool> {
+        let id = tunnel.id.clone();
+        let old = DB::get_tunnel_by_id(&id)
+            .await?
+            .ok_or_else(|| AppError::not_found("Tunnel not found"))?;
+        self.save_tunnel(tunnel).await?;
+        let new = DB::get_tunnel_by_id(&id)
+            .await?
+            .ok_or_else(|| AppError::not_found("Tunnel not found"))?;
+
+        if !self.is_running(&id).await {
+            return Ok(false);
+        }
+
+        // 除可在线修改的字段外完全相同，则无需重启
+        let mut live_only = new.clone();
+        live_only.name = old.name.clone();
+        live_only.auto_reconnect = old.auto_reconnect;
+        live_only.on_failure_command = old.on_failure_command.clone();
+        live_only.check_command = old.check_command.clone();
+        live_only.schedule = old.schedule.clone();
+        if live_only == old {
+            self.server_manager.update_tunnel_config(&new).await?;
+            info!("Tunnel {} updated without a restart", id);
+            return Ok(false);
+        }
+
+        info!("Connection settings of tunnel {} changed, restarting", id);
+        self.stop_tunnel(id.clone()).await?;
+        self.start_tunnel(id).await?;
+        Ok(true)
+    }
+
+    /// 启动上次关闭时仍在运行的隧道，返回成功发起启动的数量
+    pub async fn restore_running_tunnels(&self) -> Result<usize> {
+        let ids = DB::load_was_running_ids().await?;
+        let mut restored = 0;
+        for id in ids {
+            // 已离开时间段的隧道等下次进入时间段再由调度启动
+            let outside_schedule = DB::get_tunnel_by_id(&id)
+                .await?
+                .and_then(|tunnel| schedule_of(&tunnel))
+                .is_some_and(|schedule| !schedule.is_open_at(chrono::Utc::now()).unwrap_or(true));
+            if outside_schedule {
+                info!("Tunnel {} is outside its schedule, not restoring it", id);
+                continue;
+            }
+            match self.start_tunnel(id.clone()).await {
+                Ok(()) => restored += 1,
+                Err(e) => warn!("Failed to restore tunnel {}: {:#}", id, e),
+            }
+        }
+        Ok(restored)
+    }
+
+    async fn remember_running(&self, id: &str, running: bool) {
+        if let Err(e) = DB::set_was_running(id, running).await {
+            warn!("Failed to persist running state of tunnel {}: {:#}", id, e);
+        }
+    }
+
+    /// 处于错误状态的隧道 id
+    pub async fn get_failed_tunnels(&self) -> Vec<String> {
+        self.server_manager.failed_tunnels().await
+    }
+
+    /// 清除错误状态：移除隧道的 actor (连同待执行的自动重连)，隧道回到停止状态
+    pub async fn clear_failed_tunnel(&self, id: String) -> Result<()> {
+        let metric = self.server_manager.get_tunnel_metric(&id).await;
+        if !matches!(metric.tunnel_state, TunnelState::Error(_)) {
+            return Err(AppError::validation("Tunnel is not in an error state").into());
+        }
+        self.server_manager.remove_tunnel(&id).await?;
+        self.remember_running(&id, false).await;
+        info!("Cleared the error state of tunnel {}", id);
+        Ok(())
+    }
+
+    /// 清除所有隧道的错误状态，返回清除的数量
+    pub async fn clear_all_failed(&self) -> Result<usize> {
+        let failed = self.server_manager.failed_tunnels().await;
+        for id in &failed {
+            self.server_manager.remove_tunnel(id).await?;
+            self.remember_running(id, false).await;
+        }
+        if !failed.is_empty() {
+            info!("Cleared the error state of {} tunnel(s)", failed.len());
+        }
+        Ok(failed.len())
+    }
+
+    /// 推送最新的托盘状态
+    pub async fn emit_tray_status(&self, app_handle: &AppHandle) {
+        self.server_manager.emit_tray_status(app_handle).await;
+    }
+
     pub async fn stop_tunnel(&self, id: String) -> Result<()> {
         debug!("Stopping tunnel {}", id);
         println!("Stopping tunnel {}", id);
         match self.server_manager.stop_tunnel(&id).await {
-            Ok(_) => self.server_manager.remove_tunnel(&id).await,
+            Ok(_) => {
+                self.remember_running(&id, false).await;
+                self.server_manager.remove_tunnel(&id).await
+            }
             Err(e) => Err(e),
         }
     }
 
+    pub async fn to_ssh_command(&self, id: String) -> Result<String> {
+        let tunnel = DB::get_tunnel_by_id(&id)
+            .await?
+            .ok_or_else(|| AppError::not_found("Tunnel not found"))?;
+        ssh_command::to_ssh_command(&tunnel)
+    }
+
     pub async fn get_tunnel_health_status(&self, id: String) -> Result<TunnelMetric> {
         let tunnel_metric = self.server_manager.get_tunnel_metric(&id).await;
         Ok(tunnel_metric)
     }
 
+    /// 所有已加载隧道的指标，一次取完，供前端批量刷新状态
+    pub async fn get_all_tunnel_health_status(&self) -> HashMap<String, TunnelMetric> {
+        self.server_manager.get_all_tunnel_metrics().await
+    }
+
+    /// 并发测试所有隧道的 SSH 连接与认证，不建立转发
+    pub async fn test_all_tunnels(&self) -> Result<Vec<ConnectionTest>> {
+        let timeout = Duration::from_secs(self.settings.get_settings().connection_timeout as u64);
+        let mut tasks = tokio::task::JoinSet::new();
+        for (index, tunnel) in DB::load_tunnels().await?.iter().enumerate() {
+            let Some(model) = DB::get_tunnel_by_id(&tunnel.id).await? else {
+                continue;
+            };
+            tasks
+                .spawn(async move { (index, diagnostics::test_connection(&model, timeout).await) });
+        }
+
+        let mut results = tasks.join_all().await;
+        results.sort_by_key(|(index, _)| *index);
+        Ok(results.into_iter().map(|(_, result)| result).collect())
+    }
+
+    /// 计算隧道启动时实际使用的实现、SSH 地址和转发目标，不绑定本地端口
+    pub async fn preview_tunnel(&self, id: String) -> Result<ForwardPreview> {
+        let model = DB::get_tunnel_by_id(&id)
+            .await?
+            .ok_or_else(|| AppError::not_found(format!("Tunnel {} not found", id)))?;
+        preview::preview(&model).await
+    }
+
+    /// 用隧道的连接配置对比经隧道和直连到目标的延迟，并测量 SSH 会话吞吐
+    ///
+    /// io_buffer_size 覆盖隧道保存的读缓冲区大小，便于在保存前比较吞吐。
+    pub async fn measure_tunnel_overhead(
+        &self,
+        id: String,
+        samples: Option<u32>,
+        io_buffer_size: Option<u32>,
+    ) -> Result<OverheadReport> {
+        let mut model = DB::get_tunnel_by_id(&id)
+            .await?
+            .ok_or_else(|| AppError::not_found(format!("Tunnel {} not found", id)))?;
+        if io_buffer_size.is_some() {
+            model.io_buffer_size = io_buffer_size;
+        }
+        overhead::measure_overhead(&model, samples.unwrap_or(overhead::DEFAULT_SAMPLES)).await
+    }
+
+    /// 用隧道的连接配置执行一次 check_command，不启动隧道，结束后断开会话
+    pub async fn run_check_command(
+        &self,
+        id: String,
+        timeout_secs: Option<u64>,
+    ) -> Result<ExecOutput> {
+        let model = DB::get_tunnel_by_id(&id)
+            .await?
+            .ok_or_else(|| AppError::not_found(format!("Tunnel {} not found", id)))?;
+        let timeout_secs = timeout_secs.unwrap_or(diagnostics::DEFAULT_CHECK_TIMEOUT_SECS);
+        if !(1..=diagnostics::MAX_CHECK_TIMEOUT_SECS).contains(&timeout_secs) {
+            return Err(AppError::validation(format!(
+                "Timeout must be between 1 and {} seconds",
+                diagnostics::MAX_CHECK_TIMEOUT_SECS
+            ))
+            .into());
+        }
+        diagnostics::run_check_command(&model, Duration::from_secs(timeout_secs)).await
+    }
+
+    /// 生成脱敏后的诊断包 (JSON)，返回写入的字节数
+    pub async fn create_diagnostic_bundle(
+        &self,
+        dest_path: String,
+        app_version: String,
+    ) -> Result<u64> {
+        const LOG_LINES_PER_TUNNEL: usize = 200;
+        const EVENT_LIMIT: u64 = 500;
+
+        let tunnels = DB::load_tunnels().await?;
+        // 日志里出现的密码和私钥口令一并替换
+        let secrets: Vec<String> = tunnels
+            .iter()
+            .flat_map(|tunnel| [&tunnel.ssh_password, &tunnel.ssh_key_passphrase])
+            .filter_map(|secret| secret.clone())
+            .collect();
+        let logs = tunnels
+            .iter()
+            .map(|tunnel| {
+                let lines = self
+                    .get_recent_logs(Some(tunnel.id.clone()), LOG_LINES_PER_TUNNEL)
+                    .into_iter()
+                    .map(|mut log| {
+                        log.line = diagnostics::scrub_line(&log.line, &secrets);
+                        log
+                    })
+                    .collect();
+                (tunnel.id.clone(), lines)
+            })
+            .collect();
+        let connection_tests = self.test_all_tunnels().await?;
+
+        let bundle = DiagnosticBundle {
+            app_version,
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            created_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            settings: diagnostics::redact_settings(self.settings.get_settings()),
+            tunnels: tunnels
+                .into_iter()
+                .map(diagnostics::redact_tunnel)
+                .collect::<serde_json::Result<_>>()?,
+            events: DB::load_recent_events(EVENT_LIMIT).await?,
+            logs,
+            connection_tests,
+            dns: dns::stats(),
+        };
+
+        let json = serde_json::to_vec_pretty(&bundle)?;
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&dest_path)
+            .with_context(|| format!("Failed to create {}", dest_path))?;
+        file.write_all(&json)?;
+
+        info!("Wrote diagnostic bundle to {}", dest_path);
+        Ok(json.len() as u64)
+    }
+
+    /// 把所有隧道当前的状态、流量、延迟、连接数和运行时长写入 JSON 文件，返回写入的字节数
+    pub async fn snapshot_metrics(&self, dest_path: String, app_version: String) -> Result<u64> {
+        let tunnels = DB::load_tunnels().await?;
+        let metrics = self.server_manager.get_all_tunnel_metrics().await;
+        let snapshot = MetricsSnapshot {
+            app_version,
+            created_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            tunnels: tunnels
+                .iter()
+                .map(|tunnel| {
+                    let metric = metrics.get(&tunnel.id).cloned().unwrap_or_default();
+                    TunnelSnapshot::new(tunnel, &metric)
+                })
+                .collect(),
+        };
+
+        let json = serde_json::to_vec_pretty(&snapshot)?;
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&dest_path)
+            .with_context(|| format!("Failed to create {}", dest_path))?;
+        file.write_all(&json)?;
+
+        info!(
+            "Wrote metrics snapshot of {} tunnel(s) to {}",
+            snapshot.tunnels.len(),
+            dest_path
+        );
+        Ok(json.len() as u64)
+    }
+
+    /// 累计接受的连接数和失败数，停止或重启后继续累加
+    pub async fn get_tunnel_counters(&self, id: String) -> Result<TunnelCounters> {
+        self.server_manager.get_tunnel_counters(&id).await
+    }
+
+    /// 运行中隧道实际监听的本地地址，包含自动换端口后的结果
+    pub async fn get_active_bindings(&self) -> Vec<(String, SocketAddr)> {
+        self.server_manager.get_active_bindings().await
+    }
+
+    /// 暂停累计流量统计并开始新的测量窗口，转发不受影响
+    pub async fn pause_stats(&self, id: String) -> Result<()> {
+        self.server_manager
+            .pause_stats(&id)
+            .await
+            .map_err(|_| AppError::not_found("Tunnel is not running").into())
+    }
+
+    /// 恢复累计流量统计，测量窗口的结果保留到下次暂停
+    pub async fn resume_stats(&self, id: String) -> Result<()> {
+        self.server_manager
+            .resume_stats(&id)
+            .await
+            .map_err(|_| AppError::not_found("Tunnel is not running").into())
+    }
+
+    /// 健康检查缓存的会话 RTT，不会额外建立连接
+    pub async fn get_tunnel_latency(&self, id: String) -> Option<Duration> {
+        self.server_manager
+            .get_tunnel_metric(&id)
+            .await
+            .last_latency
+    }
+
+    /// 有活动连接且最近仍有流量的隧道，退出前据此请求确认
+    pub async fn busy_tunnels(&self) -> Vec<BusyTunnel> {
+        let busy = self.server_manager.busy_tunnels(BUSY_TRAFFIC_WINDOW).await;
+        if busy.is_empty() {
+            return Vec::new();
+        }
+        let names: HashMap<String, String> = DB::load_tunnels()
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|tunnel| (tunnel.id, tunnel.name))
+            .collect();
+        busy.into_iter()
+            .map(|(id, active_connections)| BusyTunnel {
+                name: names.get(&id).cloned().unwrap_or_else(|| id.clone()),
+                id,
+                active_connections,
+            })
+            .collect()
+    }
+
+    /// 所有隧道的最近事件，按时间倒序
+    pub async fn get_activity_feed(&self, limit: u64) -> Result<Vec<ActivityEntry>> {
+        let names: HashMap<String, String> = DB::load_tunnels()
+            .await?
+            .into_iter()
+            .map(|tunnel| (tunnel.id, tunnel.name))
+            .collect();
+        let events = DB::load_recent_events(limit).await?;
+
+        Ok(events
+            .into_iter()
+            .map(|event| {
+                let tunnel_name = names.get(&event.tunnel_id).cloned();
+                let subject = tunnel_name
+                    .as_deref()
+                    .map(|name| format!("'{}'", name))
+                    .unwrap_or_else(|| "A deleted tunnel".to_string());
+                let detail = event.detail.as_deref();
+                let description = match (event.kind.as_str(), detail) {
+                    ("started", Some(addr)) => format!("{} started on {}", subject, addr),
+                    ("started", None) => format!("{} started", subject),
+                    ("stopped", _) => format!("{} stopped", subject),
+                    ("failed", Some(reason)) => format!("{} failed: {}", subject, reason),
+                    ("failed", None) => format!("{} failed", subject),
+                    ("reconnected", Some(attempts)) => {
+                        format!("{} reconnected after {} attempt(s)", subject, attempts)
+                    }
+                    ("reconnected", None) => format!("{} reconnected", subject),
+                    ("high_traffic", Some(bytes)) => {
+                        let mb = bytes.parse::<u64>().unwrap_or_default() / (1024 * 1024);
+                        format!("{} transferred {} MB in one minute", subject, mb)
+                    }
+                    ("unexpected_source", Some(detail)) => format!("{}: {}", subject, detail),
+                    ("auth_fallback", Some(method)) => format!(
+                        "{} authenticated with the saved {} after the configured auth was rejected",
+                        subject, method
+                    ),
+                    // 主机级事件，detail 已包含完整描述
+                    ("host_key_changed", Some(detail)) => detail.to_string(),
+                    (kind, _) => format!("{}: {}", subject, kind),
+                };
+                ActivityEntry {
+                    id: event.id,
+                    tunnel_id: event.tunnel_id,
+                    tunnel_name,
+                    kind: event.kind,
+                    description,
+                    timestamp: event.timestamp,
+                }
+            })
+            .collect())
+    }
+
+    pub fn get_recent_logs(&self, id: Option<String>, limit: usize) -> Vec<LogPayload> {
+        self.server_manager.get_recent_logs(id.as_deref(), limit)
+    }
+
+    /// 导出流量历史为 CSV，`id` 为空时导出所有隧道，返回写入的行数
+    pub async fn export_metrics_csv(&self, id: Option<String>, dest_path: String) -> Result<usize> {
+        let samples = DB::load_metric_history(id.as_deref()).await?;
+        if let Some(id) = &id {
+            if samples.is_empty() && DB::get_tunnel_by_id(id).await?.is_none() {
+                return Err(AppError::not_found("Tunnel not found").into());
+            }
+        }
+
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&dest_path)
+            .with_context(|| format!("Failed to create {}", dest_path))?;
+        let mut writer = std::io::BufWriter::new(file);
+
+        writeln!(writer, "tunnel_id,timestamp,tx_bytes,rx_bytes,latency_ms")?;
+        for sample in &samples {
+            let timestamp = chrono::DateTime::from_timestamp_millis(sample.timestamp as i64)
+                .map(|time| time.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+                .unwrap_or_default();
+            let latency = sample
+                .latency_ms
+                .map(|ms| ms.to_string())
+                .unwrap_or_default();
+            writeln!(
+                writer,
+                "{},{},{},{},{}",
+                sample.tunnel_id, timestamp, sample.tx_bytes, sample.rx_bytes, latency
+            )?;
+        }
+        writer.flush()?;
+
+        info!("Exported {} metric samples to {}", samples.len(), dest_path);
+        Ok(samples.len())
+    }
+
+    /// 设置变更后同步 WebSocket 指标服务的状态
+    pub async fn apply_metrics_server(&self) -> Result<()> {
+        let settings = self.settings.get_settings();
+        self.server_manager.apply_metrics_server(&settings).await
+    }
+
     pub async fn monitor_health_status(&self, app_handle: &AppHandle) -> Result<()> {
         self.server_manager.monitor_tunnels_status(app_handle).await
     }
@@ -0,0 +1,226 @@
+use crate::database::models::TunnelConfig;
+use anyhow::{anyhow, Context, Result};
+
+/// 解析 `ssh://user@host:port?L=localport:targethost:targetport` 形式的 URI
+///
+/// 返回的配置尚未保存，由前端确认后再调用 `save_tunnel`。
+/// `D=localport` 代替 `L=` 创建 SOCKS 隧道。可选参数：`i=` 私钥路径，`name=` 隧道名称。
+pub fn tunnel_from_uri(uri: &str) -> Result<TunnelConfig> {
+    let rest = uri
+        .trim()
+        .strip_prefix("ssh://")
+        .ok_or_else(|| anyhow!("URI must start with ssh://"))?;
+
+    let (authority, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let authority = authority.trim_end_matches('/');
+
+    let (user, host_port) = authority
+        .rsplit_once('@')
+        .ok_or_else(|| anyhow!("URI is missing the user, expected ssh://user@host"))?;
+    let user = percent_decode(user)?;
+    if user.is_empty() {
+        return Err(anyhow!("URI has an empty user"));
+    }
+    let (ssh_host, ssh_port) = split_host_port(host_port, Some(22))?;
+
+    let mut forward = None;
+    let mut key_path = None;
+    let mut name = None;
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = percent_decode(value)?;
+        match key {
+            "L" | "D" if forward.is_some() => {
+                return Err(anyhow!("Only one forward (L= or D=) is supported per URI"));
+            }
+            "L" => forward = Some(UriForward::Local(parse_local_forward(&value)?)),
+            "D" => forward = Some(UriForward::Dynamic(parse_port(&value)?)),
+            "i" => key_path = Some(value),
+            "name" => name = Some(value),
+            other => return Err(anyhow!("Unknown URI parameter: {}", other)),
+        }
+    }
+
+    let forward = forward
+        .ok_or_else(|| anyhow!("URI is missing a forward, e.g. ?L=8080:localhost:80 or ?D=1080"))?;
+    let (mode, local_port, target_host, target_port, default_name) = match forward {
+        UriForward::Local((local_port, target_host, target_port)) => {
+            let default_name = format!("{}:{} via {}", target_host, target_port, ssh_host);
+            (
+                "standard",
+                local_port,
+                Some(target_host),
+                Some(target_port),
+                default_name,
+            )
+        }
+        // SOCKS 隧道的目标由客户端按连接指定
+        UriForward::Dynamic(local_port) => {
+            let default_name = format!("SOCKS via {}", ssh_host);
+            ("socks", local_port, None, None, default_name)
+        }
+    };
+
+    Ok(TunnelConfig {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: name.unwrap_or(default_name),
+        mode: mode.to_string(),
+        direction: None,
+        ssh_host,
+        ssh_port,
+        ssh_username: user,
+        // 未指定私钥时使用密码认证，由用户在确认时补全
+        auth_type: if key_path.is_some() {
+            "key"
+        } else {
+            "password"
+        }
+        .to_string(),
+        ssh_password: None,
+        ssh_key_path: key_path,
+        ssh_key_passphrase: None,
+        identities_only: true,
+        source_addr: None,
+        bypass_proxy: false,
+        auth_fallback: false,
+        jump_host: None,
+        jump_port: None,
+        jump_user: None,
+        jump_auth: None,
+        local_port: Some(local_port),
+        auto_remap_port: false,
+        target_host,
+        target_port,
+        target_resolver_command: None,
+        proxy_protocol: None,
+        port_map: Vec::new(),
+        io_buffer_size: None,
+        keepalive_idle_secs: None,
+        fail_fast: false,
+        target_check_interval_secs: None,
+        target_unreachable_action: None,
+        max_connections: None,
+        queue_timeout_secs: None,
+        pause_when_locked: false,
+        dscp: None,
+        container_name: None,
+        container_port: None,
+        container_runtime: None,
+        docker_forward_via: None,
+        wait_for_healthy: false,
+        remote_shell: None,
+        control_path: None,
+        backend: None,
+        extra_ssh_options: Vec::new(),
+        auto_reconnect: None,
+        on_failure_command: None,
+        check_command: None,
+        wol_mac: None,
+        schedule: None,
+    })
+}
+
+enum UriForward {
+    /// `L=`：本地端口、目标主机、目标端口
+    Local((u16, String, u16)),
+    /// `D=`：SOCKS 监听的本地端口
+    Dynamic(u16),
+}
+
+/// `localport:targethost:targetport`，IPv6 目标需用方括号包裹
+fn parse_local_forward(value: &str) -> Result<(u16, String, u16)> {
+    let (local_port, target) = value
+        .split_once(':')
+        .ok_or_else(|| anyhow!("Invalid forward '{}', expected localport:host:port", value))?;
+    let local_port = parse_port(local_port)?;
+    let (target_host, target_port) = split_host_port(target, None)
+        .with_context(|| format!("Invalid forward target '{}'", target))?;
+    Ok((local_port, target_host, target_port))
+}
+
+fn split_host_port(value: &str, default_port: Option<u16>) -> Result<(String, u16)> {
+    let (host, port) = if let Some(rest) = value.strip_prefix('[') {
+        let (host, rest) = rest
+            .split_once(']')
+            .ok_or_else(|| anyhow!("Unclosed '[' in host '{}'", value))?;
+        (host, rest.strip_prefix(':'))
+    } else {
+        match value.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (value, None),
+        }
+    };
+
+    if host.is_empty() {
+        return Err(anyhow!("Host is empty"));
+    }
+    let port = match (port, default_port) {
+        (Some(port), _) => parse_port(port)?,
+        (None, Some(port)) => port,
+        (None, None) => return Err(anyhow!("Port is missing in '{}'", value)),
+    };
+    Ok((host.to_string(), port))
+}
+
+fn parse_port(value: &str) -> Result<u16> {
+    match value.parse::<u16>() {
+        Ok(port) if port > 0 => Ok(port),
+        _ => Err(anyhow!("Invalid port '{}'", value)),
+    }
+}
+
+fn percent_decode(value: &str) -> Result<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = value
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| anyhow!("Invalid percent-encoding in '{}'", value))?;
+            decoded.push(hex);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).context("URI is not valid UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_forward() {
+        let tunnel = tunnel_from_uri("ssh://alice@bastion:2222?L=8080:[::1]:80").unwrap();
+        assert_eq!(tunnel.mode, "standard");
+        assert_eq!(
+            (tunnel.ssh_host.as_str(), tunnel.ssh_port),
+            ("bastion", 2222)
+        );
+        assert_eq!(tunnel.local_port, Some(8080));
+        assert_eq!(tunnel.target_host.as_deref(), Some("::1"));
+        assert_eq!(tunnel.target_port, Some(80));
+    }
+
+    #[test]
+    fn dynamic_forward_creates_socks_tunnel() {
+        let tunnel = tunnel_from_uri("ssh://alice@bastion?D=1080&i=~/.ssh/id_ed25519").unwrap();
+        assert_eq!(tunnel.mode, "socks");
+        assert_eq!(tunnel.local_port, Some(1080));
+        assert_eq!(tunnel.target_host, None);
+        assert_eq!(tunnel.target_port, None);
+        assert_eq!(tunnel.auth_type, "key");
+        assert_eq!(tunnel.name, "SOCKS via bastion");
+    }
+
+    #[test]
+    fn rejects_more_than_one_forward() {
+        assert!(tunnel_from_uri("ssh://alice@bastion?D=1080&L=8080:db:5432").is_err());
+        assert!(tunnel_from_uri("ssh://alice@bastion?D=0").is_err());
+        assert!(tunnel_from_uri("ssh://alice@bastion").is_err());
+    }
+}
@@ -0,0 +1,82 @@
+use crate::database::DB;
+use crate::error::AppError;
+use crate::server::model::unix_millis;
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, Local, NaiveTime, TimeZone};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// 一段时间内所有隧道的流量，`since`/`until` 为 unix 毫秒
+///
+/// 基于每分钟的流量采样，只能统计到历史数据保留期 (30 天) 以内。
+#[derive(Debug, Serialize)]
+pub struct UsageSummary {
+    pub since: u64,
+    pub until: u64,
+    pub tx_bytes: u64,
+    pub rx_bytes: u64,
+    /// 按总流量从大到小排序
+    pub tunnels: Vec<TunnelUsage>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TunnelUsage {
+    pub tunnel_id: String,
+    /// 隧道已删除时为 None
+    pub tunnel_name: Option<String>,
+    pub tx_bytes: u64,
+    pub rx_bytes: u64,
+}
+
+/// 预设的时间范围起点 (本地时间)："today" | "week" (周一起) | "month"
+pub fn preset_since(preset: &str, now: DateTime<Local>) -> Result<u64> {
+    let today = now.date_naive();
+    let start = match preset {
+        "today" => today,
+        "week" => today - ChronoDuration::days(today.weekday().num_days_from_monday() as i64),
+        "month" => today.with_day(1).unwrap_or(today),
+        other => {
+            return Err(AppError::validation(format!("Unknown usage period: {}", other)).into())
+        }
+    };
+    // 夏令时切换导致午夜不存在时取最早的有效时间
+    let start = Local
+        .from_local_datetime(&start.and_time(NaiveTime::MIN))
+        .earliest()
+        .unwrap_or(now);
+    Ok(start.timestamp_millis().max(0) as u64)
+}
+
+/// 汇总 `[since, until)` 内的流量，`until` 默认为当前时间
+pub async fn usage_summary(since: u64, until: Option<u64>) -> Result<UsageSummary> {
+    let until = until.unwrap_or_else(unix_millis);
+    if since >= until {
+        return Err(AppError::validation("Usage period start must be before its end").into());
+    }
+
+    let names: HashMap<String, String> = DB::load_tunnels()
+        .await?
+        .into_iter()
+        .map(|tunnel| (tunnel.id, tunnel.name))
+        .collect();
+
+    let mut tunnels: Vec<TunnelUsage> = DB::sum_traffic(since, until)
+        .await?
+        .into_iter()
+        .map(|total| TunnelUsage {
+            tunnel_name: names.get(&total.tunnel_id).cloned(),
+            tunnel_id: total.tunnel_id,
+            tx_bytes: total.tx_bytes,
+            rx_bytes: total.rx_bytes,
+        })
+        .collect();
+    tunnels.sort_by_key(|usage| std::cmp::Reverse(usage.tx_bytes.saturating_add(usage.rx_bytes)));
+
+    Ok(UsageSummary {
+        since,
+        until,
+        tx_bytes: tunnels.iter().map(|usage| usage.tx_bytes).sum(),
+        rx_bytes: tunnels.iter().map(|usage| usage.rx_bytes).sum(),
+        tunnels,
+    })
+}
@@ -1,7 +1,7 @@
 pub use crate::database::models::AppSettings;
 use crate::database::DB;
 use anyhow::Result;
-use log::debug;
+use log::{debug, error, warn};
 use std::sync::Mutex;
 
 pub struct SettingsManager {
@@ -10,12 +10,36 @@ pub struct SettingsManager {
 
 impl SettingsManager {
     pub async fn new() -> Self {
-        let initial_settings = DB::load_settings().await.unwrap();
+        let initial_settings = Self::load_or_heal().await.unwrap_or_else(|e| {
+            error!("Failed to load settings, using defaults: {:#}", e);
+            None
+        });
         Self {
             settings: Mutex::new(initial_settings.unwrap_or_else(AppSettings::default)),
         }
     }
 
+    /// 读取失败时先修复设置行再读取一次，避免一列损坏就丢掉全部设置
+    async fn load_or_heal() -> Result<Option<AppSettings>> {
+        match DB::load_settings().await {
+            Ok(settings) => Ok(settings),
+            Err(e) => {
+                warn!("Failed to load settings, repairing the row: {:#}", e);
+                DB::heal_settings().await?;
+                DB::load_settings().await
+            }
+        }
+    }
+
+    /// 修复设置行并重新加载，返回修复的列名
+    pub async fn repair(&self) -> Result<Vec<String>> {
+        let repaired = DB::heal_settings().await?;
+        if let Some(settings) = DB::load_settings().await? {
+            *self.settings.lock().unwrap() = settings;
+        }
+        Ok(repaired)
+    }
+
     pub fn get_settings(&self) -> AppSettings {
         self.settings.lock().unwrap().clone()
     }
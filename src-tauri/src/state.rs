@@ -4,11 +4,11 @@ use std::sync::Arc;
 
 pub struct AppState {
     pub tunnel_service: Arc<TunnelService>,
-    pub settings: SettingsManager,
+    pub settings: Arc<SettingsManager>,
 }
 
 impl AppState {
-    pub fn new(tunnel_service: TunnelService, settings: SettingsManager) -> Self {
+    pub fn new(tunnel_service: TunnelService, settings: Arc<SettingsManager>) -> Self {
         let tunnel_service = Arc::new(tunnel_service);
         Self {
             tunnel_service,
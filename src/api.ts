@@ -1,26 +1,139 @@
 import { invoke } from "@tauri-apps/api/core";
 
+export type ErrorCode = "auth" | "network" | "not_found" | "validation" | "database" | "internal";
+
+// Shape of every error rejected by a backend command
+export interface CommandError {
+  code: ErrorCode;
+  message: string;
+  detail: string | null;
+}
+
+export function isCommandError(e: unknown): e is CommandError {
+  return typeof e === "object" && e !== null && "code" in e && "message" in e;
+}
+
+/** Human readable message for anything thrown by `invoke`. */
+export function errorMessage(e: unknown): string {
+  if (isCommandError(e)) return e.message;
+  return e instanceof Error ? e.message : String(e);
+}
+
+export interface PortMapping {
+  local_start: number;
+  local_end: number;
+  remote_base: number;
+}
+
 export interface TunnelConfig {
   id: string;
   name: string;
-  mode: "standard" | "docker";
+  // "socks" is ssh -D: local_port is a SOCKS5 proxy and target_host/target_port are ignored
+  mode: "standard" | "docker" | "socks";
 
   // SSH
   ssh_host: string;
   ssh_port: number;
   ssh_username: string;
-  auth_type: "password" | "key";
+  // "agent" signs with the identities in ssh-agent (SSH_AUTH_SOCK), macOS and Linux only
+  auth_type: "password" | "key" | "agent";
   ssh_password?: string;
   ssh_key_path: string | null;
+  // Passphrase of an encrypted private key
+  ssh_key_passphrase?: string | null;
+  identities_only?: boolean;
+  // Local IP the outgoing SSH connection binds to (e.g. a VPN interface address)
+  source_addr?: string | null;
+  // Connect directly even when a system proxy (HTTPS_PROXY, ALL_PROXY or OS settings) is configured
+  bypass_proxy?: boolean;
+  // When the configured auth method is rejected, also try the other saved credential
+  // (or the first default key); the card then offers to save the method that worked
+  auth_fallback?: boolean;
+  // Bastion the SSH connection goes through, like ssh -J (one hop only). The port defaults to
+  // 22 and the user to ssh_username
+  jump_host?: string | null;
+  jump_port?: number | null;
+  jump_user?: string | null;
+  // Auth method for the bastion, null uses auth_type; it uses the tunnel's saved credentials
+  jump_auth?: "password" | "key" | "agent" | null;
 
   // Forwarding
+  // "remote" is ssh -R: the SSH host listens on target_host:target_port and connections
+  // come back to 127.0.0.1:local_port. Standard mode only, without port_map or proxy_protocol.
+  direction?: "local" | "remote" | null;
   local_port: number | null;
+  // Use the next free port when local_port is taken
+  auto_remap_port?: boolean;
   target_host: string | null;
   target_port: number | null;
+  // Standard mode: remote command printing "host:port", re-run on every (re)connect
+  target_resolver_command?: string | null;
+  proxy_protocol?: "v1" | "v2" | null;
+  // Extra local port ranges forwarded to consecutive remote ports
+  port_map?: PortMapping[];
+  // Read buffer size in bytes per forwarded connection, defaults to 64KB
+  io_buffer_size?: number | null;
+  // Idle seconds before keepalive probes on a forwarded connection, null disables
+  keepalive_idle_secs?: number | null;
+  // Fail the start when the target can't be reached through the SSH host, instead of
+  // only failing per connection later. Leave off for targets that start on demand.
+  fail_fast?: boolean;
+  // Probe the target through the SSH host every N seconds while running (null = off). When it is
+  // unreachable the tunnel shows "unstable", or reconnects (re-resolving docker IPs) with "reconnect"
+  target_check_interval_secs?: number | null;
+  target_unreachable_action?: "mark" | "reconnect" | null;
+  // Concurrent forwarded connections allowed; extra ones wait up to queue_timeout_secs (default 10)
+  max_connections?: number | null;
+  queue_timeout_secs?: number | null;
+  // Refuse new connections while the screen is locked; the SSH session and open connections stay up
+  pause_when_locked?: boolean;
+  // DSCP code point (0-63, e.g. 46 = EF, 8 = CS1) for QoS-aware routers; ignored where the OS refuses it
+  dscp?: number | null;
 
   // Docker
   container_name: string | null;
   container_port: number | null;
+  container_runtime?: "docker" | "podman" | "auto" | null;
+  // Docker: forward to the container IP (default, needs the server to reach the container network)
+  // or to the port the container publishes on the server's host
+  docker_forward_via?: "container_ip" | "published_port" | null;
+  // Docker: wait until the container healthcheck reports healthy before forwarding
+  wait_for_healthy?: boolean;
+  // How remote commands run: null uses the login shell, "none" runs without shell/sudo, or e.g. "sh -c"
+  remote_shell?: string | null;
+
+  // Multiplexing
+  control_path?: string | null;
+  // "auto" is the same as "russh", which ignores ~/.ssh/config. "system_ssh" is opt-in and runs the
+  // ssh binary so ProxyJump/ProxyCommand apply; it needs key or agent auth and a plain local or
+  // SOCKS forward (no Docker, resolver, WoL, PROXY protocol, port map or remote forwarding).
+  backend?: "auto" | "russh" | "system_ssh" | null;
+  // "Key=Value" options passed to the system ssh as -o after the built-in ones, system_ssh only
+  extra_ssh_options?: string[];
+
+  // Reconnect: overrides the global auto_reconnect setting when set
+  auto_reconnect?: boolean | null;
+  // Runs an arbitrary LOCAL shell command when the tunnel fails (at most once a minute).
+  // Receives CICONIA_TUNNEL_ID / CICONIA_TUNNEL_NAME / CICONIA_FAILURE_REASON in the environment.
+  on_failure_command?: string | null;
+  // Command run on the SSH host by runCheckCommand, e.g. "systemctl is-active nginx"
+  check_command?: string | null;
+  // Wake-on-LAN MAC sent from the SSH host before forwarding, e.g. "aa:bb:cc:dd:ee:ff"
+  wol_mac?: string | null;
+
+  // Only allowed to run inside this window; started when it opens and stopped when it closes
+  schedule?: TunnelSchedule | null;
+}
+
+export interface TunnelSchedule {
+  // "mon".."sun", empty means every day
+  days: string[];
+  // "HH:MM"; an end before the start runs past midnight
+  start: string;
+  end: string;
+  // "local" (default), "UTC" or a fixed offset like "+08:00". Only "local" follows daylight
+  // saving time; zone names like "Europe/Berlin" are rejected.
+  timezone?: string | null;
 }
 
 export interface DockerContainer {
@@ -39,10 +152,21 @@ export interface SshParams {
   host: string;
   port: number;
   username: string;
-  auth_type: "password" | "key";
+  auth_type: "password" | "key" | "agent";
   private_key_path: string | null;
+  key_passphrase?: string | null;
   password?: string;
   keyword?: string;
+  container_runtime?: "docker" | "podman" | "auto";
+  identities_only?: boolean;
+  remote_shell?: string | null;
+  source_addr?: string | null;
+  bypass_proxy?: boolean;
+  // Bastion, same meaning as in TunnelConfig; it reuses the credentials above
+  jump_host?: string | null;
+  jump_port?: number | null;
+  jump_user?: string | null;
+  jump_auth?: "password" | "key" | "agent" | null;
 }
 
 export async function getTunnels(): Promise<TunnelConfig[]> {
@@ -53,32 +177,276 @@ export async function saveTunnel(tunnel: TunnelConfig): Promise<void> {
   return invoke("save_tunnel", { tunnel });
 }
 
+/**
+ * Saves a running tunnel. Name, auto-reconnect and the failure hook apply live;
+ * resolves to true when connection settings changed and the tunnel was restarted.
+ */
+export async function updateRunningTunnel(tunnel: TunnelConfig): Promise<boolean> {
+  return invoke("update_running_tunnel", { tunnel });
+}
+
+/** Parses an `ssh://user@host:port?L=local:host:port` (or `?D=port` for SOCKS) URI into an unsaved tunnel. */
+export async function createTunnelFromUri(uri: string): Promise<TunnelConfig> {
+  return invoke("create_tunnel_from_uri", { uri });
+}
+
+export interface KeyPathMigration {
+  id: string;
+  name: string;
+  from: string;
+  /** New absolute path, null when unchanged or invalid. */
+  to: string | null;
+  error: string | null;
+}
+
+/** Rewrites saved key paths to validated absolute paths. */
+export async function migrateKeyPaths(): Promise<KeyPathMigration[]> {
+  return invoke("migrate_key_paths");
+}
+
+export interface KeyPermIssue {
+  path: string;
+  /** Octal mode such as "0644". */
+  mode: string;
+  tunnel_ids: string[];
+  /** Whether this is the default key from settings. */
+  default_key: boolean;
+}
+
+/** Lists configured key files that are readable by group or others (Unix only). */
+export async function auditKeyPermissions(): Promise<KeyPermIssue[]> {
+  return invoke("audit_key_permissions");
+}
+
+/** Restricts a private key file to mode 0600 and returns its normalized path. */
+export async function fixKeyPermissions(path: string): Promise<string> {
+  return invoke("fix_key_permissions", { path });
+}
+
+/**
+ * Points a tunnel at a new private key. The key is saved only when a test login with it
+ * succeeds; otherwise the old key is kept and the login error is thrown.
+ */
+export async function rotateTunnelKey(
+  id: string,
+  newKeyPath: string,
+  passphrase?: string,
+): Promise<void> {
+  return invoke("rotate_tunnel_key", { id, newKeyPath, passphrase: passphrase ?? null });
+}
+
+/** Saves the auth method a tunnel fell back to (see TunnelStatusResponse.auth_fallback) as its configured one. */
+export async function applyAuthFallback(id: string): Promise<void> {
+  return invoke("apply_auth_fallback", { id });
+}
+
+/** Fields to change in batchUpdate; omitted fields are left as they are. */
+export interface TunnelPatch {
+  ssh_username?: string;
+  ssh_port?: number;
+  /** Switches the tunnels to key auth and clears saved passwords. */
+  ssh_key_path?: string;
+}
+
+export interface BatchUpdateResult {
+  id: string;
+  ok: boolean;
+  error: string | null;
+}
+
+/**
+ * Applies the same SSH username, port or key to several tunnels in one transaction.
+ * Returns one result per id; unknown ids are reported instead of failing the batch.
+ */
+export async function batchUpdate(
+  ids: string[],
+  patch: TunnelPatch,
+): Promise<BatchUpdateResult[]> {
+  return invoke("batch_update", { ids, patch });
+}
+
+/** Groups of tunnel ids that share the same connection and forward settings. */
+export async function findDuplicateTunnels(): Promise<string[][]> {
+  return invoke("find_duplicate_tunnels");
+}
+
+export interface FieldDiff {
+  field: string;
+  // Passwords and failure commands show as "<redacted>" when set
+  a: unknown;
+  b: unknown;
+}
+
+/** Fields whose values differ between two tunnels (the id is ignored). */
+export async function diffTunnels(idA: string, idB: string): Promise<FieldDiff[]> {
+  return invoke("diff_tunnels", { idA, idB });
+}
+
 export async function deleteTunnel(id: string): Promise<void> {
   return invoke("delete_tunnel", { id });
 }
 
-export async function startTunnel(id: string): Promise<void> {
-  return invoke("start_tunnel", { id });
+/**
+ * Starts a tunnel. Outside its schedule the start is rejected with a validation error
+ * unless `overrideSchedule` is set.
+ */
+export async function startTunnel(id: string, overrideSchedule = false): Promise<void> {
+  return invoke("start_tunnel", { id, overrideSchedule });
 }
 
 export async function stopTunnel(id: string): Promise<void> {
   return invoke("stop_tunnel", { id });
 }
 
+/** Ids of tunnels currently in an error state. */
+export async function getFailedTunnels(): Promise<string[]> {
+  return invoke("get_failed_tunnels");
+}
+
+/**
+ * Dismisses a tunnel's error state: the tunnel is stopped and any pending
+ * auto-reconnect is cancelled.
+ */
+export async function clearFailedTunnel(id: string): Promise<void> {
+  return invoke("clear_failed_tunnel", { id });
+}
+
+/** Dismisses every failed tunnel; resolves to the number cleared. */
+export async function clearAllFailed(): Promise<number> {
+  return invoke("clear_all_failed");
+}
+
+export interface ShutdownReport {
+  id: string;
+  actor_exited: boolean;
+  metrics_task_exited: boolean;
+  /** Tasks still running when the deadline passed. */
+  monitors_left: number;
+  accept_loops_left: number;
+  connections_left: number;
+  elapsed_ms: number;
+  clean: boolean;
+}
+
+/**
+ * Developer tool: removes the tunnel's actor and reports whether every task it spawned
+ * exited within a few seconds. The tunnel can be started again afterwards.
+ */
+export async function shutdownTunnelDebug(id: string): Promise<ShutdownReport> {
+  return invoke("shutdown_tunnel_debug", { id });
+}
+
+/** Equivalent `ssh` command line for the tunnel; passwords are never included. */
+export async function toSshCommand(id: string): Promise<string> {
+  return invoke("to_ssh_command", { id });
+}
+
+export interface ForwardPreview {
+  id: string;
+  name: string;
+  backend: "control_master" | "russh" | "system_ssh";
+  // Why the default russh backend is not used, or a ~/.ssh/config proxy that russh ignores
+  backend_reason: string | null;
+  // Host actually dialed; the system ssh expands ~/.ssh/config aliases
+  ssh_host: string;
+  ssh_port: number;
+  // Empty when the system ssh goes through a ProxyJump/ProxyCommand from ~/.ssh/config
+  ssh_addresses: string[];
+  direction: "local" | "remote" | "dynamic";
+  // Local listen address, or where remote forwards connect back to
+  local_addr: string;
+  // Forward target, or the listen address on the SSH host for remote forwards; null for SOCKS
+  target: string | null;
+  target_source: "configured" | "resolver" | "container_ip" | "published_port" | "socks_client";
+  // One "local range -> remote range" line per port_map entry
+  port_map: string[];
+}
+
+/**
+ * Computes what starting the tunnel would forward without binding anything. Docker mode and
+ * target resolver commands open a short-lived SSH session. Rejects with the first error found.
+ */
+export async function previewTunnel(id: string): Promise<ForwardPreview> {
+  return invoke("preview_tunnel", { id });
+}
+
+/** In-session round-trip time in ms measured by the health monitor, null when unknown. */
+export async function getTunnelLatency(id: string): Promise<number | null> {
+  return invoke("get_tunnel_latency", { id });
+}
+
+export interface TunnelCounters {
+  tunnel_id: string;
+  // Connections accepted over the tunnel's lifetime
+  connections: number;
+  // Connections that were rejected (queue full/timeout) or failed (e.g. target unreachable)
+  connection_errors: number;
+}
+
+export async function getTunnelCounters(id: string): Promise<TunnelCounters> {
+  return invoke("get_tunnel_counters", { id });
+}
+
+/** Local addresses bound by running tunnels as [tunnel id, "host:port"] pairs. */
+export async function getActiveBindings(): Promise<[string, string][]> {
+  return invoke("get_active_bindings");
+}
+
+/** Stops recording traffic history for a running tunnel and starts a fresh measurement window. */
+export async function pauseStats(id: string): Promise<void> {
+  return invoke("pause_stats", { id });
+}
+
+/** Resumes recording traffic history; the window totals stay readable until the next pause. */
+export async function resumeStats(id: string): Promise<void> {
+  return invoke("resume_stats", { id });
+}
+
+/**
+ * Exports per-minute traffic history as CSV to `destPath` (all tunnels when `id` is omitted).
+ * Returns the number of data rows written.
+ */
+export async function exportMetricsCsv(destPath: string, id?: string): Promise<number> {
+  return invoke("export_metrics_csv", { id: id ?? null, destPath });
+}
+
 export interface TunnelStatusResponse {
   is_running: boolean;
   ping: number | null;
+  // "listening" while the port is bound but no forward has reached the target yet,
+  // "running" once one has (or the fail-fast probe passed)
   state?: string;
   send_bytes?: number;
+  // Effective local port, differs from the config after a remap
+  local_port?: number | null;
   recv_bytes?: number;
+  // Set by pauseStats: lifetime totals stop growing while the window counts
+  stats_paused?: boolean;
+  // Traffic since the last pauseStats, kept after resumeStats until the next pause
+  window_send_bytes?: number;
+  window_recv_bytes?: number;
+  active_connections?: number;
+  // Connections waiting for a free slot when max_connections is reached
+  queued_connections?: number;
+  // Connections this session from non-loopback addresses to a loopback-only listener; each
+  // burst is also logged as an "unexpected_source" event
+  unexpected_sources?: number;
+  // Set when auth_fallback kicked in, e.g. "password" or "key /home/me/.ssh/id_ed25519"
+  auth_fallback?: string | null;
 }
 
 export async function getTunnelStatus(id: string): Promise<TunnelStatusResponse> {
   return invoke("get_tunnel_status", { id });
 }
 
+/** Statuses of all loaded tunnels keyed by id, in one call. Tunnels that were never started are absent. */
+export async function getAllTunnelStatuses(): Promise<Record<string, TunnelStatusResponse>> {
+  return invoke("get_all_tunnel_statuses");
+}
+
 export interface AppSettings {
   launch_at_login: boolean;
+  // Superseded by close_button_action, kept so older settings still load
   minimize_to_tray_on_close: boolean;
   keep_alive_interval: number;
   default_ssh_key: string | null;
@@ -87,6 +455,22 @@ export interface AppSettings {
   auto_reconnect: boolean;
   theme: string;
   language: string;
+  // Local WebSocket metrics server for external dashboards
+  metrics_ws_enabled?: boolean;
+  metrics_ws_addr?: string;
+  metrics_ws_allow_remote?: boolean;
+  // What a left click on the tray icon does
+  tray_left_click?: "show" | "toggle" | "menu" | "none";
+  // Persisted log level, null uses the build default
+  log_level?: LogLevel | null;
+  // Attempts for resolving the SSH host on connect (1-10), 1 disables the retry
+  dns_retry_attempts?: number;
+  // Whole command lines runDiagnosticCommand may run on an SSH host
+  diagnostic_commands?: string[];
+  // Seconds SSH keepalives must keep failing before a tunnel is marked failed (0-300, 0 = immediate)
+  failure_grace_secs?: number;
+  // Window close button: hide to the tray, quit, or emit `close-requested` to ask each time
+  close_button_action?: "minimize" | "quit" | "ask";
 }
 
 export async function getSettings(): Promise<AppSettings> {
@@ -97,6 +481,537 @@ export async function saveSettings(settings: AppSettings): Promise<void> {
   return invoke("save_settings", { settings });
 }
 
+export interface AutostartState {
+  /** Whether launch at login is actually registered with the OS. */
+  enabled: boolean;
+  /** The stored launch_at_login setting. */
+  setting: boolean;
+  mismatch: boolean;
+}
+
+/** Reads the real autostart registration; `mismatch` is set when it disagrees with settings. */
+export async function getAutostartState(): Promise<AutostartState> {
+  return invoke("get_autostart_state");
+}
+
+/** Registers or removes launch at login and stores the same value in settings. */
+export async function setAutostart(enabled: boolean): Promise<AutostartState> {
+  return invoke("set_autostart", { enabled });
+}
+
+export type LogLevel = "off" | "error" | "warn" | "info" | "debug" | "trace";
+
+export async function getLogLevel(): Promise<LogLevel> {
+  return invoke("get_log_level");
+}
+
+/**
+ * Changes the log level of the running app, including russh session logs. With `persist`
+ * the level is also saved to settings and used after a restart.
+ */
+export async function setLogLevel(level: LogLevel, persist = false): Promise<LogLevel> {
+  return invoke("set_log_level", { level, persist });
+}
+
+/** Payload of the `quit-requested` event: tunnels that were carrying traffic. */
+export interface BusyTunnel {
+  id: string;
+  name: string;
+  active_connections: number;
+}
+
+/**
+ * Answers a `close-requested` prompt: quit (still confirmed via `quit-requested` while tunnels
+ * carry traffic) or hide the window to the tray.
+ */
+export async function answerCloseRequest(quit: boolean): Promise<void> {
+  return invoke("answer_close_request", { quit });
+}
+
+/** Quits immediately; used after the user confirms a `quit-requested` prompt. */
+export async function quitApp(): Promise<void> {
+  return invoke("quit_app");
+}
+
+export interface AppInfo {
+  version: string;
+  // Null when built outside a git checkout
+  git_commit: string | null;
+  tauri_version: string;
+  russh_version: string | null;
+  ssh_backend: "russh";
+  os: string;
+  arch: string;
+  debug_build: boolean;
+}
+
+/** Version and build details of the running app, for bug reports. */
+export async function getAppInfo(): Promise<AppInfo> {
+  return invoke("get_app_info");
+}
+
+export interface MaintenanceStatus {
+  enabled: boolean;
+  // Unix millis when maintenance mode ends by itself, null until turned off
+  until: number | null;
+}
+
+export async function getMaintenanceMode(): Promise<MaintenanceStatus> {
+  return invoke("get_maintenance_mode");
+}
+
+/**
+ * Turns maintenance mode on or off. While on, failure hooks are skipped and the tray shows a
+ * neutral status; tunnels still reconnect. With durationMinutes it turns itself off afterwards.
+ */
+export async function setMaintenanceMode(
+  enabled: boolean,
+  durationMinutes?: number,
+): Promise<MaintenanceStatus> {
+  return invoke("set_maintenance_mode", { enabled, durationMinutes: durationMinutes ?? null });
+}
+
 export async function fetchContainers(params: SshParams): Promise<DockerContainer[]> {
   return invoke("fetch_containers", { params });
 }
+
+export interface ContainerPort {
+  // Null when the port is exposed but not published on the SSH host
+  ip: string | null;
+  host_port: number | null;
+  container_port: number;
+  protocol: "tcp" | "udp" | "sctp";
+}
+
+/** Ports of a running container (by id, id prefix or name), parsed from `docker ps`; ranges are expanded. */
+export async function getContainerPortsStructured(
+  params: SshParams,
+  id: string,
+): Promise<ContainerPort[]> {
+  return invoke("get_container_ports_structured", { params, id });
+}
+
+export interface ListeningPort {
+  address: string;
+  port: number;
+  // Only shown when the SSH user may see the owning process
+  process: string | null;
+  pid: number | null;
+}
+
+/** Lists TCP ports listening on the SSH host, to help pick a target port. */
+export async function fetchRemotePorts(params: SshParams): Promise<ListeningPort[]> {
+  return invoke("fetch_remote_ports", { params });
+}
+
+export interface TargetReachability {
+  reachable: boolean;
+  // "nc" or "bash", whichever the SSH host had available
+  method: string;
+}
+
+/** Checks from the SSH host whether targetHost:targetPort accepts connections. */
+export async function checkTargetReachable(
+  params: SshParams,
+  targetHost: string,
+  targetPort: number,
+): Promise<TargetReachability> {
+  return invoke("check_target_reachable", { params, targetHost, targetPort });
+}
+
+export interface PublishedPort {
+  // "0.0.0.0" / "::" mean every address on the host
+  host_ip: string;
+  host_port: number;
+}
+
+export interface ContainerPortStatus {
+  container_port: number;
+  // Empty when the port is only reachable on the container network
+  published: PublishedPort[];
+  recommended: "container_ip" | "published_port";
+}
+
+/** Checks whether a container port is published on the SSH host, and which forward mode suits it. */
+export async function checkContainerPort(
+  params: SshParams,
+  containerName: string,
+  containerPort: number,
+): Promise<ContainerPortStatus> {
+  return invoke("check_container_port", { params, containerName, containerPort });
+}
+
+/**
+ * Aborts every in-flight container/port discovery and closes the SSH session reused
+ * between discovery calls. Returns how many operations were cancelled.
+ */
+export async function cancelDiscovery(): Promise<number> {
+  return invoke("cancel_discovery");
+}
+
+export async function captureTrace(params: SshParams): Promise<string> {
+  return invoke("capture_trace", { params });
+}
+
+export interface PathMtu {
+  host: string;
+  mtu: number;
+  probes: number;
+}
+
+/**
+ * Estimates the IPv4 path MTU with don't-fragment pings from the SSH host, towards
+ * `targetHost` or back to this machine. Takes up to 30s, abort with `cancelDiscovery()`.
+ */
+export async function probePathMtu(params: SshParams, targetHost?: string): Promise<PathMtu> {
+  return invoke("probe_path_mtu", { params, targetHost: targetHost ?? null });
+}
+
+/**
+ * Auth methods the server accepts for `user`, e.g. ["publickey", "password"].
+ * Empty when the server doesn't reveal them; ["none"] when no auth is required.
+ */
+export async function getAuthMethods(host: string, port: number, user: string): Promise<string[]> {
+  return invoke("get_auth_methods", { host, port, user });
+}
+
+export interface HostKeyRecord {
+  host: string;
+  port: number;
+  key_type: string;
+  // "SHA256:..."
+  fingerprint: string;
+  /** Unix timestamps in milliseconds */
+  first_seen: number;
+  last_seen: number;
+}
+
+/**
+ * Every host key fingerprint seen for host:port, oldest first. A change from the previous
+ * fingerprint of the same key type also shows up in the activity feed as host_key_changed.
+ */
+export async function getHostKeyHistory(host: string, port: number): Promise<HostKeyRecord[]> {
+  return invoke("get_host_key_history", { host, port });
+}
+
+export interface LinkQuality {
+  host: string;
+  port: number;
+  samples: number;
+  failures: number;
+  // 0.0 - 1.0
+  failure_rate: number;
+  // Latencies in milliseconds, null when every probe failed
+  min_ms: number | null;
+  avg_ms: number | null;
+  max_ms: number | null;
+  // Standard deviation of the latencies
+  jitter_ms: number | null;
+  // 0 - 100, failures weigh most, then jitter, then latency
+  score: number;
+  rating: "excellent" | "good" | "fair" | "poor";
+}
+
+/**
+ * Opens `samples` TCP connections (default 10) to the SSH port and reports latency, jitter and
+ * failure rate, to tell a slow-but-stable link from an unstable one.
+ */
+export async function measureLinkQuality(
+  host: string,
+  port: number,
+  samples?: number,
+): Promise<LinkQuality> {
+  return invoke("measure_link_quality", { host, port, samples: samples ?? null });
+}
+
+export interface ChannelLatency {
+  samples: number;
+  failures: number;
+  min_ms: number | null;
+  avg_ms: number | null;
+  max_ms: number | null;
+}
+
+export interface OverheadReport {
+  target: string;
+  // TCP connects from this machine to the target (or the same port on the SSH host when the
+  // target is loopback there); falls back to the SSH port when the target isn't reachable directly
+  direct: LinkQuality;
+  // host:port the direct side actually measured
+  direct_endpoint: string;
+  // True when the target wasn't reachable directly and direct measured the SSH port instead
+  direct_fallback: boolean;
+  // Time to open a direct-tcpip channel to the target through the SSH host
+  tunnel: ChannelLatency;
+  // tunnel.avg_ms - direct.avg_ms, null when either side had no successful sample or when
+  // direct fell back to the SSH port
+  latency_overhead_ms: number | null;
+  // Download rate of the SSH session in bytes/s, null when the host can't run commands
+  tunnel_throughput: number | null;
+  // Buffer size in bytes used for the throughput sample: io_buffer_size unless overridden
+  io_buffer_size: number;
+}
+
+/**
+ * Compares latency through the tunnel's SSH host against a direct connection and measures the
+ * session throughput, using the tunnel's saved settings; it doesn't need to be running.
+ * Pass ioBufferSize to measure with a different read buffer than the saved io_buffer_size.
+ * Limited to about a minute and cancelled by cancelDiscovery.
+ */
+export async function measureTunnelOverhead(
+  id: string,
+  samples?: number,
+  ioBufferSize?: number,
+): Promise<OverheadReport> {
+  return invoke("measure_tunnel_overhead", {
+    id,
+    samples: samples ?? null,
+    ioBufferSize: ioBufferSize ?? null,
+  });
+}
+
+/**
+ * Every address (IPv4 and IPv6) the host name resolves to. Rejects with code "not_found"
+ * when the name does not exist and "network" when the lookup times out or fails, or the name
+ * resolves to no addresses.
+ */
+export async function resolveSshHost(host: string): Promise<string[]> {
+  return invoke("resolve_ssh_host", { host });
+}
+
+export interface ExecOutput {
+  stdout: string;
+  stderr: string;
+  exit_code: number;
+}
+
+/**
+ * Connects with the tunnel's SSH settings, runs its `check_command` once and disconnects, without
+ * starting the tunnel. `timeoutSecs` (1-300, default 30) covers both the connect and the command.
+ */
+export async function runCheckCommand(id: string, timeoutSecs?: number): Promise<ExecOutput> {
+  return invoke("run_check_command", { id, timeoutSecs });
+}
+
+/**
+ * Runs one command from the `diagnostic_commands` allow-list on the SSH host. The command
+ * must match an allow-list line exactly; anything else is rejected with a validation error.
+ */
+export async function runDiagnosticCommand(params: SshParams, command: string): Promise<ExecOutput> {
+  return invoke("run_diagnostic_command", { params, command });
+}
+
+// Payload of the "remote-tail" event; "ended" is always the last event of a tail
+export type RemoteTailEvent = { id: number } & (
+  | { kind: "line"; line: string }
+  // tail's stderr, e.g. the file was truncated or replaced after rotation
+  | { kind: "notice"; message: string }
+  // Lines skipped because the file grew faster than 200 lines/s
+  | { kind: "dropped"; count: number }
+  | { kind: "ended"; error: string | null }
+);
+
+/**
+ * Follows a remote file with `tail -n <lines> -F` over its own SSH session. Output arrives as
+ * "remote-tail" events tagged with the returned id; call stopRemoteTail to end it.
+ */
+export async function tailRemoteFile(params: SshParams, path: string, lines: number): Promise<number> {
+  return invoke("tail_remote_file", { params, path, lines });
+}
+
+/** Stops a tail started by tailRemoteFile. Resolves false when it has already ended. */
+export async function stopRemoteTail(id: number): Promise<boolean> {
+  return invoke("stop_remote_tail", { id });
+}
+
+/**
+ * Tries publickey auth with only this key. Resolves false when the server rejects it;
+ * connection problems and unreadable keys reject with a CommandError instead.
+ */
+export async function isKeyAuthorized(
+  host: string,
+  port: number,
+  user: string,
+  keyPath: string,
+): Promise<boolean> {
+  return invoke("is_key_authorized", { host, port, user, keyPath });
+}
+
+export interface TimeSkew {
+  // Remote clock minus local clock; positive means the SSH host is ahead
+  offset_secs: number;
+  round_trip_ms: number;
+  // True beyond 30s, enough to break SSH certificates and TLS through the tunnel
+  exceeds_threshold: boolean;
+}
+
+/** Compares the SSH host clock (`date +%s`) with the local clock. */
+export async function checkTimeSkew(params: SshParams): Promise<TimeSkew> {
+  return invoke("check_time_skew", { params });
+}
+
+export interface ConnectionTest {
+  id: string;
+  name: string;
+  ok: boolean;
+  elapsed_ms: number;
+  error: string | null;
+}
+
+/** Connects and authenticates every tunnel's SSH host (no forwarding), in list order. */
+export async function testAllTunnels(): Promise<ConnectionTest[]> {
+  return invoke("test_all_tunnels");
+}
+
+/**
+ * Writes a JSON support bundle to `destPath`: settings, tunnel configs, recent events and logs,
+ * plus a testAllTunnels run. Passwords, key paths and hook commands are redacted.
+ * Returns the file size in bytes.
+ */
+export async function createDiagnosticBundle(destPath: string): Promise<number> {
+  return invoke("create_diagnostic_bundle", { destPath });
+}
+
+/**
+ * Writes a point-in-time JSON snapshot of every tunnel's state, traffic, latency, connections
+ * and uptime to `destPath`, for before/after comparisons. Returns the file size in bytes.
+ */
+export async function snapshotMetrics(destPath: string): Promise<number> {
+  return invoke("snapshot_metrics", { destPath });
+}
+
+export interface PortOwner {
+  pid: number;
+  name: string;
+}
+
+/** Best-effort lookup of the process listening on a local port, null when unknown. */
+export async function whoHasPort(port: number): Promise<PortOwner | null> {
+  return invoke("who_has_port", { port });
+}
+
+/**
+ * Writes a consistent copy of the database to `destPath`, returns its size in bytes.
+ * Writes from running tunnels wait while a backup or compaction is in progress.
+ */
+export async function backupDatabase(destPath: string): Promise<number> {
+  return invoke("backup_database", { destPath });
+}
+
+/** Reclaims free space in the database, returns the new file size in bytes. */
+export async function compactDatabase(): Promise<number> {
+  return invoke("compact_database");
+}
+
+/**
+ * Imports host keys from an OpenSSH known_hosts file (defaults to ~/.ssh/known_hosts),
+ * returns how many new entries were added. Hashed host names are kept as-is.
+ */
+export async function importKnownHosts(path?: string): Promise<number> {
+  return invoke("import_known_hosts", { path: path ?? null });
+}
+
+/** Appends pinned host keys to an OpenSSH known_hosts file, returns how many lines were written. */
+export async function exportKnownHosts(destPath?: string): Promise<number> {
+  return invoke("export_known_hosts", { destPath: destPath ?? null });
+}
+
+/** Sent as "host-key-prompt" when strict checking meets a host missing from ~/.ssh/known_hosts. */
+export interface HostKeyPrompt {
+  host: string;
+  port: number;
+  key_type: string;
+  /** SHA256 fingerprint */
+  fingerprint: string;
+}
+
+/**
+ * Answers a host-key-prompt; approved keys are appended to ~/.ssh/known_hosts.
+ * Returns false when no connection is waiting any more (the prompt timed out).
+ */
+export async function answerHostKey(host: string, port: number, approved: boolean): Promise<boolean> {
+  return invoke("answer_host_key", { host, port, approved });
+}
+
+/** Adds any columns missing from the tunnels table, returns the names it added. */
+export async function repairDatabaseSchema(): Promise<string[]> {
+  return invoke("repair_database_schema");
+}
+
+/**
+ * Adds missing settings columns and resets NULL ones to their defaults, keeping every other
+ * saved setting, then reloads. Returns the repaired column names (empty when the row is intact).
+ */
+export async function repairSettings(): Promise<string[]> {
+  return invoke("repair_settings");
+}
+
+export interface ActivityEntry {
+  id: number;
+  tunnel_id: string;
+  // null when the tunnel has since been deleted
+  tunnel_name: string | null;
+  // host_key_changed is a host-level event, its tunnel_id is "host:port"
+  kind:
+    | "started"
+    | "stopped"
+    | "failed"
+    | "reconnected"
+    | "high_traffic"
+    | "host_key_changed"
+    | "unexpected_source"
+    | "auth_fallback";
+  description: string;
+  /** Unix timestamp in milliseconds */
+  timestamp: number;
+}
+
+/** Notable events across all tunnels, newest first. */
+export async function getActivityFeed(limit?: number): Promise<ActivityEntry[]> {
+  return invoke("get_activity_feed", { limit });
+}
+
+export interface TunnelUsage {
+  tunnel_id: string;
+  /** Null for tunnels that have been deleted. */
+  tunnel_name: string | null;
+  tx_bytes: number;
+  rx_bytes: number;
+}
+
+export interface UsageSummary {
+  since: number;
+  until: number;
+  tx_bytes: number;
+  rx_bytes: number;
+  /** Per-tunnel totals, largest first. */
+  tunnels: TunnelUsage[];
+}
+
+export type UsagePreset = "today" | "week" | "month";
+
+/**
+ * Data transferred by all tunnels in a period, from the per-minute traffic history (kept for
+ * 30 days). Pass a preset, or a start time in unix millis with an optional end.
+ */
+export async function getUsageSummary(
+  period: UsagePreset | { since: number; until?: number },
+): Promise<UsageSummary> {
+  return typeof period === "string"
+    ? invoke("get_usage_summary", { preset: period })
+    : invoke("get_usage_summary", { since: period.since, until: period.until ?? null });
+}
+
+// The "tunnel-log" event carries an array of these, batched at most every 100ms
+export interface LogPayload {
+  id: string;
+  line: string;
+  level: string;
+  /** Unix timestamp in milliseconds */
+  timestamp: number;
+}
+
+/** Recent buffered log lines, oldest first. Omit `id` to merge all tunnels. */
+export async function getRecentLogs(id: string | null = null, limit?: number): Promise<LogPayload[]> {
+  return invoke("get_recent_logs", { id, limit });
+}